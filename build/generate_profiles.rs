@@ -1,26 +1,24 @@
 use std::{
-    fs, io,
+    env, fs, io,
     path::{Path, PathBuf},
 };
 
 use serde_json::{Map, Value};
 
-static PRESETS_PATH: &'static str = "profiles";
-static PRESETS_OUT: &'static str = "./src/generate/profiles.rs";
+static PRESETS_PATH: &str = "profiles";
+static PRESETS_OUT: &str = "profiles.rs";
 
 pub fn generate_presets() -> io::Result<()> {
-    println!("cargo::rerun-if-changed={:?}", Path::new(PRESETS_OUT));
+    println!("cargo::rerun-if-changed={PRESETS_PATH}");
 
-    let presets_out = Path::new(PRESETS_OUT).parent().unwrap();
-    fs::create_dir_all(presets_out).unwrap();
+    let presets_out = Path::new(&env::var("OUT_DIR").unwrap()).join(PRESETS_OUT);
 
     let mut generated = String::from(
-        r#"/// THIS FILE IS GENERATED IN `build/generate_presets.rs.
-/// DON't MODIFIED THIS FILE AND DON'T PUSH IN GIT.
-use std::collections::HashMap;
+        r#"// THIS FILE IS GENERATED IN `build/generate_profiles.rs`.
+// DON'T MODIFY THIS FILE, EDIT THE PRESETS IN `profiles/` INSTEAD.
 
-pub fn get_profiles() -> HashMap<String, Profile> {
-    let mut map: HashMap<String, Profile> = HashMap::new();
+pub(crate) fn get_profiles() -> HashMap<String, Preset> {
+    let mut map: HashMap<String, Preset> = HashMap::new();
 
 "#,
     );
@@ -28,20 +26,26 @@ pub fn get_profiles() -> HashMap<String, Profile> {
     let root_dir = PathBuf::from(PRESETS_PATH);
     let mut dirs_stack: Vec<PathBuf> = vec![root_dir.clone()];
 
-    while dirs_stack.len() > 0 {
-        let last_dir = dirs_stack.last().unwrap().clone();
-        dirs_stack.pop();
+    while let Some(last_dir) = dirs_stack.pop() {
+        let mut entries = fs::read_dir(&last_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        entries.sort();
 
-        for entry in fs::read_dir(&last_dir).unwrap() {
-            let entry = entry.unwrap();
+        for entry in entries {
+            if entry.is_dir() {
+                dirs_stack.push(entry);
+                continue;
+            }
 
-            if entry.metadata().unwrap().is_dir() {
-                dirs_stack.push(entry.path().to_path_buf());
+            if entry.extension().and_then(|ext| ext.to_str()) != Some("json") {
                 continue;
             }
 
-            let entry_name = last_dir.file_name().unwrap().to_str().unwrap().to_string();
-            let profile_str = fs::read_to_string(entry.path()).unwrap();
+            let category = last_dir.file_name().unwrap().to_str().unwrap().to_string();
+            let id = entry.file_stem().unwrap().to_str().unwrap().to_string();
+            let profile_str = fs::read_to_string(&entry).unwrap();
 
             let json: Map<String, Value> = serde_json::from_str(&profile_str).unwrap();
 
@@ -52,15 +56,15 @@ pub fn get_profiles() -> HashMap<String, Profile> {
             let display_aspect = json.get("display_aspect").unwrap().as_object().unwrap();
             let explicit_type = json.get("explicit_type").unwrap().as_str().unwrap();
             let render_type = json.get("render_type").unwrap().as_str().unwrap();
-            let color_space = json.get("color_space").unwrap().as_str().unwrap();
+            let color = json.get("color").unwrap().as_object().unwrap();
 
             generated.push_str("    {\n");
             generated.push_str("        let profile = ProfileBuilder::new()\n");
 
             generated.push_str(&format!(
                 r#"            .set_frame(geometry::Frame {{
-                width: {} as f32,
-                height: {} as f32,
+                width: {}.0,
+                height: {}.0,
             }})"#,
                 frame.get("width").unwrap().as_i64().unwrap(),
                 frame.get("height").unwrap().as_i64().unwrap(),
@@ -69,8 +73,8 @@ pub fn get_profiles() -> HashMap<String, Profile> {
             generated.push_str(&format!(
                 r#"
             .set_frame_rate(Ratio {{
-                number: {} as f32,
-                denominator: {} as f32,
+                number: {},
+                denominator: {},
             }})"#,
                 frame_rate.get("number").unwrap().as_i64().unwrap(),
                 frame_rate.get("denominator").unwrap().as_i64().unwrap(),
@@ -79,8 +83,8 @@ pub fn get_profiles() -> HashMap<String, Profile> {
             generated.push_str(&format!(
                 r#"
             .set_sample_aspect(Ratio {{
-                number: {} as f32,
-                denominator: {} as f32,
+                number: {},
+                denominator: {},
             }})"#,
                 sample_aspect.get("number").unwrap().as_i64().unwrap(),
                 sample_aspect.get("denominator").unwrap().as_i64().unwrap(),
@@ -89,8 +93,8 @@ pub fn get_profiles() -> HashMap<String, Profile> {
             generated.push_str(&format!(
                 r#"
             .set_display_aspect(Ratio {{
-                number: {} as f32,
-                denominator: {} as f32,
+                number: {},
+                denominator: {},
             }})"#,
                 display_aspect.get("number").unwrap().as_i64().unwrap(),
                 display_aspect.get("denominator").unwrap().as_i64().unwrap(),
@@ -98,8 +102,16 @@ pub fn get_profiles() -> HashMap<String, Profile> {
 
             generated.push_str(&format!(
                 r#"
-            .set_color_space(ColorSpace::{})"#,
-                color_space,
+            .set_color(ColorInfo {{
+                primaries: ColorPrimaries::{},
+                transfer: TransferCharacteristics::{},
+                matrix: ColorSpace::{},
+                range: ColorRange::{},
+            }})"#,
+                color.get("primaries").unwrap().as_str().unwrap(),
+                color.get("transfer").unwrap().as_str().unwrap(),
+                color.get("matrix").unwrap().as_str().unwrap(),
+                color.get("range").unwrap().as_str().unwrap(),
             ));
 
             generated.push_str(&format!(
@@ -116,7 +128,17 @@ pub fn get_profiles() -> HashMap<String, Profile> {
 
             generated.push_str("\n            .build();");
             generated.push_str(&format!(
-                "\n        map.insert(\"{entry_name} - {description}\".to_string(), profile);\n"
+                r#"
+        map.insert(
+            {id:?}.to_string(),
+            Preset {{
+                id: {id:?}.to_string(),
+                category: {category:?}.to_string(),
+                description: {description:?}.to_string(),
+                profile,
+            }},
+        );
+"#
             ));
             generated.push_str("    }\n\n");
         }
@@ -124,7 +146,7 @@ pub fn get_profiles() -> HashMap<String, Profile> {
 
     generated.push_str("    map\n}");
 
-    fs::write(PRESETS_OUT, generated).unwrap();
+    fs::write(presets_out, generated).unwrap();
 
     Ok(())
 }
@@ -0,0 +1,362 @@
+//! Sample format and layout conversion of audio [`Buffer`]s.
+//!
+//! Integer formats are scaled to full range, so `i16::MIN` maps to `-1.0`
+//! and back. Conversion to integers rounds to nearest and clips, optionally
+//! adding triangular dither when resolution is lost. Streams converted
+//! block by block use one [`Converter`], so the dither noise continues
+//! instead of repeating every block.
+
+use super::{AudioBuffer, AudioError, SampleFormat, SampleLayout};
+use crate::structures::buffer::{Buffer, BufferElement, ElementType};
+
+/// Noise added before reducing the bit depth.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Dither {
+    #[default]
+    None,
+    /// Triangular PDF noise of one LSB peak.
+    Triangular,
+}
+
+impl SampleFormat {
+    /// Full scale value of the integer formats.
+    fn scale(&self) -> f64 {
+        match self {
+            SampleFormat::U8 => 128.0,
+            SampleFormat::S16 => 32768.0,
+            SampleFormat::S32 => 2147483648.0,
+            SampleFormat::F32 => 1.0,
+        }
+    }
+
+    /// Significant bits, `24` for the float mantissa.
+    fn bits(&self) -> u32 {
+        match self {
+            SampleFormat::U8 => 8,
+            SampleFormat::S16 => 16,
+            SampleFormat::S32 => 32,
+            SampleFormat::F32 => 24,
+        }
+    }
+}
+
+/// Deterministic noise source, so renders are reproducible.
+#[derive(Debug, Clone)]
+struct Noise(u32);
+
+impl Noise {
+    const SEED: u32 = 0x9e37_79b9;
+
+    /// Noise of the stream at sample `position`.
+    fn at(position: u64) -> Self {
+        let mixed = (position ^ 0x9e37_79b9_7f4a_7c15).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        // Xorshift never leaves zero.
+        Self((mixed >> 32) as u32 | 1)
+    }
+
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f64 / u32::MAX as f64
+    }
+
+    fn triangular(&mut self) -> f64 {
+        self.next() - self.next()
+    }
+}
+
+fn to_normalized(buffer: &Buffer) -> Result<Vec<f64>, AudioError> {
+    fn normalize<T: BufferElement>(buffer: &Buffer, scale: impl Fn(T) -> f64) -> Vec<f64> {
+        let values = buffer.as_slice::<T>().unwrap_or_default();
+        values.iter().map(|&s| scale(s)).collect()
+    }
+
+    Ok(
+        match SampleFormat::of_buffer(buffer).ok_or(AudioError::UnsupportedBuffer)? {
+            SampleFormat::U8 => normalize(buffer, |s: u8| (s as f64 - 128.0) / 128.0),
+            SampleFormat::S16 => normalize(buffer, |s: i16| s as f64 / 32768.0),
+            SampleFormat::S32 => normalize(buffer, |s: i32| s as f64 / 2147483648.0),
+            SampleFormat::F32 => normalize(buffer, |s: f32| s as f64),
+        },
+    )
+}
+
+/// Sample format conversion of one stream, keeping the dither noise
+/// running from block to block.
+#[derive(Debug, Clone)]
+pub struct Converter {
+    format: SampleFormat,
+    dither: Dither,
+    noise: Noise,
+}
+
+impl Converter {
+    pub fn new(format: SampleFormat, dither: Dither) -> Self {
+        Self {
+            format,
+            dither,
+            noise: Noise(Noise::SEED),
+        }
+    }
+
+    /// Converter whose noise is seeded from sample `position` of the stream,
+    /// so seeking renders the same noise for the same position.
+    pub fn at_position(format: SampleFormat, dither: Dither, position: u64) -> Self {
+        Self {
+            noise: Noise::at(position),
+            ..Self::new(format, dither)
+        }
+    }
+
+    pub fn format(&self) -> SampleFormat {
+        self.format
+    }
+
+    /// Convert all samples of `buffer`, continuing the noise of the
+    /// previous block.
+    pub fn convert(&mut self, buffer: &Buffer) -> Result<Buffer, AudioError> {
+        let source = SampleFormat::of_buffer(buffer).ok_or(AudioError::UnsupportedBuffer)?;
+        if source == self.format {
+            return Ok(buffer.clone());
+        }
+
+        let samples = to_normalized(buffer)?;
+        let scale = self.format.scale();
+        let noise = &mut self.noise;
+        let dither = self.dither == Dither::Triangular && self.format.bits() < source.bits();
+
+        let mut quantize = |sample: f64| {
+            let mut value = sample * scale;
+            if dither {
+                value += noise.triangular();
+            }
+            value.round().clamp(-scale, scale - 1.0)
+        };
+
+        Ok(match self.format {
+            SampleFormat::U8 => Buffer::OwnedU8(
+                samples
+                    .iter()
+                    .map(|&s| (quantize(s) + 128.0) as u8)
+                    .collect(),
+            ),
+            SampleFormat::S16 => {
+                Buffer::OwnedI16(samples.iter().map(|&s| quantize(s) as i16).collect())
+            }
+            SampleFormat::S32 => {
+                Buffer::OwnedI32(samples.iter().map(|&s| quantize(s) as i32).collect())
+            }
+            SampleFormat::F32 => Buffer::OwnedF32(samples.iter().map(|&s| s as f32).collect()),
+        })
+    }
+}
+
+/// Convert all samples of `buffer` to `format`.
+///
+/// Dither noise starts over on every call; convert streams with a
+/// [`Converter`].
+pub fn convert(
+    buffer: &Buffer,
+    format: SampleFormat,
+    dither: Dither,
+) -> Result<Buffer, AudioError> {
+    Converter::new(format, dither).convert(buffer)
+}
+
+fn interleave_slice<T: Copy + Default>(planar: &[T], channels: usize) -> Vec<T> {
+    let samples = planar.len() / channels;
+    let mut interleaved = vec![T::default(); samples * channels];
+
+    for (channel, block) in planar
+        .chunks_exact(samples.max(1))
+        .take(channels)
+        .enumerate()
+    {
+        for (index, &sample) in block.iter().enumerate() {
+            interleaved[index * channels + channel] = sample;
+        }
+    }
+
+    interleaved
+}
+
+fn deinterleave_slice<T: Copy + Default>(interleaved: &[T], channels: usize) -> Vec<T> {
+    let samples = interleaved.len() / channels;
+    let mut planar = vec![T::default(); samples * channels];
+
+    for (index, frame) in interleaved.chunks_exact(channels).enumerate() {
+        for (channel, &sample) in frame.iter().enumerate() {
+            planar[channel * samples + index] = sample;
+        }
+    }
+
+    planar
+}
+
+macro_rules! map_buffer {
+    ($buffer:expr, $values:ident => $map:expr) => {
+        match $buffer.element_type() {
+            ElementType::U8 => Buffer::from(map_buffer!(@slice $buffer, u8, $values => $map)),
+            ElementType::I16 => Buffer::from(map_buffer!(@slice $buffer, i16, $values => $map)),
+            ElementType::U16 => Buffer::from(map_buffer!(@slice $buffer, u16, $values => $map)),
+            ElementType::I32 => Buffer::from(map_buffer!(@slice $buffer, i32, $values => $map)),
+            ElementType::F32 => Buffer::from(map_buffer!(@slice $buffer, f32, $values => $map)),
+        }
+    };
+    (@slice $buffer:expr, $type:ty, $values:ident => $map:expr) => {{
+        let $values = $buffer.as_slice::<$type>().unwrap_or_default();
+        $map
+    }};
+}
+
+/// Planar `L L R R` samples to interleaved `L R L R`.
+///
+/// Trailing samples not filling every channel are dropped.
+pub fn interleave(buffer: &Buffer, channels: usize) -> Buffer {
+    if channels <= 1 {
+        return buffer.clone();
+    }
+    map_buffer!(buffer, values => interleave_slice(values, channels))
+}
+
+/// Interleaved `L R L R` samples to planar `L L R R`.
+pub fn deinterleave(buffer: &Buffer, channels: usize) -> Buffer {
+    if channels <= 1 {
+        return buffer.clone();
+    }
+    map_buffer!(buffer, values => deinterleave_slice(values, channels))
+}
+
+impl AudioBuffer {
+    /// Copy of the samples in use, without spare capacity of the buffer.
+    fn used_buffer(&self) -> Buffer {
+        let len = self.samples * self.channel_count();
+        map_buffer!(&self.buffer, values => values[..len].to_vec())
+    }
+
+    /// Copy with samples converted to `format`.
+    pub fn to_format(&self, format: SampleFormat, dither: Dither) -> Result<Self, AudioError> {
+        self.to_format_with(&mut Converter::new(format, dither))
+    }
+
+    /// Copy with samples converted by `converter`, the next block of its
+    /// stream.
+    pub fn to_format_with(&self, converter: &mut Converter) -> Result<Self, AudioError> {
+        Ok(Self {
+            format: converter.format(),
+            buffer: converter.convert(&self.used_buffer())?,
+            ..*self
+        })
+    }
+
+    /// Copy with samples reordered to `layout`.
+    pub fn to_layout(&self, layout: SampleLayout) -> Self {
+        let buffer = match (self.layout, layout) {
+            (SampleLayout::Planar, SampleLayout::Interleaved) => {
+                interleave(&self.used_buffer(), self.channel_count())
+            }
+            (SampleLayout::Interleaved, SampleLayout::Planar) => {
+                deinterleave(&self.used_buffer(), self.channel_count())
+            }
+            _ => self.used_buffer(),
+        };
+
+        Self {
+            layout,
+            buffer,
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values<T: BufferElement>(buffer: &Buffer) -> Vec<T> {
+        buffer.as_slice::<T>().unwrap().to_vec()
+    }
+
+    #[test]
+    fn integer_formats_round_trip_through_float() {
+        let s16: Vec<i16> = (i16::MIN..=i16::MAX).step_by(7).chain([i16::MAX]).collect();
+        let float = convert(&Buffer::from(s16.clone()), SampleFormat::F32, Dither::None).unwrap();
+        let back = convert(&float, SampleFormat::S16, Dither::None).unwrap();
+        assert_eq!(values::<i16>(&back), s16);
+
+        let u8s: Vec<u8> = (0..=255).collect();
+        let float = convert(&Buffer::from(u8s.clone()), SampleFormat::F32, Dither::None).unwrap();
+        let back = convert(&float, SampleFormat::U8, Dither::None).unwrap();
+        assert_eq!(values::<u8>(&back), u8s);
+
+        // The float mantissa keeps 24 bits of 32 bit samples.
+        let s32: Vec<i32> = vec![i32::MIN, -123_456_789, -1, 0, 1, 987_654_321, i32::MAX];
+        let float = convert(&Buffer::from(s32.clone()), SampleFormat::F32, Dither::None).unwrap();
+        let back = convert(&float, SampleFormat::S32, Dither::None).unwrap();
+        for (a, b) in s32.iter().zip(values::<i32>(&back)) {
+            assert!((*a as i64 - b as i64).abs() <= 1 << 7, "{a} {b}");
+        }
+    }
+
+    #[test]
+    fn widening_is_exact() {
+        let s16: Vec<i16> = vec![i16::MIN, -1, 0, 1, i16::MAX];
+        let s32 = convert(&Buffer::from(s16.clone()), SampleFormat::S32, Dither::None).unwrap();
+        let expected: Vec<i32> = s16.iter().map(|&s| (s as i32) << 16).collect();
+        assert_eq!(values::<i32>(&s32), expected);
+        let back = convert(&s32, SampleFormat::S16, Dither::Triangular).unwrap();
+        assert_eq!(values::<i16>(&back), s16);
+    }
+
+    #[test]
+    fn clips_full_scale() {
+        let float = Buffer::from(vec![-2.0f32, -1.0, 1.0, 2.0]);
+        let s16 = convert(&float, SampleFormat::S16, Dither::None).unwrap();
+        assert_eq!(
+            values::<i16>(&s16),
+            [i16::MIN, i16::MIN, i16::MAX, i16::MAX]
+        );
+    }
+
+    #[test]
+    fn dither_stays_within_one_lsb() {
+        let float = Buffer::from(vec![0.25f32; 4096]);
+        let s16 = convert(&float, SampleFormat::S16, Dither::Triangular).unwrap();
+        let samples = values::<i16>(&s16);
+        assert!(samples.iter().all(|&s| (s as i32 - 8192).abs() <= 1));
+        let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / samples.len() as f64;
+        assert!((mean - 8192.0).abs() < 0.05, "{mean}");
+    }
+
+    #[test]
+    fn converter_continues_noise_across_blocks() {
+        let block = Buffer::from(vec![0.1f32 / 32768.0; 512]);
+        let mut converter = Converter::new(SampleFormat::S16, Dither::Triangular);
+        let first = values::<i16>(&converter.convert(&block).unwrap());
+        let second = values::<i16>(&converter.convert(&block).unwrap());
+        assert_ne!(first, second);
+
+        let seeked = Converter::at_position(SampleFormat::S16, Dither::Triangular, 48_000)
+            .convert(&block)
+            .unwrap();
+        let again = Converter::at_position(SampleFormat::S16, Dither::Triangular, 48_000)
+            .convert(&block)
+            .unwrap();
+        assert_eq!(values::<i16>(&seeked), values::<i16>(&again));
+    }
+
+    #[test]
+    fn interleave_round_trips() {
+        let planar = Buffer::from((0..12i16).collect::<Vec<_>>());
+        let interleaved = interleave(&planar, 3);
+        assert_eq!(
+            values::<i16>(&interleaved),
+            [0, 4, 8, 1, 5, 9, 2, 6, 10, 3, 7, 11]
+        );
+        assert_eq!(
+            values::<i16>(&deinterleave(&interleaved, 3)),
+            values::<i16>(&planar)
+        );
+    }
+}
@@ -0,0 +1,396 @@
+//! PCM audio stored in a [`Buffer`].
+
+pub mod convert;
+pub mod resample;
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    profile::{Profile, Ratio},
+    structures::buffer::{Buffer, BufferElement, ElementType},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioError {
+    /// Sample rate is zero.
+    ZeroSampleRate,
+    /// Layout has no channels.
+    NoChannels,
+    /// Buffer element type is not an audio sample format.
+    UnsupportedBuffer,
+    /// Buffer does not hold all the samples.
+    BufferTooSmall { needed: usize, len: usize },
+    /// Buffer has another number of channels than expected.
+    ChannelMismatch { expected: usize, found: usize },
+    /// Buffer has another sample rate than expected.
+    SampleRateMismatch { expected: u32, found: u32 },
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::ZeroSampleRate => write!(f, "sample rate is zero"),
+            AudioError::NoChannels => write!(f, "channel layout has no channels"),
+            AudioError::UnsupportedBuffer => write!(f, "buffer does not hold audio samples"),
+            AudioError::BufferTooSmall { needed, len } => {
+                write!(f, "buffer has {len} samples, {needed} needed")
+            }
+            AudioError::ChannelMismatch { expected, found } => {
+                write!(f, "expected {expected} channels, found {found}")
+            }
+            AudioError::SampleRateMismatch { expected, found } => {
+                write!(f, "expected {expected} Hz, found {found} Hz")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+/// Sample type, each one maps to a [`Buffer`] variant.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(C)]
+pub enum SampleFormat {
+    /// Unsigned 8-bit, silence at `128`.
+    U8,
+    #[default]
+    S16,
+    S32,
+    /// Float in `-1.0..=1.0`.
+    F32,
+}
+
+impl SampleFormat {
+    pub fn size(&self) -> usize {
+        match self {
+            SampleFormat::U8 => 1,
+            SampleFormat::S16 => 2,
+            SampleFormat::S32 | SampleFormat::F32 => 4,
+        }
+    }
+
+    /// Format of the samples held by `buffer`.
+    pub fn of_buffer(buffer: &Buffer) -> Option<SampleFormat> {
+        match buffer.element_type() {
+            ElementType::U8 => Some(SampleFormat::U8),
+            ElementType::I16 => Some(SampleFormat::S16),
+            ElementType::I32 => Some(SampleFormat::S32),
+            ElementType::F32 => Some(SampleFormat::F32),
+            ElementType::U16 => None,
+        }
+    }
+
+    /// Buffer of `len` silent samples.
+    pub fn silence(&self, len: usize) -> Buffer {
+        match self {
+            SampleFormat::U8 => Buffer::OwnedU8(vec![128; len]),
+            SampleFormat::S16 => Buffer::zeroed::<i16>(len),
+            SampleFormat::S32 => Buffer::zeroed::<i32>(len),
+            SampleFormat::F32 => Buffer::zeroed::<f32>(len),
+        }
+    }
+}
+
+/// Order of samples in the buffer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(C)]
+pub enum SampleLayout {
+    /// `L R L R ...`
+    #[default]
+    Interleaved,
+    /// `L L ... R R ...`, one block per channel.
+    Planar,
+}
+
+/// Speaker position of a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Channel {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    LowFrequency,
+    BackLeft,
+    BackRight,
+    SideLeft,
+    SideRight,
+    /// Channel without speaker position.
+    Discrete(u16),
+}
+
+/// Named channel layouts, channel order follows WAVE/SMPTE conventions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChannelLayout {
+    Mono,
+    #[default]
+    Stereo,
+    /// 5.1: L R C LFE Ls Rs.
+    Surround51,
+    /// 7.1: L R C LFE Lb Rb Ls Rs.
+    Surround71,
+    /// Channels without speaker positions.
+    Discrete(u16),
+}
+
+impl ChannelLayout {
+    pub fn channel_count(&self) -> usize {
+        match self {
+            ChannelLayout::Mono => 1,
+            ChannelLayout::Stereo => 2,
+            ChannelLayout::Surround51 => 6,
+            ChannelLayout::Surround71 => 8,
+            ChannelLayout::Discrete(count) => *count as usize,
+        }
+    }
+
+    pub fn channels(&self) -> Vec<Channel> {
+        use Channel::*;
+
+        match self {
+            ChannelLayout::Mono => vec![FrontCenter],
+            ChannelLayout::Stereo => vec![FrontLeft, FrontRight],
+            ChannelLayout::Surround51 => vec![
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                BackLeft,
+                BackRight,
+            ],
+            ChannelLayout::Surround71 => vec![
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                BackLeft,
+                BackRight,
+                SideLeft,
+                SideRight,
+            ],
+            ChannelLayout::Discrete(count) => (0..*count).map(Discrete).collect(),
+        }
+    }
+
+    /// Named layout for `count` channels, discrete when there is none.
+    pub fn from_count(count: usize) -> ChannelLayout {
+        match count {
+            1 => ChannelLayout::Mono,
+            2 => ChannelLayout::Stereo,
+            6 => ChannelLayout::Surround51,
+            8 => ChannelLayout::Surround71,
+            count => ChannelLayout::Discrete(count as u16),
+        }
+    }
+
+    /// Index of `channel` in the layout.
+    pub fn position(&self, channel: Channel) -> Option<usize> {
+        self.channels().iter().position(|c| *c == channel)
+    }
+}
+
+/// First sample of frame `position` at `sample_rate`.
+///
+/// Rounds to the nearest sample, so 48 kHz at 29.97 fps follows the
+/// `1602 1601 1602 1601 1602` sequence. A frame rate that is not positive
+/// gives `0`.
+pub fn sample_offset(frame_rate: Ratio, sample_rate: u32, position: i64) -> i64 {
+    if frame_rate.denominator == 0 || frame_rate.to_f64() <= 0.0 {
+        return 0;
+    }
+    (Ratio::from_integer(position) * sample_rate as i64 / frame_rate).round()
+}
+
+/// Number of samples per channel in frame `position`, `0` when the frame
+/// rate is not positive.
+pub fn samples_per_frame(frame_rate: Ratio, sample_rate: u32, position: i64) -> usize {
+    (sample_offset(frame_rate, sample_rate, position + 1)
+        - sample_offset(frame_rate, sample_rate, position)) as usize
+}
+
+/// Block of PCM samples.
+#[derive(Clone)]
+pub struct AudioBuffer {
+    format: SampleFormat,
+    layout: SampleLayout,
+    sample_rate: u32,
+    channel_layout: ChannelLayout,
+    samples: usize,
+    buffer: Buffer,
+}
+
+impl AudioBuffer {
+    /// Silent buffer with `samples` per channel.
+    pub fn new(
+        format: SampleFormat,
+        layout: SampleLayout,
+        sample_rate: u32,
+        channel_layout: ChannelLayout,
+        samples: usize,
+    ) -> Result<Self, AudioError> {
+        let buffer = format.silence(samples * channel_layout.channel_count());
+        Self::from_buffer(layout, sample_rate, channel_layout, samples, buffer)
+    }
+
+    /// Silent buffer sized for frame `position` of `profile`.
+    pub fn for_frame(
+        profile: &Profile,
+        position: i64,
+        format: SampleFormat,
+        layout: SampleLayout,
+        sample_rate: u32,
+        channel_layout: ChannelLayout,
+    ) -> Result<Self, AudioError> {
+        if sample_rate == 0 {
+            return Err(AudioError::ZeroSampleRate);
+        }
+        let samples = samples_per_frame(profile.frame_rate, sample_rate, position);
+        Self::new(format, layout, sample_rate, channel_layout, samples)
+    }
+
+    /// Wrap `buffer` holding `samples` per channel.
+    pub fn from_buffer(
+        layout: SampleLayout,
+        sample_rate: u32,
+        channel_layout: ChannelLayout,
+        samples: usize,
+        buffer: Buffer,
+    ) -> Result<Self, AudioError> {
+        if sample_rate == 0 {
+            return Err(AudioError::ZeroSampleRate);
+        }
+        if channel_layout.channel_count() == 0 {
+            return Err(AudioError::NoChannels);
+        }
+
+        let format = SampleFormat::of_buffer(&buffer).ok_or(AudioError::UnsupportedBuffer)?;
+
+        let needed = samples * channel_layout.channel_count();
+        if buffer.len_elems() < needed {
+            return Err(AudioError::BufferTooSmall {
+                needed,
+                len: buffer.len_elems(),
+            });
+        }
+
+        Ok(Self {
+            format,
+            layout,
+            sample_rate,
+            channel_layout,
+            samples,
+            buffer,
+        })
+    }
+
+    pub fn format(&self) -> SampleFormat {
+        self.format
+    }
+
+    pub fn layout(&self) -> SampleLayout {
+        self.layout
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channel_layout(&self) -> ChannelLayout {
+        self.channel_layout
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.channel_layout.channel_count()
+    }
+
+    /// Samples per channel.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Duration in seconds.
+    pub fn duration(&self) -> Ratio {
+        Ratio::new(self.samples as i64, self.sample_rate as i64)
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn buffer_mut(&mut self) -> &mut Buffer {
+        &mut self.buffer
+    }
+
+    pub fn into_buffer(self) -> Buffer {
+        self.buffer
+    }
+
+    /// Make the samples reference counted, so clones share them.
+    pub fn into_shared(self) -> Self {
+        Self {
+            buffer: self.buffer.into_shared(),
+            ..self
+        }
+    }
+
+    /// All samples as `T`, `None` when `T` does not match the format.
+    pub fn data<T: BufferElement>(&self) -> Option<&[T]> {
+        let len = self.samples * self.channel_count();
+        self.buffer.as_slice::<T>()?.get(..len)
+    }
+
+    pub fn data_mut<T: BufferElement>(&mut self) -> Option<&mut [T]> {
+        let len = self.samples * self.channel_count();
+        self.buffer.as_mut_slice::<T>()?.get_mut(..len)
+    }
+
+    /// Samples of one channel of a planar buffer.
+    ///
+    /// Returns `None` for interleaved buffers, mismatching `T` or a
+    /// `channel` outside the layout.
+    pub fn channel<T: BufferElement>(&self, channel: usize) -> Option<&[T]> {
+        let range = self.channel_range(channel)?;
+        self.data::<T>()?.get(range)
+    }
+
+    pub fn channel_mut<T: BufferElement>(&mut self, channel: usize) -> Option<&mut [T]> {
+        let range = self.channel_range(channel)?;
+        self.data_mut::<T>()?.get_mut(range)
+    }
+
+    /// Sample range of `channel` in a planar buffer.
+    fn channel_range(&self, channel: usize) -> Option<std::ops::Range<usize>> {
+        if self.layout != SampleLayout::Planar || channel >= self.channel_count() {
+            return None;
+        }
+        let start = channel * self.samples;
+        Some(start..start + self.samples)
+    }
+
+    /// Sample `index` of `channel` for either layout.
+    ///
+    /// Returns `None` for mismatching `T` or a `channel` or `index` outside
+    /// the buffer.
+    pub fn sample<T: BufferElement>(&self, channel: usize, index: usize) -> Option<T> {
+        if channel >= self.channel_count() || index >= self.samples {
+            return None;
+        }
+        let position = match self.layout {
+            SampleLayout::Interleaved => index * self.channel_count() + channel,
+            SampleLayout::Planar => channel * self.samples + index,
+        };
+        self.data::<T>()?.get(position).copied()
+    }
+}
+
+impl fmt::Debug for AudioBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AudioBuffer")
+            .field("format", &self.format)
+            .field("layout", &self.layout)
+            .field("sample_rate", &self.sample_rate)
+            .field("channel_layout", &self.channel_layout)
+            .field("samples", &self.samples)
+            .finish()
+    }
+}
@@ -0,0 +1,307 @@
+//! Sample rate conversion of [`AudioBuffer`]s.
+//!
+//! [`Resampler`] keeps the tail of the previous input, so a stream split
+//! into frames is converted without discontinuities at frame boundaries.
+//! Output sample `k` is computed at input time `k * from / to`, the filter
+//! only adds latency and never shifts the signal.
+
+use std::f64::consts::PI;
+
+use super::{
+    AudioBuffer, AudioError, SampleFormat, SampleLayout,
+    convert::{self, Dither},
+};
+use crate::{profile::Ratio, structures::buffer::Buffer};
+
+/// Interpolation kernel used by the [`Resampler`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ResampleQuality {
+    /// Linear interpolation, no anti-alias filtering.
+    Linear,
+    /// Windowed sinc, 8 zero crossings.
+    Low,
+    /// Windowed sinc, 16 zero crossings.
+    #[default]
+    Medium,
+    /// Windowed sinc, 32 zero crossings.
+    High,
+}
+
+impl ResampleQuality {
+    /// `(zero crossings, kaiser beta, phases, passband)`.
+    fn parameters(&self) -> (usize, f64, usize, f64) {
+        match self {
+            ResampleQuality::Linear => (1, 0.0, 1, 1.0),
+            ResampleQuality::Low => (8, 6.0, 128, 0.90),
+            ResampleQuality::Medium => (16, 8.0, 256, 0.94),
+            ResampleQuality::High => (32, 10.0, 512, 0.97),
+        }
+    }
+}
+
+/// Zeroth order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+
+    for k in 1..64 {
+        term *= half / k as f64;
+        let square = term * term;
+        sum += square;
+        if square < sum * 1e-12 {
+            break;
+        }
+    }
+
+    sum
+}
+
+/// Polyphase coefficient table, `phases + 1` rows of `2 * half` taps.
+struct Kernel {
+    half: usize,
+    phases: usize,
+    taps: Vec<f32>,
+}
+
+impl Kernel {
+    fn new(quality: ResampleQuality, from: u32, to: u32) -> Self {
+        let (crossings, beta, phases, passband) = quality.parameters();
+
+        if quality == ResampleQuality::Linear {
+            // Triangle kernel, exact for linear phase interpolation.
+            return Self {
+                half: 1,
+                phases,
+                taps: vec![1.0, 0.0, 0.0, 1.0],
+            };
+        }
+
+        let cutoff = passband * (to as f64 / from as f64).min(1.0);
+        let half = (crossings as f64 / cutoff).ceil() as usize;
+        let width = 2 * half;
+        let norm = bessel_i0(beta);
+        let mut taps = Vec::with_capacity((phases + 1) * width);
+
+        for phase in 0..=phases {
+            let fraction = phase as f64 / phases as f64;
+            let row = taps.len();
+            for j in 0..width {
+                let x = (j as f64 - half as f64 + 1.0) - fraction;
+                let t = x / half as f64;
+                let window = if t.abs() >= 1.0 {
+                    0.0
+                } else {
+                    bessel_i0(beta * (1.0 - t * t).sqrt()) / norm
+                };
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * cutoff * x).sin() / (PI * cutoff * x)
+                };
+                taps.push((cutoff * sinc * window) as f32);
+            }
+
+            // Unity gain at DC for every phase.
+            let sum = taps[row..].iter().sum::<f32>();
+            taps[row..].iter_mut().for_each(|tap| *tap /= sum);
+        }
+
+        Self { half, phases, taps }
+    }
+
+    /// Interpolate `samples` whose first element sits `half - 1` samples
+    /// before the output time, `fraction` past it.
+    fn apply(&self, samples: &[f32], fraction: f64) -> f32 {
+        let width = 2 * self.half;
+        let position = fraction * self.phases as f64;
+        let phase = (position as usize).min(self.phases - 1);
+        let blend = (position - phase as f64) as f32;
+
+        let low = &self.taps[phase * width..(phase + 1) * width];
+        let high = &self.taps[(phase + 1) * width..(phase + 2) * width];
+
+        samples
+            .iter()
+            .zip(low.iter().zip(high))
+            .map(|(sample, (a, b))| sample * (a + (b - a) * blend))
+            .sum()
+    }
+}
+
+/// Streaming sample rate converter.
+pub struct Resampler {
+    input_rate: u32,
+    output_rate: u32,
+    /// Reduced `input_rate / output_rate` step.
+    from: i64,
+    to: i64,
+    channels: usize,
+    kernel: Kernel,
+    /// Pending input per channel, first element at input index `offset`.
+    history: Vec<Vec<f32>>,
+    offset: i64,
+    /// Input samples received so far.
+    received: i64,
+    /// Index of the next output sample.
+    next: i64,
+}
+
+impl Resampler {
+    pub fn new(
+        from: u32,
+        to: u32,
+        channels: usize,
+        quality: ResampleQuality,
+    ) -> Result<Self, AudioError> {
+        if from == 0 || to == 0 {
+            return Err(AudioError::ZeroSampleRate);
+        }
+        if channels == 0 {
+            return Err(AudioError::NoChannels);
+        }
+
+        let ratio = Ratio::new(from as i64, to as i64);
+        let kernel = Kernel::new(quality, from, to);
+        let mut resampler = Self {
+            input_rate: from,
+            output_rate: to,
+            from: ratio.number,
+            to: ratio.denominator,
+            channels,
+            kernel,
+            history: Vec::new(),
+            offset: 0,
+            received: 0,
+            next: 0,
+        };
+        resampler.reset();
+
+        Ok(resampler)
+    }
+
+    /// Drop pending samples and restart at time zero.
+    pub fn reset(&mut self) {
+        let lead = self.kernel.half - 1;
+        self.history = vec![vec![0.0; lead]; self.channels];
+        self.offset = -(lead as i64);
+        self.received = 0;
+        self.next = 0;
+    }
+
+    /// Input samples held back before output is produced.
+    pub fn latency(&self) -> usize {
+        self.kernel.half
+    }
+
+    /// Convert the next block of the stream.
+    ///
+    /// Output keeps the format, layout and channel layout of `input`.
+    pub fn process(&mut self, input: &AudioBuffer) -> Result<AudioBuffer, AudioError> {
+        if input.channel_count() != self.channels {
+            return Err(AudioError::ChannelMismatch {
+                expected: self.channels,
+                found: input.channel_count(),
+            });
+        }
+        if input.sample_rate() != self.input_rate {
+            return Err(AudioError::SampleRateMismatch {
+                expected: self.input_rate,
+                found: input.sample_rate(),
+            });
+        }
+
+        let planar = input
+            .to_format(SampleFormat::F32, Dither::None)?
+            .to_layout(SampleLayout::Planar);
+
+        for (channel, history) in self.history.iter_mut().enumerate() {
+            history.extend_from_slice(planar.channel::<f32>(channel).unwrap_or_default());
+        }
+        self.received += input.samples() as i64;
+
+        let output = self.drain(false);
+        self.finish(input, output)
+    }
+
+    /// Emit the samples still held back at the end of the stream.
+    pub fn flush(&mut self, template: &AudioBuffer) -> Result<AudioBuffer, AudioError> {
+        for history in self.history.iter_mut() {
+            history.extend(std::iter::repeat_n(0.0, self.kernel.half + 1));
+        }
+
+        let output = self.drain(true);
+        let output = self.finish(template, output);
+        self.reset();
+        output
+    }
+
+    /// Produce every output sample whose filter support is available.
+    fn drain(&mut self, flushing: bool) -> Vec<Vec<f32>> {
+        let half = self.kernel.half as i64;
+        let available = self.offset + self.history[0].len() as i64;
+        let mut output = vec![Vec::new(); self.channels];
+
+        loop {
+            let position = self.next * self.from;
+            let index = position / self.to;
+            let fraction = (position % self.to) as f64 / self.to as f64;
+
+            if index + half >= available || (flushing && position >= self.received * self.to) {
+                break;
+            }
+
+            let start = (index - half + 1 - self.offset) as usize;
+            for (channel, history) in self.history.iter().enumerate() {
+                let window = &history[start..start + 2 * half as usize];
+                output[channel].push(self.kernel.apply(window, fraction));
+            }
+            self.next += 1;
+        }
+
+        // Keep only the samples needed by the next output.
+        let position = self.next * self.from;
+        let keep_from = (position / self.to - half + 1).max(self.offset);
+        let consumed = ((keep_from - self.offset) as usize).min(self.history[0].len());
+        for history in self.history.iter_mut() {
+            history.drain(..consumed);
+        }
+        self.offset += consumed as i64;
+
+        output
+    }
+
+    fn finish(
+        &self,
+        template: &AudioBuffer,
+        output: Vec<Vec<f32>>,
+    ) -> Result<AudioBuffer, AudioError> {
+        let samples = output[0].len();
+        let planar = AudioBuffer::from_buffer(
+            SampleLayout::Planar,
+            self.output_rate,
+            template.channel_layout(),
+            samples,
+            Buffer::OwnedF32(output.concat()),
+        )?;
+
+        let buffer = convert::convert(planar.buffer(), template.format(), Dither::None)?;
+        let converted = AudioBuffer::from_buffer(
+            SampleLayout::Planar,
+            self.output_rate,
+            template.channel_layout(),
+            samples,
+            buffer,
+        )?;
+
+        Ok(converted.to_layout(template.layout()))
+    }
+
+    pub fn input_rate(&self) -> u32 {
+        self.input_rate
+    }
+
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+}
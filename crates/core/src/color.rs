@@ -1,6 +1,12 @@
-use serde::{Deserialize, Serialize};
+//! Color description of video signals.
+//!
+//! Numeric code points follow ITU-T H.273, the same values used by the VUI
+//! of H.264/H.265 and by most containers.
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Matrix coefficients used to derive luma and chroma from RGB.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum ColorSpace {
     #[default]
@@ -20,3 +26,465 @@ pub enum ColorSpace {
     Smpte2085,
     Ycgco,
 }
+
+impl ColorSpace {
+    /// H.273 `MatrixCoefficients` value, `None` for [`ColorSpace::Invalid`].
+    ///
+    /// [`ColorSpace::Bt601`] is signalled as `6` (SMPTE 170M).
+    pub fn code_point(&self) -> Option<u8> {
+        match self {
+            ColorSpace::Invalid => None,
+            ColorSpace::Rgb => Some(0),
+            ColorSpace::Bt709 => Some(1),
+            ColorSpace::Unspecified => Some(2),
+            ColorSpace::Reserved => Some(3),
+            ColorSpace::Fcc => Some(4),
+            ColorSpace::Bt470bg => Some(5),
+            ColorSpace::Bt601 | ColorSpace::Smpte170m => Some(6),
+            ColorSpace::Smpte240m => Some(7),
+            ColorSpace::Ycgco => Some(8),
+            ColorSpace::Bt2020Ncl => Some(9),
+            ColorSpace::Bt2020Cl => Some(10),
+            ColorSpace::Smpte2085 => Some(11),
+        }
+    }
+
+    pub fn from_code_point(code_point: u8) -> ColorSpace {
+        match code_point {
+            0 => ColorSpace::Rgb,
+            1 => ColorSpace::Bt709,
+            2 => ColorSpace::Unspecified,
+            4 => ColorSpace::Fcc,
+            5 => ColorSpace::Bt470bg,
+            6 => ColorSpace::Smpte170m,
+            7 => ColorSpace::Smpte240m,
+            8 => ColorSpace::Ycgco,
+            9 => ColorSpace::Bt2020Ncl,
+            10 => ColorSpace::Bt2020Cl,
+            11 => ColorSpace::Smpte2085,
+            _ => ColorSpace::Reserved,
+        }
+    }
+
+    /// Luma weights `(Kr, Kb)` of non-linear RGB, `None` for matrices that
+    /// are not such weighted sums: [`ColorSpace::Rgb`], [`ColorSpace::Ycgco`]
+    /// and the constant luminance [`ColorSpace::Bt2020Cl`] and
+    /// [`ColorSpace::Smpte2085`].
+    ///
+    /// Unknown matrices use BT.709 weights.
+    pub fn luma_coefficients(&self) -> Option<(f64, f64)> {
+        match self {
+            ColorSpace::Rgb | ColorSpace::Ycgco => None,
+            ColorSpace::Bt2020Cl | ColorSpace::Smpte2085 => None,
+            ColorSpace::Bt601 | ColorSpace::Bt470bg | ColorSpace::Smpte170m => Some((0.299, 0.114)),
+            ColorSpace::Bt2020Ncl => Some((0.2627, 0.0593)),
+            ColorSpace::Fcc => Some((0.30, 0.11)),
+            ColorSpace::Smpte240m => Some((0.212, 0.087)),
+            ColorSpace::Bt709
+            | ColorSpace::Invalid
+            | ColorSpace::Unspecified
+            | ColorSpace::Reserved => Some((0.2126, 0.0722)),
+        }
+    }
+
+    /// Normalized `[Y, Cb, Cr]` of non-linear `rgb`, luma in `0.0..=1.0`
+    /// and chroma in `-0.5..=0.5`. [`ColorSpace::Rgb`] returns
+    /// `[G, B - 0.5, R - 0.5]`.
+    ///
+    /// `None` for constant luminance matrices, which derive luma from
+    /// linear light rather than from `rgb`.
+    pub fn rgb_to_ycbcr(&self, [r, g, b]: [f64; 3]) -> Option<[f64; 3]> {
+        match (self, self.luma_coefficients()) {
+            (_, Some((kr, kb))) => {
+                let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+                Some([
+                    y,
+                    (b - y) / (2.0 * (1.0 - kb)),
+                    (r - y) / (2.0 * (1.0 - kr)),
+                ])
+            }
+            (ColorSpace::Ycgco, None) => Some([
+                0.25 * r + 0.5 * g + 0.25 * b,
+                -0.25 * r + 0.5 * g - 0.25 * b,
+                0.5 * r - 0.5 * b,
+            ]),
+            (ColorSpace::Bt2020Cl | ColorSpace::Smpte2085, None) => None,
+            _ => Some([g, b - 0.5, r - 0.5]),
+        }
+    }
+}
+
+/// Chromaticity of the RGB primaries and white point.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum ColorPrimaries {
+    #[default]
+    Unspecified,
+    Reserved,
+
+    Bt709,
+    Bt470m,
+    Bt470bg,
+    Smpte170m,
+    Smpte240m,
+    Film,
+    Bt2020,
+    /// CIE 1931 XYZ, SMPTE ST 428-1.
+    Smpte428,
+    /// DCI-P3, SMPTE RP 431-2.
+    Smpte431,
+    /// Display P3, SMPTE EG 432-1.
+    Smpte432,
+    Ebu3213,
+}
+
+impl ColorPrimaries {
+    /// H.273 `ColourPrimaries` value.
+    pub fn code_point(&self) -> u8 {
+        match self {
+            ColorPrimaries::Bt709 => 1,
+            ColorPrimaries::Unspecified => 2,
+            ColorPrimaries::Reserved => 3,
+            ColorPrimaries::Bt470m => 4,
+            ColorPrimaries::Bt470bg => 5,
+            ColorPrimaries::Smpte170m => 6,
+            ColorPrimaries::Smpte240m => 7,
+            ColorPrimaries::Film => 8,
+            ColorPrimaries::Bt2020 => 9,
+            ColorPrimaries::Smpte428 => 10,
+            ColorPrimaries::Smpte431 => 11,
+            ColorPrimaries::Smpte432 => 12,
+            ColorPrimaries::Ebu3213 => 22,
+        }
+    }
+
+    pub fn from_code_point(code_point: u8) -> ColorPrimaries {
+        match code_point {
+            1 => ColorPrimaries::Bt709,
+            2 => ColorPrimaries::Unspecified,
+            4 => ColorPrimaries::Bt470m,
+            5 => ColorPrimaries::Bt470bg,
+            6 => ColorPrimaries::Smpte170m,
+            7 => ColorPrimaries::Smpte240m,
+            8 => ColorPrimaries::Film,
+            9 => ColorPrimaries::Bt2020,
+            10 => ColorPrimaries::Smpte428,
+            11 => ColorPrimaries::Smpte431,
+            12 => ColorPrimaries::Smpte432,
+            22 => ColorPrimaries::Ebu3213,
+            _ => ColorPrimaries::Reserved,
+        }
+    }
+}
+
+/// Opto-electronic transfer function of the signal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum TransferCharacteristics {
+    #[default]
+    Unspecified,
+    Reserved,
+
+    Bt709,
+    /// Gamma 2.2, BT.470 System M.
+    Gamma22,
+    /// Gamma 2.8, BT.470 System B/G.
+    Gamma28,
+    Smpte170m,
+    Smpte240m,
+    Linear,
+    Log100,
+    Log316,
+    /// xvYCC, IEC 61966-2-4.
+    Iec61966_2_4,
+    Bt1361,
+    /// sRGB, IEC 61966-2-1.
+    Srgb,
+    Bt2020_10,
+    Bt2020_12,
+    /// Perceptual quantizer, SMPTE ST 2084.
+    Pq,
+    Smpte428,
+    /// Hybrid log-gamma, ARIB STD-B67.
+    Hlg,
+}
+
+impl TransferCharacteristics {
+    /// BT.1886 displays are signalled with the BT.709 code point.
+    pub const BT1886: TransferCharacteristics = TransferCharacteristics::Bt709;
+
+    /// H.273 `TransferCharacteristics` value.
+    pub fn code_point(&self) -> u8 {
+        match self {
+            TransferCharacteristics::Bt709 => 1,
+            TransferCharacteristics::Unspecified => 2,
+            TransferCharacteristics::Reserved => 3,
+            TransferCharacteristics::Gamma22 => 4,
+            TransferCharacteristics::Gamma28 => 5,
+            TransferCharacteristics::Smpte170m => 6,
+            TransferCharacteristics::Smpte240m => 7,
+            TransferCharacteristics::Linear => 8,
+            TransferCharacteristics::Log100 => 9,
+            TransferCharacteristics::Log316 => 10,
+            TransferCharacteristics::Iec61966_2_4 => 11,
+            TransferCharacteristics::Bt1361 => 12,
+            TransferCharacteristics::Srgb => 13,
+            TransferCharacteristics::Bt2020_10 => 14,
+            TransferCharacteristics::Bt2020_12 => 15,
+            TransferCharacteristics::Pq => 16,
+            TransferCharacteristics::Smpte428 => 17,
+            TransferCharacteristics::Hlg => 18,
+        }
+    }
+
+    pub fn from_code_point(code_point: u8) -> TransferCharacteristics {
+        match code_point {
+            1 => TransferCharacteristics::Bt709,
+            2 => TransferCharacteristics::Unspecified,
+            4 => TransferCharacteristics::Gamma22,
+            5 => TransferCharacteristics::Gamma28,
+            6 => TransferCharacteristics::Smpte170m,
+            7 => TransferCharacteristics::Smpte240m,
+            8 => TransferCharacteristics::Linear,
+            9 => TransferCharacteristics::Log100,
+            10 => TransferCharacteristics::Log316,
+            11 => TransferCharacteristics::Iec61966_2_4,
+            12 => TransferCharacteristics::Bt1361,
+            13 => TransferCharacteristics::Srgb,
+            14 => TransferCharacteristics::Bt2020_10,
+            15 => TransferCharacteristics::Bt2020_12,
+            16 => TransferCharacteristics::Pq,
+            17 => TransferCharacteristics::Smpte428,
+            18 => TransferCharacteristics::Hlg,
+            _ => TransferCharacteristics::Reserved,
+        }
+    }
+
+    /// High dynamic range transfer functions.
+    pub fn is_hdr(&self) -> bool {
+        matches!(
+            self,
+            TransferCharacteristics::Pq | TransferCharacteristics::Hlg
+        )
+    }
+}
+
+/// Quantization range of the samples.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum ColorRange {
+    /// Studio swing, e.g. 16-235 for 8-bit luma.
+    #[default]
+    Limited,
+    /// Full swing, e.g. 0-255 for 8-bit.
+    Full,
+}
+
+impl ColorRange {
+    /// H.273 `VideoFullRangeFlag` value.
+    pub fn code_point(&self) -> u8 {
+        match self {
+            ColorRange::Limited => 0,
+            ColorRange::Full => 1,
+        }
+    }
+
+    pub fn from_code_point(code_point: u8) -> ColorRange {
+        if code_point == 0 {
+            ColorRange::Limited
+        } else {
+            ColorRange::Full
+        }
+    }
+
+    /// Code value of normalized luma or RGB `value` at `bits` depth,
+    /// unrounded and unclamped.
+    pub fn quantize_luma(&self, value: f64, bits: u32) -> f64 {
+        match self {
+            ColorRange::Limited => (16.0 + 219.0 * value) * (1u32 << (bits - 8)) as f64,
+            ColorRange::Full => value * ((1u64 << bits) - 1) as f64,
+        }
+    }
+
+    /// Code value of normalized chroma `value` in `-0.5..=0.5` at `bits`
+    /// depth, unrounded and unclamped.
+    pub fn quantize_chroma(&self, value: f64, bits: u32) -> f64 {
+        match self {
+            ColorRange::Limited => (128.0 + 224.0 * value) * (1u32 << (bits - 8)) as f64,
+            ColorRange::Full => value * ((1u64 << bits) - 1) as f64 + (1u64 << (bits - 1)) as f64,
+        }
+    }
+
+    /// Normalized luma or RGB of code value `value`, inverse of
+    /// [`ColorRange::quantize_luma`].
+    pub fn normalize_luma(&self, value: f64, bits: u32) -> f64 {
+        match self {
+            ColorRange::Limited => (value / (1u32 << (bits - 8)) as f64 - 16.0) / 219.0,
+            ColorRange::Full => value / ((1u64 << bits) - 1) as f64,
+        }
+    }
+
+    /// Normalized chroma of code value `value`, inverse of
+    /// [`ColorRange::quantize_chroma`].
+    pub fn normalize_chroma(&self, value: f64, bits: u32) -> f64 {
+        match self {
+            ColorRange::Limited => (value / (1u32 << (bits - 8)) as f64 - 128.0) / 224.0,
+            ColorRange::Full => (value - (1u64 << (bits - 1)) as f64) / ((1u64 << bits) - 1) as f64,
+        }
+    }
+}
+
+/// Complete color description: primaries, transfer, matrix and range.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[repr(C)]
+pub struct ColorInfo {
+    pub primaries: ColorPrimaries,
+    pub transfer: TransferCharacteristics,
+    pub matrix: ColorSpace,
+    pub range: ColorRange,
+}
+
+impl ColorInfo {
+    pub const BT709: ColorInfo = ColorInfo {
+        primaries: ColorPrimaries::Bt709,
+        transfer: TransferCharacteristics::Bt709,
+        matrix: ColorSpace::Bt709,
+        range: ColorRange::Limited,
+    };
+
+    pub const SRGB: ColorInfo = ColorInfo {
+        primaries: ColorPrimaries::Bt709,
+        transfer: TransferCharacteristics::Srgb,
+        matrix: ColorSpace::Rgb,
+        range: ColorRange::Full,
+    };
+
+    pub const BT2020_PQ: ColorInfo = ColorInfo {
+        primaries: ColorPrimaries::Bt2020,
+        transfer: TransferCharacteristics::Pq,
+        matrix: ColorSpace::Bt2020Ncl,
+        range: ColorRange::Limited,
+    };
+
+    pub const BT2020_HLG: ColorInfo = ColorInfo {
+        primaries: ColorPrimaries::Bt2020,
+        transfer: TransferCharacteristics::Hlg,
+        matrix: ColorSpace::Bt2020Ncl,
+        range: ColorRange::Limited,
+    };
+
+    /// H.273 code points as `(primaries, transfer, matrix, full_range)`.
+    pub fn code_points(&self) -> (u8, u8, Option<u8>, u8) {
+        (
+            self.primaries.code_point(),
+            self.transfer.code_point(),
+            self.matrix.code_point(),
+            self.range.code_point(),
+        )
+    }
+
+    pub fn from_code_points(primaries: u8, transfer: u8, matrix: u8, full_range: u8) -> Self {
+        Self {
+            primaries: ColorPrimaries::from_code_point(primaries),
+            transfer: TransferCharacteristics::from_code_point(transfer),
+            matrix: ColorSpace::from_code_point(matrix),
+            range: ColorRange::from_code_point(full_range),
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.transfer.is_hdr()
+    }
+}
+
+/// Usual primaries, transfer and range for material tagged only with its
+/// matrix, as in profiles written before [`ColorInfo`] existed.
+impl From<ColorSpace> for ColorInfo {
+    fn from(matrix: ColorSpace) -> Self {
+        let (primaries, transfer, range) = match matrix {
+            ColorSpace::Rgb => (
+                ColorPrimaries::Bt709,
+                TransferCharacteristics::Srgb,
+                ColorRange::Full,
+            ),
+            ColorSpace::Bt709 | ColorSpace::Ycgco => (
+                ColorPrimaries::Bt709,
+                TransferCharacteristics::Bt709,
+                ColorRange::Limited,
+            ),
+            ColorSpace::Bt601 | ColorSpace::Bt470bg => (
+                ColorPrimaries::Bt470bg,
+                TransferCharacteristics::Smpte170m,
+                ColorRange::Limited,
+            ),
+            ColorSpace::Smpte170m => (
+                ColorPrimaries::Smpte170m,
+                TransferCharacteristics::Smpte170m,
+                ColorRange::Limited,
+            ),
+            ColorSpace::Smpte240m => (
+                ColorPrimaries::Smpte240m,
+                TransferCharacteristics::Smpte240m,
+                ColorRange::Limited,
+            ),
+            ColorSpace::Fcc => (
+                ColorPrimaries::Bt470m,
+                TransferCharacteristics::Gamma22,
+                ColorRange::Limited,
+            ),
+            ColorSpace::Bt2020Ncl | ColorSpace::Bt2020Cl => (
+                ColorPrimaries::Bt2020,
+                TransferCharacteristics::Bt2020_10,
+                ColorRange::Limited,
+            ),
+            ColorSpace::Smpte2085 => (
+                ColorPrimaries::Bt2020,
+                TransferCharacteristics::Pq,
+                ColorRange::Limited,
+            ),
+            ColorSpace::Invalid | ColorSpace::Unspecified | ColorSpace::Reserved => (
+                ColorPrimaries::Unspecified,
+                TransferCharacteristics::Unspecified,
+                ColorRange::Limited,
+            ),
+        };
+
+        Self {
+            primaries,
+            transfer,
+            matrix,
+            range,
+        }
+    }
+}
+
+/// Accepts the full description as well as a bare [`ColorSpace`] name.
+impl<'de> Deserialize<'de> for ColorInfo {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Default, Deserialize)]
+        #[serde(default)]
+        struct Fields {
+            primaries: ColorPrimaries,
+            transfer: TransferCharacteristics,
+            matrix: ColorSpace,
+            range: ColorRange,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Matrix(ColorSpace),
+            Info(Fields),
+        }
+
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::Matrix(matrix) => ColorInfo::from(matrix),
+            Raw::Info(fields) => ColorInfo {
+                primaries: fields.primaries,
+                transfer: fields.transfer,
+                matrix: fields.matrix,
+                range: fields.range,
+            },
+        })
+    }
+}
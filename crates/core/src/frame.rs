@@ -1,27 +1,62 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{profile::Profile, structures::object};
+use crate::{
+    audio::AudioBuffer, color::ColorInfo, image::Image, profile::Profile, structures::object,
+};
 use mcf_math::geometry;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[repr(C)]
 pub struct FrameMetaData {}
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[repr(C)]
 pub struct Frame {
     pub profile: Profile,
     pub aspect_ratio: f32,
     pub viewport: geometry::Frame,
+    pub color: ColorInfo,
+    /// Picture data, not serialized.
+    #[serde(skip)]
+    pub image: Option<Image>,
+    /// Audio samples of the frame, not serialized.
+    #[serde(skip)]
+    pub audio: Option<AudioBuffer>,
     pub properties: object::Object,
     pub meta: FrameMetaData,
 
-    position: geometry::Position,
+    /// Frame number in the timeline of the producer.
+    position: i64,
+    /// Playback speed, negative when playing backwards.
     speed: i8,
 }
 
+impl Frame {
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: i64) {
+        self.position = position;
+    }
+
+    pub fn speed(&self) -> i8 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: i8) {
+        self.speed = speed;
+    }
+}
+
 pub struct FrameBuilder(Frame);
 
+impl Default for FrameBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FrameBuilder {
     pub fn new() -> Self {
         Self(Frame::default())
@@ -42,6 +77,21 @@ impl FrameBuilder {
         self
     }
 
+    pub fn set_color(&mut self, color: ColorInfo) -> &Self {
+        self.0.color = color;
+        self
+    }
+
+    pub fn set_image(&mut self, image: Image) -> &Self {
+        self.0.image = Some(image);
+        self
+    }
+
+    pub fn set_audio(&mut self, audio: AudioBuffer) -> &Self {
+        self.0.audio = Some(audio);
+        self
+    }
+
     pub fn set_properties(&mut self, properties: object::Object) -> &Self {
         self.0.properties = properties;
         self
@@ -52,7 +102,7 @@ impl FrameBuilder {
         self
     }
 
-    pub fn set_position(&mut self, position: geometry::Position) -> &Self {
+    pub fn set_position(&mut self, position: i64) -> &Self {
         self.0.position = position;
         self
     }
@@ -61,6 +111,10 @@ impl FrameBuilder {
         self.0.speed = speed;
         self
     }
+
+    pub fn build(&self) -> Frame {
+        self.0.clone()
+    }
 }
 
 mod ffi {
@@ -92,7 +146,7 @@ mod ffi {
                 return COption::None;
             }
 
-            COption::Some(unsafe { (*frame).aspect_ratio.clone() })
+            COption::Some(unsafe { (*frame).aspect_ratio })
         }
 
         #[unsafe(no_mangle)]
@@ -114,12 +168,12 @@ mod ffi {
         // }
 
         #[unsafe(no_mangle)]
-        pub extern "C" fn frame_get_position(frame: *const Frame) -> COption<geometry::Position> {
+        pub extern "C" fn frame_get_position(frame: *const Frame) -> COption<i64> {
             if frame.is_null() {
                 return COption::None;
             }
 
-            COption::Some(unsafe { (*frame).position.clone() })
+            COption::Some(unsafe { (*frame).position })
         }
     }
 
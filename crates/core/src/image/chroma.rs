@@ -0,0 +1,177 @@
+//! Resampling of chroma planes between subsampling layouts.
+//!
+//! Chroma positions follow [`ChromaSiting`]. Interlaced 4:2:0 keeps the
+//! chroma lines of each field apart, the top field chroma a quarter and
+//! the bottom field chroma three quarters between two field lines.
+
+use serde::{Deserialize, Serialize};
+
+use super::resample::{self, ResampleFilter, Weights};
+use crate::profile::{Profile, RenderType};
+
+/// Position of subsampled chroma samples relative to luma samples.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChromaSiting {
+    /// Co-sited with the left luma sample, vertically centered, as in
+    /// MPEG-2 and H.264.
+    #[default]
+    Left,
+    /// Centered between the luma samples, as in JPEG and MPEG-1.
+    Center,
+    /// Co-sited with the top left luma sample, as in BT.2020 4:2:0.
+    TopLeft,
+}
+
+impl ChromaSiting {
+    fn cosited(&self, vertical: bool) -> bool {
+        match self {
+            ChromaSiting::Left => !vertical,
+            ChromaSiting::Center => false,
+            ChromaSiting::TopLeft => true,
+        }
+    }
+
+    /// Luma position of the first chroma sample of a plane subsampled by
+    /// `shift`.
+    pub(super) fn offset(&self, shift: u32, vertical: bool) -> f64 {
+        match self.cosited(vertical) {
+            true => 0.0,
+            false => ((1 << shift) - 1) as f64 / 2.0,
+        }
+    }
+
+    /// Luma position within the field of `parity` of the first chroma
+    /// sample of that field.
+    fn field_offset(&self, shift: u32, parity: usize) -> f64 {
+        match (shift, self.cosited(true)) {
+            (0, _) | (_, true) => 0.0,
+            _ => self.offset(shift, true) + (parity as f64 - 0.5) * 0.5,
+        }
+    }
+}
+
+/// How chroma planes are resampled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChromaOptions {
+    /// Siting of the planes read.
+    pub source: ChromaSiting,
+    /// Siting of the planes written.
+    pub target: ChromaSiting,
+    pub filter: ResampleFilter,
+    /// Resample vertically per field.
+    pub interlaced: bool,
+}
+
+impl ChromaOptions {
+    /// Default options, interlaced for [`RenderType::Interlace`] profiles.
+    pub fn for_profile(profile: &Profile) -> Self {
+        Self {
+            interlaced: profile.render_type == RenderType::Interlace,
+            ..Self::default()
+        }
+    }
+}
+
+/// Resample a chroma plane of an image of `(width, height)` luma samples
+/// from subsampling `from` to `to`, both `(shift_x, shift_y)`.
+pub fn resample(
+    values: &[f32],
+    (width, height): (usize, usize),
+    from: (u32, u32),
+    to: (u32, u32),
+    options: &ChromaOptions,
+) -> Vec<f32> {
+    let size = |length: usize, shift: u32| length.div_ceil(1 << shift);
+    let (source_width, source_height) = (size(width, from.0), size(height, from.1));
+    let (target_width, target_height) = (size(width, to.0), size(height, to.1));
+
+    let horizontal = match from.0 == to.0 {
+        true => values[..source_width * source_height].to_vec(),
+        false => {
+            let weights = axis_weights(
+                options.filter,
+                (source_width, from.0, options.source.offset(from.0, false)),
+                (target_width, to.0, options.target.offset(to.0, false)),
+            );
+            resample::resample_rows(values, source_width, source_height, &weights)
+        }
+    };
+
+    if from.1 == to.1 {
+        horizontal
+    } else if options.interlaced {
+        resample_fields(
+            &horizontal,
+            target_width,
+            source_height,
+            target_height,
+            from.1,
+            to.1,
+            options,
+        )
+    } else {
+        let weights = axis_weights(
+            options.filter,
+            (source_height, from.1, options.source.offset(from.1, true)),
+            (target_height, to.1, options.target.offset(to.1, true)),
+        );
+        resample::resample_columns(&horizontal, target_width, source_height, &weights)
+    }
+}
+
+/// Vertical resampling of each field of a plane on its own.
+fn resample_fields(
+    values: &[f32],
+    width: usize,
+    source_height: usize,
+    target_height: usize,
+    from: u32,
+    to: u32,
+    options: &ChromaOptions,
+) -> Vec<f32> {
+    let field_height = |height: usize, parity: usize| (height + 1 - parity) / 2;
+
+    let mut output = vec![0.0; width * target_height];
+    for parity in 0..2 {
+        let field: Vec<f32> = values
+            .chunks_exact(width)
+            .skip(parity)
+            .step_by(2)
+            .flatten()
+            .copied()
+            .collect();
+        let source = field_height(source_height, parity);
+        let target = field_height(target_height, parity);
+        if source == 0 || target == 0 {
+            continue;
+        }
+
+        let weights = axis_weights(
+            options.filter,
+            (source, from, options.source.field_offset(from, parity)),
+            (target, to, options.target.field_offset(to, parity)),
+        );
+        let field = resample::resample_columns(&field, width, source, &weights);
+        for (row, line) in field.chunks_exact(width).enumerate() {
+            output[(row * 2 + parity) * width..][..width].copy_from_slice(line);
+        }
+    }
+    output
+}
+
+/// Weights from `(length, shift, offset)` samples to `(length, shift,
+/// offset)` samples along one axis.
+fn axis_weights(
+    filter: ResampleFilter,
+    (source, from, source_offset): (usize, u32, f64),
+    (target, to, target_offset): (usize, u32, f64),
+) -> Weights {
+    let (source_step, target_step) = ((1 << from) as f64, (1 << to) as f64);
+    Weights::new(
+        filter,
+        source,
+        target,
+        |i| (i as f64 * target_step + target_offset - source_offset) / source_step,
+        target_step / source_step,
+    )
+}
@@ -0,0 +1,884 @@
+//! Conversion between RGB and YCbCr for every [`ColorSpace`] matrix.
+//!
+//! Kernels work on planes of any sample type with a [`Coding`] giving the
+//! range and bit depth of the code values. [`PlanarImage`] unpacks whole
+//! images to normalized `f32` planes for conversions between formats.
+
+use super::{
+    Image, ImageError,
+    chroma::{self, ChromaOptions},
+    format::{PixelFormat, SampleType},
+};
+use crate::{
+    color::{ColorInfo, ColorRange, ColorSpace},
+    structures::buffer::BufferElement,
+};
+
+/// Sample type holding code values.
+pub trait Sample: BufferElement + Copy {
+    fn to_code(self) -> f64;
+    /// Rounded and clamped for integer samples.
+    fn from_code(value: f64) -> Self;
+}
+
+impl Sample for u8 {
+    fn to_code(self) -> f64 {
+        self as f64
+    }
+
+    fn from_code(value: f64) -> Self {
+        value.round().clamp(0.0, u8::MAX as f64) as u8
+    }
+}
+
+impl Sample for u16 {
+    fn to_code(self) -> f64 {
+        self as f64
+    }
+
+    fn from_code(value: f64) -> Self {
+        value.round().clamp(0.0, u16::MAX as f64) as u16
+    }
+}
+
+impl Sample for f32 {
+    fn to_code(self) -> f64 {
+        self as f64
+    }
+
+    fn from_code(value: f64) -> Self {
+        value as f32
+    }
+}
+
+/// Range and depth of code values. Float codings store normalized values,
+/// chroma centered on zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Coding {
+    pub range: ColorRange,
+    /// Bits per sample, `32` for float.
+    pub bits: u32,
+}
+
+impl Coding {
+    pub const FLOAT: Coding = Coding {
+        range: ColorRange::Full,
+        bits: 32,
+    };
+
+    pub fn new(range: ColorRange, bits: u32) -> Self {
+        Self { range, bits }
+    }
+
+    /// Full range RGB at `bits`.
+    pub fn rgb(bits: u32) -> Self {
+        Self::new(ColorRange::Full, bits)
+    }
+
+    pub fn is_float(&self) -> bool {
+        self.bits >= 32
+    }
+
+    /// Clamp integer codes to the bit depth.
+    fn clamp(&self, code: f64) -> f64 {
+        code.clamp(0.0, ((1u64 << self.bits) - 1) as f64)
+    }
+
+    fn encode_luma(&self, value: f64) -> f64 {
+        if self.is_float() {
+            value
+        } else {
+            self.clamp(self.range.quantize_luma(value, self.bits))
+        }
+    }
+
+    fn decode_luma(&self, code: f64) -> f64 {
+        if self.is_float() {
+            code
+        } else {
+            self.range.normalize_luma(code, self.bits)
+        }
+    }
+
+    /// GBR matrices store chroma as offset luma.
+    fn encode_chroma(&self, value: f64, gbr: bool) -> f64 {
+        match (self.is_float(), gbr) {
+            (true, _) => value,
+            (false, true) => self.clamp(self.range.quantize_luma(value + 0.5, self.bits)),
+            (false, false) => self.clamp(self.range.quantize_chroma(value, self.bits)),
+        }
+    }
+
+    fn decode_chroma(&self, code: f64, gbr: bool) -> f64 {
+        match (self.is_float(), gbr) {
+            (true, _) => code,
+            (false, true) => self.range.normalize_luma(code, self.bits) - 0.5,
+            (false, false) => self.range.normalize_chroma(code, self.bits),
+        }
+    }
+}
+
+/// Affine RGB to YCbCr transform of a matrix, with its inverse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorMatrix {
+    space: ColorSpace,
+    forward: [[f64; 3]; 3],
+    offset: [f64; 3],
+    inverse: [[f64; 3]; 3],
+}
+
+impl ColorMatrix {
+    /// Transform of [`ColorSpace::rgb_to_ycbcr`] for `space`.
+    ///
+    /// Fails with [`ImageError::UnsupportedMatrix`] for constant luminance
+    /// matrices, which are not affine transforms of non-linear RGB.
+    pub fn new(space: ColorSpace) -> Result<Self, ImageError> {
+        let transform = |rgb| {
+            space
+                .rgb_to_ycbcr(rgb)
+                .ok_or(ImageError::UnsupportedMatrix(space))
+        };
+        let offset = transform([0.0; 3])?;
+        let mut forward = [[0.0; 3]; 3];
+        for column in 0..3 {
+            let mut unit = [0.0; 3];
+            unit[column] = 1.0;
+            let out = transform(unit)?;
+            for row in 0..3 {
+                forward[row][column] = out[row] - offset[row];
+            }
+        }
+
+        Ok(Self {
+            space,
+            forward,
+            offset,
+            inverse: invert(&forward),
+        })
+    }
+
+    pub fn space(&self) -> ColorSpace {
+        self.space
+    }
+
+    fn is_gbr(&self) -> bool {
+        self.space == ColorSpace::Rgb
+    }
+
+    /// Normalized `[Y, Cb, Cr]` of `rgb`.
+    pub fn to_ycbcr(&self, rgb: [f64; 3]) -> [f64; 3] {
+        let mut out = self.offset;
+        for (row, out) in out.iter_mut().enumerate() {
+            *out += (0..3).map(|i| self.forward[row][i] * rgb[i]).sum::<f64>();
+        }
+        out
+    }
+
+    /// Normalized `[R, G, B]` of `ycbcr`.
+    pub fn to_rgb(&self, ycbcr: [f64; 3]) -> [f64; 3] {
+        let centered = [0, 1, 2].map(|i| ycbcr[i] - self.offset[i]);
+        [0, 1, 2].map(|row| (0..3).map(|i| self.inverse[row][i] * centered[i]).sum())
+    }
+}
+
+fn invert(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let cofactor = |r: usize, c: usize| {
+        let (r0, r1) = ((r + 1) % 3, (r + 2) % 3);
+        let (c0, c1) = ((c + 1) % 3, (c + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let determinant = (0..3).map(|c| m[0][c] * cofactor(0, c)).sum::<f64>();
+
+    let mut inverse = [[0.0; 3]; 3];
+    for (r, row) in inverse.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = cofactor(c, r) / determinant;
+        }
+    }
+    inverse
+}
+
+/// Convert planes of R, G and B into Y, Cb and Cr. Planes have the same
+/// length.
+pub fn rgb_to_ycbcr<S: Sample, D: Sample>(
+    matrix: &ColorMatrix,
+    [r, g, b]: [&[S]; 3],
+    from: Coding,
+    [y, cb, cr]: [&mut [D]; 3],
+    to: Coding,
+) {
+    let gbr = matrix.is_gbr();
+    for i in 0..y.len() {
+        let rgb = [r[i], g[i], b[i]].map(|s| from.decode_luma(s.to_code()));
+        let [luma, blue, red] = matrix.to_ycbcr(rgb);
+        y[i] = D::from_code(to.encode_luma(luma));
+        cb[i] = D::from_code(to.encode_chroma(blue, gbr));
+        cr[i] = D::from_code(to.encode_chroma(red, gbr));
+    }
+}
+
+/// Convert planes of Y, Cb and Cr into R, G and B. Planes have the same
+/// length.
+pub fn ycbcr_to_rgb<S: Sample, D: Sample>(
+    matrix: &ColorMatrix,
+    [y, cb, cr]: [&[S]; 3],
+    from: Coding,
+    [r, g, b]: [&mut [D]; 3],
+    to: Coding,
+) {
+    let gbr = matrix.is_gbr();
+    for i in 0..r.len() {
+        let ycbcr = [
+            from.decode_luma(y[i].to_code()),
+            from.decode_chroma(cb[i].to_code(), gbr),
+            from.decode_chroma(cr[i].to_code(), gbr),
+        ];
+        let rgb = matrix.to_rgb(ycbcr);
+        r[i] = D::from_code(to.encode_luma(rgb[0]));
+        g[i] = D::from_code(to.encode_luma(rgb[1]));
+        b[i] = D::from_code(to.encode_luma(rgb[2]));
+    }
+}
+
+/// Matrix of the luma of gray and YUV formats, BT.709 for gray images
+/// tagged RGB.
+fn yuv_matrix(format: PixelFormat, color: &ColorInfo) -> ColorSpace {
+    match color.matrix {
+        ColorSpace::Rgb if format.is_gray() => ColorSpace::Bt709,
+        matrix => matrix,
+    }
+}
+
+/// Coding of the code values of `format` with `color`.
+fn format_coding(format: PixelFormat, color: &ColorInfo) -> Coding {
+    match (format.sample_type(), format.is_rgb()) {
+        (SampleType::F32, _) => Coding::FLOAT,
+        (_, true) => Coding::rgb(format.bit_depth()),
+        (_, false) => Coding::new(color.range, format.bit_depth()),
+    }
+}
+
+/// Normalized samples of an image at full resolution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanarImage {
+    pub width: usize,
+    pub height: usize,
+    /// Matrix of Y, Cb and Cr planes, `None` for R, G and B.
+    pub matrix: Option<ColorSpace>,
+    pub planes: [Vec<f32>; 3],
+    pub alpha: Option<Vec<f32>>,
+}
+
+impl PlanarImage {
+    /// Unpack `image` described by `color`. RGB formats are full range,
+    /// gray formats have neutral chroma.
+    pub fn from_image(image: &Image, color: &ColorInfo) -> Self {
+        Self::from_image_with(image, color, &ChromaOptions::default())
+    }
+
+    /// [`from_image`](Self::from_image) upsampling chroma with `options`.
+    pub fn from_image_with(image: &Image, color: &ColorInfo, options: &ChromaOptions) -> Self {
+        let format = image.format();
+        let coding = format_coding(format, color);
+        let (width, height) = (image.width(), image.height());
+
+        let mut planes: [Vec<f32>; 3] = Default::default();
+        let mut alpha = None;
+
+        let matrix = if format.is_rgb() {
+            let components = format.planes()[0].components;
+            let order = match format {
+                PixelFormat::Bgra8 => [2, 1, 0],
+                _ => [0, 1, 2],
+            };
+            for (plane, component) in planes.iter_mut().zip(order) {
+                *plane = read_component(image, 0, component);
+                normalize(plane, |code| coding.decode_luma(code));
+            }
+            if components == 4 {
+                let mut values = read_component(image, 0, 3);
+                normalize(&mut values, |code| coding.decode_luma(code));
+                alpha = Some(values);
+            }
+            None
+        } else {
+            let matrix = yuv_matrix(format, color);
+            let gbr = matrix == ColorSpace::Rgb;
+
+            planes[0] = read_component(image, 0, 0);
+            normalize(&mut planes[0], |code| coding.decode_luma(code));
+
+            if format.is_gray() {
+                planes[1] = vec![0.0; width * height];
+                planes[2] = vec![0.0; width * height];
+            } else {
+                let chroma = match format.plane_count() {
+                    2 => [(1, 0), (1, 1)],
+                    _ => [(1, 0), (2, 0)],
+                };
+                let shift = format.chroma_shift();
+                for (target, (index, component)) in planes[1..].iter_mut().zip(chroma) {
+                    let mut values = read_component(image, index, component);
+                    normalize(&mut values, |code| coding.decode_chroma(code, gbr));
+                    *target = chroma::resample(&values, (width, height), shift, (0, 0), options);
+                }
+            }
+            Some(matrix)
+        };
+
+        Self {
+            width,
+            height,
+            matrix,
+            planes,
+            alpha,
+        }
+    }
+
+    /// Pack into an image of `format` described by `color`, converting
+    /// between RGB and YCbCr as needed. Missing alpha is opaque.
+    pub fn to_image(&self, format: PixelFormat, color: &ColorInfo) -> Result<Image, ImageError> {
+        self.to_image_with(format, color, &ChromaOptions::default())
+    }
+
+    /// [`to_image`](Self::to_image) downsampling chroma with `options`.
+    pub fn to_image_with(
+        &self,
+        format: PixelFormat,
+        color: &ColorInfo,
+        options: &ChromaOptions,
+    ) -> Result<Image, ImageError> {
+        let converted;
+        let planar = match (format.is_rgb(), self.matrix) {
+            (true, Some(_)) => {
+                converted = self.clone().into_rgb()?;
+                &converted
+            }
+            (false, matrix) if matrix != Some(yuv_matrix(format, color)) => {
+                converted = self.clone().into_ycbcr(yuv_matrix(format, color))?;
+                &converted
+            }
+            _ => self,
+        };
+
+        let coding = format_coding(format, color);
+        let mut image = Image::new(format, self.width, self.height)?;
+
+        if format.is_rgb() {
+            let order = match format {
+                PixelFormat::Bgra8 => [2, 1, 0],
+                _ => [0, 1, 2],
+            };
+            for (plane, component) in planar.planes.iter().zip(order) {
+                write_component(&mut image, 0, component, plane, |v| coding.encode_luma(v));
+            }
+            if format.has_alpha() {
+                let opaque;
+                let alpha = match &planar.alpha {
+                    Some(alpha) => alpha,
+                    None => {
+                        opaque = vec![1.0; self.width * self.height];
+                        &opaque
+                    }
+                };
+                write_component(&mut image, 0, 3, alpha, |v| coding.encode_luma(v));
+            }
+            return Ok(image);
+        }
+
+        let gbr = yuv_matrix(format, color) == ColorSpace::Rgb;
+        write_component(&mut image, 0, 0, &planar.planes[0], |v| {
+            coding.encode_luma(v)
+        });
+
+        if !format.is_gray() {
+            let shift = format.chroma_shift();
+            let chroma = match format.plane_count() {
+                2 => [(1, 0), (1, 1)],
+                _ => [(1, 0), (2, 0)],
+            };
+            for (values, (index, component)) in planar.planes[1..].iter().zip(chroma) {
+                let size = (self.width, self.height);
+                let values = chroma::resample(values, size, (0, 0), shift, options);
+                write_component(&mut image, index, component, &values, |v| {
+                    coding.encode_chroma(v, gbr)
+                });
+            }
+        }
+        Ok(image)
+    }
+
+    /// Convert Y, Cb and Cr planes to R, G and B.
+    pub fn into_rgb(mut self) -> Result<Self, ImageError> {
+        if let Some(space) = self.matrix {
+            let matrix = ColorMatrix::new(space)?;
+            let [y, cb, cr] = std::mem::take(&mut self.planes);
+            let mut rgb: [Vec<f32>; 3] = std::array::from_fn(|_| vec![0.0; y.len()]);
+            let [r, g, b] = &mut rgb;
+            ycbcr_to_rgb(
+                &matrix,
+                [&y, &cb, &cr],
+                Coding::FLOAT,
+                [r, g, b],
+                Coding::FLOAT,
+            );
+            self.planes = rgb;
+            self.matrix = None;
+        }
+        Ok(self)
+    }
+
+    /// Convert to Y, Cb and Cr planes of `space`.
+    pub fn into_ycbcr(self, space: ColorSpace) -> Result<Self, ImageError> {
+        if self.matrix == Some(space) {
+            return Ok(self);
+        }
+
+        let matrix = ColorMatrix::new(space)?;
+        let mut planar = self.into_rgb()?;
+        let [r, g, b] = std::mem::take(&mut planar.planes);
+        let mut ycbcr: [Vec<f32>; 3] = std::array::from_fn(|_| vec![0.0; r.len()]);
+        let [y, cb, cr] = &mut ycbcr;
+        rgb_to_ycbcr(
+            &matrix,
+            [&r, &g, &b],
+            Coding::FLOAT,
+            [y, cb, cr],
+            Coding::FLOAT,
+        );
+        planar.planes = ycbcr;
+        planar.matrix = Some(space);
+        Ok(planar)
+    }
+}
+
+/// Convert `image` described by `from` to `format` described by `to`.
+pub fn convert(
+    image: &Image,
+    from: &ColorInfo,
+    format: PixelFormat,
+    to: &ColorInfo,
+) -> Result<Image, ImageError> {
+    convert_with(image, from, format, to, &ChromaOptions::default())
+}
+
+/// [`convert`] resampling chroma with `options`.
+pub fn convert_with(
+    image: &Image,
+    from: &ColorInfo,
+    format: PixelFormat,
+    to: &ColorInfo,
+    options: &ChromaOptions,
+) -> Result<Image, ImageError> {
+    let same_coding = format.is_rgb()
+        || (yuv_matrix(format, from) == yuv_matrix(format, to) && from.range == to.range);
+    if image.format() == format && same_coding {
+        return Ok(image.clone());
+    }
+    PlanarImage::from_image_with(image, from, options).to_image_with(format, to, options)
+}
+
+/// Scale of P010 samples, which keep 10 bits in the high bits.
+fn sample_scale(format: PixelFormat) -> f32 {
+    if format == PixelFormat::P010 {
+        64.0
+    } else {
+        1.0
+    }
+}
+
+fn normalize(values: &mut [f32], decode: impl Fn(f64) -> f64) {
+    for value in values {
+        *value = decode(*value as f64) as f32;
+    }
+}
+
+/// Code values of `component` of plane `index`, row by row.
+pub(super) fn read_component(image: &Image, index: usize, component: usize) -> Vec<f32> {
+    fn read<T: Sample>(image: &Image, index: usize, component: usize, scale: f32) -> Vec<f32> {
+        let Some(plane) = image.plane(index) else {
+            return Vec::new();
+        };
+        let mut values = Vec::with_capacity(plane.width * plane.height);
+        for y in 0..plane.height {
+            if let Some(row) = image.row::<T>(index, y) {
+                values.extend(
+                    row.iter()
+                        .skip(component)
+                        .step_by(plane.components)
+                        .map(|&sample| sample.to_code() as f32 / scale),
+                );
+            }
+        }
+        values
+    }
+
+    let scale = sample_scale(image.format());
+    match image.format().sample_type() {
+        SampleType::U8 => read::<u8>(image, index, component, scale),
+        SampleType::U16 => read::<u16>(image, index, component, scale),
+        SampleType::F32 => read::<f32>(image, index, component, scale),
+    }
+}
+
+pub(super) fn write_component(
+    image: &mut Image,
+    index: usize,
+    component: usize,
+    values: &[f32],
+    encode: impl Fn(f64) -> f64,
+) {
+    fn write<T: Sample>(
+        image: &mut Image,
+        index: usize,
+        component: usize,
+        values: &[f32],
+        encode: impl Fn(f64) -> f64,
+    ) {
+        let Some(&plane) = image.plane(index) else {
+            return;
+        };
+        let scale = sample_scale(image.format()) as f64;
+        // Round before scaling so P010 keeps its low bits clear.
+        let code = |value: f64| match scale {
+            1.0 => encode(value),
+            _ => encode(value).round() * scale,
+        };
+        for (y, values) in values.chunks_exact(plane.width).enumerate() {
+            if let Some(row) = image.row_mut::<T>(index, y) {
+                for (sample, &value) in row
+                    .iter_mut()
+                    .skip(component)
+                    .step_by(plane.components)
+                    .zip(values)
+                {
+                    *sample = T::from_code(code(value as f64));
+                }
+            }
+        }
+    }
+
+    match image.format().sample_type() {
+        SampleType::U8 => write::<u8>(image, index, component, values, encode),
+        SampleType::U16 => write::<u16>(image, index, component, values, encode),
+        SampleType::F32 => write::<f32>(image, index, component, values, encode),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `[Y, Cb, Cr]` codes of full intensity red, green and blue.
+    type Vectors = [[u16; 3]; 3];
+
+    /// Reference codes from the BT.601, BT.709 and BT.2020 equations with
+    /// the quantization of H.273 / BT.2100.
+    const REFERENCE: [(ColorSpace, ColorRange, u32, Vectors); 18] = [
+        (
+            ColorSpace::Bt601,
+            ColorRange::Limited,
+            8,
+            [[81, 90, 240], [145, 54, 34], [41, 240, 110]],
+        ),
+        (
+            ColorSpace::Bt601,
+            ColorRange::Limited,
+            10,
+            [[326, 361, 960], [578, 215, 137], [164, 960, 439]],
+        ),
+        (
+            ColorSpace::Bt601,
+            ColorRange::Limited,
+            16,
+            [
+                [20859, 23092, 61440],
+                [37006, 13772, 8759],
+                [10487, 61440, 28105],
+            ],
+        ),
+        (
+            ColorSpace::Bt601,
+            ColorRange::Full,
+            8,
+            [[76, 85, 255], [150, 44, 21], [29, 255, 107]],
+        ),
+        (
+            ColorSpace::Bt601,
+            ColorRange::Full,
+            10,
+            [[306, 339, 1023], [601, 173, 84], [117, 1023, 429]],
+        ),
+        (
+            ColorSpace::Bt601,
+            ColorRange::Full,
+            16,
+            [
+                [19595, 21710, 65535],
+                [38469, 11059, 5329],
+                [7471, 65535, 27439],
+            ],
+        ),
+        (
+            ColorSpace::Bt709,
+            ColorRange::Limited,
+            8,
+            [[63, 102, 240], [173, 42, 26], [32, 240, 118]],
+        ),
+        (
+            ColorSpace::Bt709,
+            ColorRange::Limited,
+            10,
+            [[250, 409, 960], [691, 167, 105], [127, 960, 471]],
+        ),
+        (
+            ColorSpace::Bt709,
+            ColorRange::Limited,
+            16,
+            [
+                [16015, 26198, 61440],
+                [44193, 10666, 6725],
+                [8144, 61440, 30139],
+            ],
+        ),
+        (
+            ColorSpace::Bt709,
+            ColorRange::Full,
+            8,
+            [[54, 99, 255], [182, 30, 12], [18, 255, 116]],
+        ),
+        (
+            ColorSpace::Bt709,
+            ColorRange::Full,
+            10,
+            [[217, 395, 1023], [732, 118, 47], [74, 1023, 465]],
+        ),
+        (
+            ColorSpace::Bt709,
+            ColorRange::Full,
+            16,
+            [
+                [13933, 25260, 65535],
+                [46871, 7509, 3005],
+                [4732, 65535, 29763],
+            ],
+        ),
+        (
+            ColorSpace::Bt2020Ncl,
+            ColorRange::Limited,
+            8,
+            [[74, 97, 240], [164, 47, 25], [29, 240, 119]],
+        ),
+        (
+            ColorSpace::Bt2020Ncl,
+            ColorRange::Limited,
+            10,
+            [[294, 387, 960], [658, 189, 100], [116, 960, 476]],
+        ),
+        (
+            ColorSpace::Bt2020Ncl,
+            ColorRange::Limited,
+            16,
+            [
+                [18824, 24761, 61440],
+                [42107, 12103, 6402],
+                [7421, 61440, 30462],
+            ],
+        ),
+        (
+            ColorSpace::Bt2020Ncl,
+            ColorRange::Full,
+            8,
+            [[67, 92, 255], [173, 36, 11], [15, 255, 118]],
+        ),
+        (
+            ColorSpace::Bt2020Ncl,
+            ColorRange::Full,
+            10,
+            [[269, 369, 1023], [694, 143, 42], [61, 1023, 471]],
+        ),
+        (
+            ColorSpace::Bt2020Ncl,
+            ColorRange::Full,
+            16,
+            [
+                [17216, 23617, 65535],
+                [44433, 9151, 2636],
+                [3886, 65535, 30133],
+            ],
+        ),
+    ];
+
+    /// Every `step`-th RGB code triple of `bits` depth, as planes.
+    fn rgb_grid(bits: u32, step: usize) -> [Vec<u16>; 3] {
+        let codes = (0..1usize << bits)
+            .step_by(step)
+            .chain([(1 << bits) - 1])
+            .map(|code| code as u16)
+            .collect::<Vec<_>>();
+        let mut planes: [Vec<u16>; 3] = Default::default();
+        for &r in &codes {
+            for &g in &codes {
+                for &b in &codes {
+                    planes[0].push(r);
+                    planes[1].push(g);
+                    planes[2].push(b);
+                }
+            }
+        }
+        planes
+    }
+
+    fn to_ycbcr(space: ColorSpace, rgb: &[Vec<u16>; 3], from: Coding, to: Coding) -> [Vec<u16>; 3] {
+        let mut ycbcr: [Vec<u16>; 3] = std::array::from_fn(|_| vec![0; rgb[0].len()]);
+        let [y, cb, cr] = &mut ycbcr;
+        let [r, g, b] = rgb;
+        rgb_to_ycbcr(
+            &ColorMatrix::new(space).unwrap(),
+            [r, g, b],
+            from,
+            [y, cb, cr],
+            to,
+        );
+        ycbcr
+    }
+
+    fn to_rgb(space: ColorSpace, ycbcr: &[Vec<u16>; 3], from: Coding, to: Coding) -> [Vec<u16>; 3] {
+        let mut rgb: [Vec<u16>; 3] = std::array::from_fn(|_| vec![0; ycbcr[0].len()]);
+        let [r, g, b] = &mut rgb;
+        let [y, cb, cr] = ycbcr;
+        ycbcr_to_rgb(
+            &ColorMatrix::new(space).unwrap(),
+            [y, cb, cr],
+            from,
+            [r, g, b],
+            to,
+        );
+        rgb
+    }
+
+    fn max_error(a: &[Vec<u16>; 3], b: &[Vec<u16>; 3]) -> u16 {
+        (0..3)
+            .flat_map(|plane| a[plane].iter().zip(&b[plane]).map(|(a, b)| a.abs_diff(*b)))
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn reference_vectors() {
+        for (space, range, bits, vectors) in REFERENCE {
+            let max = ((1u32 << bits) - 1) as u16;
+            let rgb = [vec![max, 0, 0], vec![0, max, 0], vec![0, 0, max]];
+            let coding = Coding::new(range, bits);
+
+            let ycbcr = to_ycbcr(space, &rgb, Coding::rgb(bits), coding);
+            for (color, expected) in vectors.iter().enumerate() {
+                let found = [0, 1, 2].map(|plane| ycbcr[plane][color]);
+                assert_eq!(
+                    &found, expected,
+                    "{space:?} {range:?} {bits} bit, color {color}"
+                );
+            }
+
+            // Decoding the rounded codes lands back on the primaries.
+            let decoded = to_rgb(space, &ycbcr, coding, Coding::rgb(bits));
+            assert!(
+                max_error(&decoded, &rgb) <= 1,
+                "{space:?} {range:?} {bits} bit"
+            );
+        }
+    }
+
+    #[test]
+    fn float_round_trip_is_exact() {
+        let matrices = [
+            ColorSpace::Rgb,
+            ColorSpace::Bt601,
+            ColorSpace::Bt709,
+            ColorSpace::Bt2020Ncl,
+            ColorSpace::Fcc,
+            ColorSpace::Bt470bg,
+            ColorSpace::Smpte170m,
+            ColorSpace::Smpte240m,
+            ColorSpace::Ycgco,
+        ];
+        let steps = (0..=8).map(|step| step as f32 / 8.0).collect::<Vec<_>>();
+        let mut rgb: [Vec<f32>; 3] = Default::default();
+        for &r in &steps {
+            for &g in &steps {
+                for &b in &steps {
+                    rgb[0].push(r);
+                    rgb[1].push(g);
+                    rgb[2].push(b);
+                }
+            }
+        }
+
+        for space in matrices {
+            let planar = PlanarImage {
+                width: rgb[0].len(),
+                height: 1,
+                matrix: None,
+                planes: rgb.clone(),
+                alpha: None,
+            };
+            let back = planar.into_ycbcr(space).unwrap().into_rgb().unwrap();
+            for (plane, expected) in back.planes.iter().zip(&rgb) {
+                for (value, expected) in plane.iter().zip(expected) {
+                    assert!((value - expected).abs() < 1e-5, "{space:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integer_round_trips_stay_close() {
+        for (space, range, bits, tolerance) in [
+            (ColorSpace::Bt709, ColorRange::Limited, 8, 2),
+            (ColorSpace::Bt709, ColorRange::Full, 8, 1),
+            (ColorSpace::Bt601, ColorRange::Limited, 10, 2),
+            (ColorSpace::Bt2020Ncl, ColorRange::Limited, 10, 2),
+            (ColorSpace::Bt2020Ncl, ColorRange::Full, 16, 1),
+        ] {
+            let rgb = rgb_grid(bits, (1 << bits) / 16);
+            let coding = Coding::new(range, bits);
+            let ycbcr = to_ycbcr(space, &rgb, Coding::rgb(bits), coding);
+            let back = to_rgb(space, &ycbcr, coding, Coding::rgb(bits));
+            let error = max_error(&back, &rgb);
+            assert!(
+                error <= tolerance,
+                "{space:?} {range:?} {bits} bit: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn constant_luminance_matrices_are_rejected() {
+        for space in [ColorSpace::Bt2020Cl, ColorSpace::Smpte2085] {
+            assert_eq!(
+                ColorMatrix::new(space),
+                Err(ImageError::UnsupportedMatrix(space))
+            );
+
+            let color = ColorInfo {
+                matrix: space,
+                ..ColorInfo::BT709
+            };
+            let image = Image::new(PixelFormat::Rgb24, 4, 2).unwrap();
+            assert_eq!(
+                convert(&image, &color, PixelFormat::Yuv444p, &color).unwrap_err(),
+                ImageError::UnsupportedMatrix(space)
+            );
+        }
+    }
+
+    #[test]
+    fn constant_luminance_ycbcr_keeps_its_matrix() {
+        let color = ColorInfo {
+            matrix: ColorSpace::Bt2020Cl,
+            ..ColorInfo::BT709
+        };
+        let image = Image::new(PixelFormat::Yuv420p, 4, 2).unwrap();
+        assert!(convert(&image, &color, PixelFormat::Yuv444p, &color).is_ok());
+    }
+}
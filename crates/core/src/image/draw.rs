@@ -0,0 +1,127 @@
+//! Drawing of solid colors into images.
+
+use super::{
+    Image, ImageError,
+    convert::Sample,
+    format::{PixelFormat, SampleType},
+};
+use crate::color::{ColorInfo, ColorRange, ColorSpace};
+
+/// Code values of every component of every plane for non-linear `rgba`.
+///
+/// RGB formats are full range, YUV and gray formats follow `color`. Fails
+/// for matrices [`ColorSpace::rgb_to_ycbcr`] does not support.
+pub fn plane_values(
+    format: PixelFormat,
+    rgba: [f64; 4],
+    color: &ColorInfo,
+) -> Result<Vec<Vec<f64>>, ImageError> {
+    let [r, g, b, a] = rgba;
+    let bits = format.bit_depth();
+
+    if format.sample_type() == SampleType::F32 {
+        return Ok(vec![vec![r, g, b, a]]);
+    }
+    if format.is_rgb() {
+        let full = |value: f64| ColorRange::Full.quantize_luma(value, bits);
+        let values = match format {
+            PixelFormat::Bgra8 => vec![full(b), full(g), full(r), full(a)],
+            _ => vec![full(r), full(g), full(b), full(a)],
+        };
+        return Ok(vec![values[..format.planes()[0].components].to_vec()]);
+    }
+
+    let matrix = match color.matrix {
+        ColorSpace::Rgb if format.is_gray() => ColorSpace::Bt709,
+        matrix => matrix,
+    };
+    let [y, cb, cr] = matrix
+        .rgb_to_ycbcr([r, g, b])
+        .ok_or(ImageError::UnsupportedMatrix(matrix))?;
+    let (y, cb, cr) = match matrix {
+        ColorSpace::Rgb => (
+            color.range.quantize_luma(y, bits),
+            color.range.quantize_luma(cb + 0.5, bits),
+            color.range.quantize_luma(cr + 0.5, bits),
+        ),
+        _ => (
+            color.range.quantize_luma(y, bits),
+            color.range.quantize_chroma(cb, bits),
+            color.range.quantize_chroma(cr, bits),
+        ),
+    };
+
+    // P010 keeps its 10 bits in the high bits of each sample.
+    let scale = if format == PixelFormat::P010 {
+        64.0
+    } else {
+        1.0
+    };
+
+    Ok(match format.plane_count() {
+        1 => vec![vec![y * scale]],
+        2 => vec![vec![y * scale], vec![cb * scale, cr * scale]],
+        _ => vec![vec![y], vec![cb], vec![cr]],
+    })
+}
+
+fn fill_plane<T: Sample>(
+    image: &mut Image,
+    index: usize,
+    (x, y, width, height): (usize, usize, usize, usize),
+    values: &[f64],
+) {
+    let (shift_x, shift_y) = {
+        let descriptor = image.format().planes()[index];
+        (descriptor.shift_x, descriptor.shift_y)
+    };
+    let Some(&plane) = image.plane(index) else {
+        return;
+    };
+
+    let x0 = (x >> shift_x).min(plane.width);
+    let x1 = (x + width).div_ceil(1 << shift_x).min(plane.width);
+    let y0 = (y >> shift_y).min(plane.height);
+    let y1 = (y + height).div_ceil(1 << shift_y).min(plane.height);
+
+    let pixel = values.iter().map(|&v| T::from_code(v)).collect::<Vec<_>>();
+
+    for row in y0..y1 {
+        let Some(row) = image.row_mut::<T>(index, row) else {
+            return;
+        };
+        for chunk in
+            row[x0 * plane.components..x1 * plane.components].chunks_exact_mut(plane.components)
+        {
+            chunk.copy_from_slice(&pixel);
+        }
+    }
+}
+
+/// Fill the rectangle at `x, y` of `width` by `height` pixels, clipped to
+/// the image, with non-linear `rgba` in `0.0..=1.0`.
+///
+/// Subsampled chroma planes cover every chroma sample the rectangle touches.
+pub fn fill_rect(
+    image: &mut Image,
+    rect: (usize, usize, usize, usize),
+    rgba: [f64; 4],
+    color: &ColorInfo,
+) -> Result<(), ImageError> {
+    let values = plane_values(image.format(), rgba, color)?;
+
+    for (index, values) in values.iter().enumerate() {
+        match image.format().sample_type() {
+            SampleType::U8 => fill_plane::<u8>(image, index, rect, values),
+            SampleType::U16 => fill_plane::<u16>(image, index, rect, values),
+            SampleType::F32 => fill_plane::<f32>(image, index, rect, values),
+        }
+    }
+    Ok(())
+}
+
+/// Fill the whole image with `rgba`.
+pub fn fill(image: &mut Image, rgba: [f64; 4], color: &ColorInfo) -> Result<(), ImageError> {
+    let rect = (0, 0, image.width(), image.height());
+    fill_rect(image, rect, rgba, color)
+}
@@ -0,0 +1,183 @@
+//! Placement of the picture of one frame size in another.
+//!
+//! Plans compare displayed sizes, pixels stretched by their sample
+//! aspect, so anamorphic pictures keep their shape.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Image, ImageError, convert::Sample, draw, format::SampleType, scale::Scaler};
+use crate::{color::ColorInfo, profile::Profile};
+use mcf_math::{
+    geometry::{self, Coordinate},
+    rational::Ratio,
+};
+
+/// How a picture fills a frame of another shape.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FitMode {
+    /// Whole picture shown, letterboxed or pillarboxed.
+    #[default]
+    Fit,
+    /// Whole frame covered, the picture cropped.
+    Fill,
+    /// Picture scaled to the frame, ignoring its shape.
+    Stretch,
+    /// One picture pixel per frame pixel, centered and cropped.
+    Native,
+}
+
+/// Area of the source read and area of the target frame covered, in
+/// pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FitPlan {
+    pub source: geometry::Rect,
+    pub target: geometry::Rect,
+}
+
+/// Rect of `width` by `height` centered in `frame`.
+fn centered(frame: &geometry::Frame, width: f64, height: f64) -> geometry::Rect {
+    let left = (frame.width as f64 - width) / 2.0;
+    let top = (frame.height as f64 - height) / 2.0;
+    geometry::Rect {
+        top: top as Coordinate,
+        left: left as Coordinate,
+        right: (left + width) as Coordinate,
+        bottom: (top + height) as Coordinate,
+    }
+}
+
+/// Effective sample aspect of `profile`, square when unknown.
+pub(crate) fn sample_aspect(profile: &Profile) -> Ratio {
+    profile
+        .effective_sample_aspect()
+        .unwrap_or(Ratio::from_integer(1))
+}
+
+impl FitPlan {
+    /// Plan showing a `source` frame of pixels `source_aspect` wide in a
+    /// `target` frame of pixels `target_aspect` wide.
+    pub fn new(
+        mode: FitMode,
+        (source, source_aspect): (&geometry::Frame, Ratio),
+        (target, target_aspect): (&geometry::Frame, Ratio),
+    ) -> Self {
+        let whole =
+            |frame: &geometry::Frame| centered(frame, frame.width as f64, frame.height as f64);
+
+        // Display widths divided by pixel widths.
+        let source_pixel =
+            source.calculate_display_width(source_aspect) as f64 / source.width as f64;
+        let target_pixel =
+            target.calculate_display_width(target_aspect) as f64 / target.width as f64;
+        let scale_x =
+            source.calculate_display_scale_width(target.width, source_aspect, target_aspect);
+        let scale_y = source.calculate_scale_height(target.height);
+
+        match mode {
+            FitMode::Stretch => Self {
+                source: whole(source),
+                target: whole(target),
+            },
+            FitMode::Fit => {
+                let scale = scale_x.min(scale_y) as f64;
+                let width = source.width as f64 * source_pixel * scale / target_pixel;
+                Self {
+                    source: whole(source),
+                    target: centered(target, width, source.height as f64 * scale),
+                }
+            }
+            FitMode::Fill => {
+                let scale = scale_x.max(scale_y) as f64;
+                let width = target.width as f64 * target_pixel / scale / source_pixel;
+                Self {
+                    source: centered(source, width, target.height as f64 / scale),
+                    target: whole(target),
+                }
+            }
+            FitMode::Native => {
+                let width = source.width.min(target.width) as f64;
+                let height = source.height.min(target.height) as f64;
+                Self {
+                    source: centered(source, width, height),
+                    target: centered(target, width, height),
+                }
+            }
+        }
+    }
+
+    /// Plan showing frames of `source` in frames of `output`.
+    pub fn for_profiles(mode: FitMode, source: &Profile, output: &Profile) -> Self {
+        Self::new(
+            mode,
+            (&source.frame, sample_aspect(source)),
+            (&output.frame, sample_aspect(output)),
+        )
+    }
+}
+
+/// Frame of `width` by `height` showing `image` as planned, the uncovered
+/// area filled with non-linear `background`. The covered area snaps to
+/// whole chroma samples.
+pub fn fit(
+    image: &Image,
+    plan: &FitPlan,
+    (width, height): (usize, usize),
+    scaler: &Scaler,
+    background: [f64; 4],
+    color: &ColorInfo,
+) -> Result<Image, ImageError> {
+    let format = image.format();
+    let mut output = Image::new(format, width, height)?;
+
+    let (shift_x, shift_y) = format.chroma_shift();
+    let snap = |value: Coordinate, shift: u32, limit: usize| {
+        let step = (1 << shift) as f64;
+        ((value as f64 / step).round() * step).clamp(0.0, limit as f64) as usize
+    };
+    let (left, right) = (
+        snap(plan.target.left, shift_x, width),
+        snap(plan.target.right, shift_x, width),
+    );
+    let (top, bottom) = (
+        snap(plan.target.top, shift_y, height),
+        snap(plan.target.bottom, shift_y, height),
+    );
+
+    if (left, top, right, bottom) != (0, 0, width, height) {
+        draw::fill(&mut output, background, color)?;
+    }
+    if right <= left || bottom <= top {
+        return Ok(output);
+    }
+
+    let scaled = scaler.scale_region(image, &plan.source, right - left, bottom - top)?;
+    for index in 0..format.plane_count() {
+        match format.sample_type() {
+            SampleType::U8 => paste_plane::<u8>(&mut output, &scaled, index, (left, top)),
+            SampleType::U16 => paste_plane::<u16>(&mut output, &scaled, index, (left, top)),
+            SampleType::F32 => paste_plane::<f32>(&mut output, &scaled, index, (left, top)),
+        }
+    }
+    Ok(output)
+}
+
+/// Copy plane `index` of `image` into `output` at luma position `x, y`.
+fn paste_plane<T: Sample>(output: &mut Image, image: &Image, index: usize, (x, y): (usize, usize)) {
+    let descriptor = image.format().planes()[index];
+    let Some(&plane) = image.plane(index) else {
+        return;
+    };
+    let x = (x >> descriptor.shift_x) * plane.components;
+    let y = y >> descriptor.shift_y;
+
+    for row in 0..plane.height {
+        let (Some(source), Some(target)) = (
+            image.row::<T>(index, row),
+            output.row_mut::<T>(index, y + row),
+        ) else {
+            return;
+        };
+        let length = source.len().min(target.len().saturating_sub(x));
+        target[x..x + length].copy_from_slice(&source[..length]);
+    }
+}
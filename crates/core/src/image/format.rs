@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+
+/// Element type of the samples of a [`PixelFormat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SampleType {
+    U8,
+    U16,
+    F32,
+}
+
+impl SampleType {
+    pub fn size(&self) -> usize {
+        match self {
+            SampleType::U8 => 1,
+            SampleType::U16 => 2,
+            SampleType::F32 => 4,
+        }
+    }
+}
+
+/// Layout of one plane of a [`PixelFormat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlaneDescriptor {
+    /// Interleaved samples per pixel, e.g. `4` for RGBA or `2` for NV12 UV.
+    pub components: usize,
+    /// Horizontal subsampling as power of two, `1` halves the width.
+    pub shift_x: u32,
+    /// Vertical subsampling as power of two, `1` halves the height.
+    pub shift_y: u32,
+}
+
+impl PlaneDescriptor {
+    const fn new(components: usize, shift_x: u32, shift_y: u32) -> Self {
+        Self {
+            components,
+            shift_x,
+            shift_y,
+        }
+    }
+
+    /// Plane width in pixels for an image `width` pixels wide.
+    pub fn width(&self, width: usize) -> usize {
+        width.div_ceil(1 << self.shift_x)
+    }
+
+    /// Plane height in rows for an image `height` rows high.
+    pub fn height(&self, height: usize) -> usize {
+        height.div_ceil(1 << self.shift_y)
+    }
+}
+
+const PACKED_1: &[PlaneDescriptor] = &[PlaneDescriptor::new(1, 0, 0)];
+const PACKED_3: &[PlaneDescriptor] = &[PlaneDescriptor::new(3, 0, 0)];
+const PACKED_4: &[PlaneDescriptor] = &[PlaneDescriptor::new(4, 0, 0)];
+const PLANAR_420: &[PlaneDescriptor] = &[
+    PlaneDescriptor::new(1, 0, 0),
+    PlaneDescriptor::new(1, 1, 1),
+    PlaneDescriptor::new(1, 1, 1),
+];
+const PLANAR_422: &[PlaneDescriptor] = &[
+    PlaneDescriptor::new(1, 0, 0),
+    PlaneDescriptor::new(1, 1, 0),
+    PlaneDescriptor::new(1, 1, 0),
+];
+const PLANAR_444: &[PlaneDescriptor] = &[
+    PlaneDescriptor::new(1, 0, 0),
+    PlaneDescriptor::new(1, 0, 0),
+    PlaneDescriptor::new(1, 0, 0),
+];
+const SEMI_PLANAR_420: &[PlaneDescriptor] =
+    &[PlaneDescriptor::new(1, 0, 0), PlaneDescriptor::new(2, 1, 1)];
+
+/// Memory layout of picture samples.
+///
+/// Planar YUV formats store Y, U and V planes in this order, semi-planar
+/// formats store Y followed by interleaved UV.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(C)]
+pub enum PixelFormat {
+    Gray8,
+    Gray16,
+    Rgb24,
+    #[default]
+    Rgba8,
+    Bgra8,
+    Rgba16,
+    /// RGBA with `f32` samples in `0.0..=1.0`.
+    RgbaF32,
+    Yuv420p,
+    Yuv422p,
+    Yuv444p,
+    /// 10-bit samples in the low bits of `u16`.
+    Yuv420p10,
+    Yuv422p10,
+    Yuv444p10,
+    /// Y plane followed by interleaved UV, 4:2:0.
+    Nv12,
+    /// 10-bit NV12 with samples in the high bits of `u16`.
+    P010,
+}
+
+impl PixelFormat {
+    pub fn planes(&self) -> &'static [PlaneDescriptor] {
+        match self {
+            PixelFormat::Gray8 | PixelFormat::Gray16 => PACKED_1,
+            PixelFormat::Rgb24 => PACKED_3,
+            PixelFormat::Rgba8
+            | PixelFormat::Bgra8
+            | PixelFormat::Rgba16
+            | PixelFormat::RgbaF32 => PACKED_4,
+            PixelFormat::Yuv420p | PixelFormat::Yuv420p10 => PLANAR_420,
+            PixelFormat::Yuv422p | PixelFormat::Yuv422p10 => PLANAR_422,
+            PixelFormat::Yuv444p | PixelFormat::Yuv444p10 => PLANAR_444,
+            PixelFormat::Nv12 | PixelFormat::P010 => SEMI_PLANAR_420,
+        }
+    }
+
+    pub fn plane_count(&self) -> usize {
+        self.planes().len()
+    }
+
+    pub fn sample_type(&self) -> SampleType {
+        match self {
+            PixelFormat::Gray8
+            | PixelFormat::Rgb24
+            | PixelFormat::Rgba8
+            | PixelFormat::Bgra8
+            | PixelFormat::Yuv420p
+            | PixelFormat::Yuv422p
+            | PixelFormat::Yuv444p
+            | PixelFormat::Nv12 => SampleType::U8,
+            PixelFormat::Gray16
+            | PixelFormat::Rgba16
+            | PixelFormat::Yuv420p10
+            | PixelFormat::Yuv422p10
+            | PixelFormat::Yuv444p10
+            | PixelFormat::P010 => SampleType::U16,
+            PixelFormat::RgbaF32 => SampleType::F32,
+        }
+    }
+
+    /// Significant bits per sample, `32` for float formats.
+    pub fn bit_depth(&self) -> u32 {
+        match self {
+            PixelFormat::Gray16 | PixelFormat::Rgba16 => 16,
+            PixelFormat::Yuv420p10
+            | PixelFormat::Yuv422p10
+            | PixelFormat::Yuv444p10
+            | PixelFormat::P010 => 10,
+            PixelFormat::RgbaF32 => 32,
+            _ => 8,
+        }
+    }
+
+    pub fn is_rgb(&self) -> bool {
+        matches!(
+            self,
+            PixelFormat::Rgb24
+                | PixelFormat::Rgba8
+                | PixelFormat::Bgra8
+                | PixelFormat::Rgba16
+                | PixelFormat::RgbaF32
+        )
+    }
+
+    pub fn is_yuv(&self) -> bool {
+        !self.is_rgb() && !self.is_gray()
+    }
+
+    pub fn is_gray(&self) -> bool {
+        matches!(self, PixelFormat::Gray8 | PixelFormat::Gray16)
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.planes()[0].components == 4
+    }
+
+    /// Chroma subsampling as `(shift_x, shift_y)`, `(0, 0)` for RGB and gray.
+    pub fn chroma_shift(&self) -> (u32, u32) {
+        self.planes()
+            .get(1)
+            .map_or((0, 0), |plane| (plane.shift_x, plane.shift_y))
+    }
+
+    /// Bytes needed for a tightly packed image.
+    pub fn image_size(&self, width: usize, height: usize) -> usize {
+        self.planes()
+            .iter()
+            .map(|plane| plane.width(width) * plane.components * plane.height(height))
+            .sum::<usize>()
+            * self.sample_type().size()
+    }
+}
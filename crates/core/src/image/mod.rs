@@ -0,0 +1,348 @@
+//! Picture data stored in a [`Buffer`].
+
+pub mod chroma;
+pub mod convert;
+pub mod draw;
+pub mod fit;
+pub mod format;
+pub mod resample;
+pub mod scale;
+
+use std::fmt;
+
+use crate::{
+    color::ColorSpace,
+    structures::{
+        buffer::{Buffer, BufferElement, ElementType},
+        pool::{BufferPool, PoolError},
+    },
+};
+use format::{PixelFormat, SampleType};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// Width or height is zero.
+    EmptySize,
+    /// Buffer element type does not match the pixel format.
+    SampleTypeMismatch(PixelFormat),
+    /// Stride is smaller than a row or not a multiple of the sample size.
+    InvalidStride { plane: usize, stride: usize },
+    /// Buffer does not hold all the planes.
+    BufferTooSmall { needed: usize, len: usize },
+    /// Number of strides does not match the number of planes.
+    PlaneCount { expected: usize, found: usize },
+    /// Pool could not hand out the image memory.
+    Pool(PoolError),
+    /// RGB and YCbCr cannot be converted with the matrix.
+    UnsupportedMatrix(ColorSpace),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::EmptySize => write!(f, "image has zero width or height"),
+            ImageError::SampleTypeMismatch(format) => {
+                write!(f, "buffer element type does not match {format:?}")
+            }
+            ImageError::InvalidStride { plane, stride } => {
+                write!(f, "invalid stride {stride} for plane {plane}")
+            }
+            ImageError::BufferTooSmall { needed, len } => {
+                write!(f, "buffer has {len} bytes, {needed} needed")
+            }
+            ImageError::PlaneCount { expected, found } => {
+                write!(f, "expected {expected} planes, found {found}")
+            }
+            ImageError::Pool(error) => write!(f, "{error}"),
+            ImageError::UnsupportedMatrix(space) => {
+                write!(f, "conversion with the {space:?} matrix is not supported")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<PoolError> for ImageError {
+    fn from(error: PoolError) -> Self {
+        ImageError::Pool(error)
+    }
+}
+
+/// Position of a plane inside the image buffer, in bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Plane {
+    pub offset: usize,
+    /// Width in pixels.
+    pub width: usize,
+    /// Height in rows.
+    pub height: usize,
+    /// Distance between rows in bytes.
+    pub stride: usize,
+    /// Interleaved samples per pixel.
+    pub components: usize,
+}
+
+impl Plane {
+    /// Bytes covered by the plane, without padding after the last row.
+    pub fn len_bytes(&self, sample_size: usize) -> usize {
+        if self.height == 0 {
+            return 0;
+        }
+        self.stride * (self.height - 1) + self.width * self.components * sample_size
+    }
+}
+
+/// Picture with its planes stored in one [`Buffer`].
+#[derive(Clone)]
+pub struct Image {
+    format: PixelFormat,
+    width: usize,
+    height: usize,
+    planes: Vec<Plane>,
+    buffer: Buffer,
+}
+
+fn sample_matches(format: PixelFormat, buffer: &Buffer) -> bool {
+    matches!(
+        (format.sample_type(), buffer.element_type()),
+        (SampleType::U8, ElementType::U8)
+            | (SampleType::U16, ElementType::U16)
+            | (SampleType::F32, ElementType::F32)
+    )
+}
+
+/// Byte strides of rows aligned to `align` and buffer length in samples.
+fn aligned_layout(
+    format: PixelFormat,
+    width: usize,
+    height: usize,
+    align: usize,
+) -> (Vec<usize>, usize) {
+    let sample_size = format.sample_type().size();
+    let align = align.max(1).next_multiple_of(sample_size);
+
+    let strides = format
+        .planes()
+        .iter()
+        .map(|plane| (plane.width(width) * plane.components * sample_size).next_multiple_of(align))
+        .collect::<Vec<_>>();
+
+    let len = format
+        .planes()
+        .iter()
+        .zip(&strides)
+        .map(|(plane, stride)| stride * plane.height(height))
+        .sum::<usize>()
+        / sample_size;
+
+    (strides, len)
+}
+
+impl Image {
+    /// Zeroed image with tightly packed rows.
+    pub fn new(format: PixelFormat, width: usize, height: usize) -> Result<Self, ImageError> {
+        Self::new_aligned(format, width, height, 1)
+    }
+
+    /// Zeroed image with every row starting at a multiple of `align` bytes.
+    pub fn new_aligned(
+        format: PixelFormat,
+        width: usize,
+        height: usize,
+        align: usize,
+    ) -> Result<Self, ImageError> {
+        let (strides, len) = aligned_layout(format, width, height, align);
+
+        let buffer = match format.sample_type() {
+            SampleType::U8 => Buffer::zeroed::<u8>(len),
+            SampleType::U16 => Buffer::zeroed::<u16>(len),
+            SampleType::F32 => Buffer::zeroed::<f32>(len),
+        };
+
+        Self::from_buffer(format, width, height, &strides, buffer)
+    }
+
+    /// Zeroed image with aligned rows, its memory taken from `pool` and
+    /// returned when the image buffer drops. Fails with [`ImageError::Pool`]
+    /// when the pool budget is exhausted.
+    pub fn new_pooled(
+        pool: &BufferPool,
+        format: PixelFormat,
+        width: usize,
+        height: usize,
+        align: usize,
+    ) -> Result<Self, ImageError> {
+        let (strides, len) = aligned_layout(format, width, height, align);
+        let element_type = match format.sample_type() {
+            SampleType::U8 => ElementType::U8,
+            SampleType::U16 => ElementType::U16,
+            SampleType::F32 => ElementType::F32,
+        };
+        let buffer = pool.acquire_zeroed(element_type, len, align)?;
+
+        Self::from_buffer(format, width, height, &strides, buffer)
+    }
+
+    /// Wrap existing `buffer` with planes stored one after another using
+    /// the given byte `strides`.
+    pub fn from_buffer(
+        format: PixelFormat,
+        width: usize,
+        height: usize,
+        strides: &[usize],
+        buffer: Buffer,
+    ) -> Result<Self, ImageError> {
+        if width == 0 || height == 0 {
+            return Err(ImageError::EmptySize);
+        }
+        if !sample_matches(format, &buffer) {
+            return Err(ImageError::SampleTypeMismatch(format));
+        }
+        if strides.len() != format.plane_count() {
+            return Err(ImageError::PlaneCount {
+                expected: format.plane_count(),
+                found: strides.len(),
+            });
+        }
+
+        let sample_size = format.sample_type().size();
+        let mut planes = Vec::with_capacity(strides.len());
+        let mut offset = 0;
+
+        for (index, (descriptor, &stride)) in format.planes().iter().zip(strides).enumerate() {
+            let plane = Plane {
+                offset,
+                width: descriptor.width(width),
+                height: descriptor.height(height),
+                stride,
+                components: descriptor.components,
+            };
+
+            if stride < plane.width * plane.components * sample_size || stride % sample_size != 0 {
+                return Err(ImageError::InvalidStride {
+                    plane: index,
+                    stride,
+                });
+            }
+
+            offset += stride * plane.height;
+            planes.push(plane);
+        }
+
+        let needed = planes
+            .last()
+            .map_or(0, |plane| plane.offset + plane.len_bytes(sample_size));
+        if buffer.len_bytes() < needed {
+            return Err(ImageError::BufferTooSmall {
+                needed,
+                len: buffer.len_bytes(),
+            });
+        }
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            planes,
+            buffer,
+        })
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn planes(&self) -> &[Plane] {
+        &self.planes
+    }
+
+    /// Layout of plane `index`, `None` when the format has no such plane.
+    pub fn plane(&self, index: usize) -> Option<&Plane> {
+        self.planes.get(index)
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn into_buffer(self) -> Buffer {
+        self.buffer
+    }
+
+    /// Make the samples reference counted, so clones share them.
+    pub fn into_shared(self) -> Self {
+        Self {
+            buffer: self.buffer.into_shared(),
+            ..self
+        }
+    }
+
+    /// Samples of plane `index` starting at its first row.
+    ///
+    /// Returns `None` when `T` is not the sample type of the format, the
+    /// plane does not exist or the buffer is too short to hold it.
+    pub fn plane_data<T: BufferElement>(&self, index: usize) -> Option<&[T]> {
+        let range = self.plane_range(index)?;
+        self.buffer.as_slice::<T>()?.get(range)
+    }
+
+    pub fn plane_data_mut<T: BufferElement>(&mut self, index: usize) -> Option<&mut [T]> {
+        let range = self.plane_range(index)?;
+        self.buffer.as_mut_slice::<T>()?.get_mut(range)
+    }
+
+    /// Sample range of plane `index`.
+    fn plane_range(&self, index: usize) -> Option<std::ops::Range<usize>> {
+        let plane = self.planes.get(index)?;
+        let size = self.format.sample_type().size();
+        let start = plane.offset / size;
+        Some(start..start + plane.len_bytes(size) / size)
+    }
+
+    /// Samples of row `y` of plane `index`, without stride padding.
+    ///
+    /// Returns `None` when `T` is not the sample type of the format or the
+    /// plane or row does not exist.
+    pub fn row<T: BufferElement>(&self, index: usize, y: usize) -> Option<&[T]> {
+        let range = self.row_range(index, y)?;
+        self.buffer.as_slice::<T>()?.get(range)
+    }
+
+    pub fn row_mut<T: BufferElement>(&mut self, index: usize, y: usize) -> Option<&mut [T]> {
+        let range = self.row_range(index, y)?;
+        self.buffer.as_mut_slice::<T>()?.get_mut(range)
+    }
+
+    /// Sample range of row `y` of plane `index`.
+    fn row_range(&self, index: usize, y: usize) -> Option<std::ops::Range<usize>> {
+        let plane = self.planes.get(index)?;
+        if y >= plane.height {
+            return None;
+        }
+        let size = self.format.sample_type().size();
+        let start = (plane.offset + plane.stride * y) / size;
+        Some(start..start + plane.width * plane.components)
+    }
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("format", &self.format)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("planes", &self.planes)
+            .field("len_bytes", &self.buffer.len_bytes())
+            .finish()
+    }
+}
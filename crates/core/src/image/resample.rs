@@ -0,0 +1,167 @@
+//! Separable resampling of `f32` planes with precomputed filter weights.
+
+use std::{f64::consts::PI, ops::Range};
+
+use serde::{Deserialize, Serialize};
+
+/// Reconstruction filter of a resampler.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ResampleFilter {
+    /// Closest sample, a box when shrinking.
+    Nearest,
+    /// Linear interpolation, a triangle when shrinking.
+    #[default]
+    Bilinear,
+    /// Catmull-Rom cubic.
+    Bicubic,
+    /// Three lobe windowed sinc.
+    Lanczos,
+}
+
+impl ResampleFilter {
+    /// Half width of the kernel in samples.
+    pub fn support(&self) -> f64 {
+        match self {
+            ResampleFilter::Nearest => 0.5,
+            ResampleFilter::Bilinear => 1.0,
+            ResampleFilter::Bicubic => 2.0,
+            ResampleFilter::Lanczos => 3.0,
+        }
+    }
+
+    /// Kernel value at distance `x`, ties of [`Nearest`](Self::Nearest)
+    /// going to the lower sample.
+    pub fn weight(&self, x: f64) -> f64 {
+        match self {
+            ResampleFilter::Nearest => (-0.5..0.5).contains(&x) as u8 as f64,
+            ResampleFilter::Bilinear => (1.0 - x.abs()).max(0.0),
+            ResampleFilter::Bicubic => match x.abs() {
+                x if x < 1.0 => 1.5 * x * x * x - 2.5 * x * x + 1.0,
+                x if x < 2.0 => -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0,
+                _ => 0.0,
+            },
+            ResampleFilter::Lanczos => match x.abs() {
+                0.0 => 1.0,
+                x if x < 3.0 => 3.0 * (PI * x).sin() * (PI * x / 3.0).sin() / (PI * PI * x * x),
+                _ => 0.0,
+            },
+        }
+    }
+}
+
+/// Filter taps of every output sample along one axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weights {
+    /// First input sample and weights of each output sample.
+    taps: Vec<(usize, Vec<f32>)>,
+}
+
+impl Weights {
+    /// Weights of `output_len` samples read from `input_len` samples, output
+    /// `i` centered at input coordinate `position(i)`. The kernel widens by
+    /// `scale`, the input samples per output sample, when shrinking. Taps
+    /// beyond the edges repeat the edge samples.
+    pub fn new(
+        filter: ResampleFilter,
+        input_len: usize,
+        output_len: usize,
+        position: impl Fn(usize) -> f64,
+        scale: f64,
+    ) -> Self {
+        let scale = scale.max(1.0);
+        let support = filter.support() * scale;
+        let last = input_len as i64 - 1;
+
+        let taps = (0..output_len)
+            .map(|i| {
+                let center = position(i);
+                let first = (center - support).ceil() as i64;
+                let end = (center + support).floor() as i64;
+
+                let mut weights: Vec<f64> = Vec::new();
+                let mut start = None;
+                for x in first..=end {
+                    let weight = filter.weight((x as f64 - center) / scale);
+                    // Skip leading taps outside the kernel.
+                    if weight == 0.0 && start.is_none() {
+                        continue;
+                    }
+                    let index = x.clamp(0, last) as usize;
+                    let start = *start.get_or_insert(index);
+                    if index - start >= weights.len() {
+                        weights.resize(index - start + 1, 0.0);
+                    }
+                    weights[index - start] += weight;
+                }
+
+                let start = start.unwrap_or(center.round().clamp(0.0, last as f64) as usize);
+                if weights.is_empty() {
+                    weights.push(1.0);
+                }
+                let sum = weights.iter().sum::<f64>();
+                (start, weights.iter().map(|w| (w / sum) as f32).collect())
+            })
+            .collect();
+
+        Self { taps }
+    }
+
+    pub fn len(&self) -> usize {
+        self.taps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.taps.is_empty()
+    }
+
+    /// Input samples read by the outputs in `outputs`.
+    pub fn span(&self, outputs: Range<usize>) -> Range<usize> {
+        self.taps[outputs]
+            .iter()
+            .map(|(start, weights)| *start..start + weights.len())
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+            .unwrap_or(0..0)
+    }
+
+    fn apply(&self, output: usize, input: impl Fn(usize) -> f32) -> f32 {
+        let (start, weights) = &self.taps[output];
+        weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| weight * input(start + i))
+            .sum()
+    }
+}
+
+/// Resample every row of a `width` by `height` plane to `weights.len()`
+/// samples.
+pub fn resample_rows(plane: &[f32], width: usize, height: usize, weights: &Weights) -> Vec<f32> {
+    let mut output = Vec::with_capacity(weights.len() * height);
+    for row in plane.chunks_exact(width).take(height) {
+        output.extend((0..weights.len()).map(|x| weights.apply(x, |i| row[i])));
+    }
+    output
+}
+
+/// Resample every column of a `width` by `height` plane to
+/// `weights.len()` rows.
+pub fn resample_columns(plane: &[f32], width: usize, height: usize, weights: &Weights) -> Vec<f32> {
+    debug_assert!(plane.len() >= width * height);
+    resample_band(plane, width, 0, weights, 0..weights.len())
+}
+
+/// Output `rows` of [`resample_columns`] from a band of input rows
+/// starting at row `first`, at least the [`span`](Weights::span) of `rows`.
+pub fn resample_band(
+    band: &[f32],
+    width: usize,
+    first: usize,
+    weights: &Weights,
+    rows: Range<usize>,
+) -> Vec<f32> {
+    let mut output = Vec::with_capacity(width * rows.len());
+    for y in rows {
+        output.extend((0..width).map(|x| weights.apply(y, |i| band[(i - first) * width + x])));
+    }
+    output
+}
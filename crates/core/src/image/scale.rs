@@ -0,0 +1,164 @@
+//! Resizing of images, every plane and component on its own.
+//!
+//! Planes go through a horizontal then a vertical [`resample`] pass. The
+//! vertical pass works in bands of output rows, so only the input rows a
+//! band reads are kept at the output width.
+
+use mcf_math::geometry::{self, Coordinate};
+
+use super::{
+    Image, ImageError,
+    chroma::ChromaSiting,
+    convert::{read_component, write_component},
+    resample::{self, ResampleFilter, Weights},
+};
+
+/// Output rows per band of the vertical pass.
+const DEFAULT_TILE_HEIGHT: usize = 64;
+
+/// Separable image scaler.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scaler {
+    filter: ResampleFilter,
+    siting: ChromaSiting,
+    tile_height: usize,
+}
+
+impl Default for Scaler {
+    fn default() -> Self {
+        Self::new(ResampleFilter::default())
+    }
+}
+
+impl Scaler {
+    pub fn new(filter: ResampleFilter) -> Self {
+        Self {
+            filter,
+            siting: ChromaSiting::default(),
+            tile_height: DEFAULT_TILE_HEIGHT,
+        }
+    }
+
+    pub fn filter(&self) -> ResampleFilter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: ResampleFilter) -> &mut Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Siting of subsampled chroma, kept through the scale.
+    pub fn set_siting(&mut self, siting: ChromaSiting) -> &mut Self {
+        self.siting = siting;
+        self
+    }
+
+    /// Output rows per band of the vertical pass, at least one.
+    pub fn set_tile_height(&mut self, rows: usize) -> &mut Self {
+        self.tile_height = rows.max(1);
+        self
+    }
+
+    /// Scale `image` to `width` by `height`.
+    pub fn scale(&self, image: &Image, width: usize, height: usize) -> Result<Image, ImageError> {
+        if (image.width(), image.height()) == (width, height) {
+            return Ok(image.clone());
+        }
+        let source = geometry::Rect {
+            top: 0.0,
+            left: 0.0,
+            right: image.width() as Coordinate,
+            bottom: image.height() as Coordinate,
+        };
+        self.scale_region(image, &source, width, height)
+    }
+
+    /// Scale the `source` area of `image`, in luma pixels and possibly
+    /// fractional, to `width` by `height`. Samples outside the image repeat
+    /// the edges.
+    pub fn scale_region(
+        &self,
+        image: &Image,
+        source: &geometry::Rect,
+        width: usize,
+        height: usize,
+    ) -> Result<Image, ImageError> {
+        let area = geometry::Frame {
+            width: source.right - source.left,
+            height: source.bottom - source.top,
+        };
+        if area.width <= 0.0 || area.height <= 0.0 {
+            return Err(ImageError::EmptySize);
+        }
+        let scale_x = area.calculate_scale_width(width as Coordinate) as f64;
+        let scale_y = area.calculate_scale_height(height as Coordinate) as f64;
+
+        let format = image.format();
+        let mut output = Image::new(format, width, height)?;
+        for (index, descriptor) in format.planes().iter().enumerate() {
+            let (Some(&input), Some(&target)) = (image.plane(index), output.plane(index)) else {
+                continue;
+            };
+            let horizontal = self.weights(
+                (descriptor.shift_x, false),
+                (source.left as f64, scale_x),
+                (input.width, target.width),
+            );
+            let vertical = self.weights(
+                (descriptor.shift_y, true),
+                (source.top as f64, scale_y),
+                (input.height, target.height),
+            );
+
+            for component in 0..descriptor.components {
+                let values = read_component(image, index, component);
+                let values = self.resample(&values, input.width, &horizontal, &vertical);
+                write_component(&mut output, index, component, &values, |value| value);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Weights along one axis of a plane subsampled by `shift`, reading
+    /// from luma position `start` with `scale` output per input pixels.
+    fn weights(
+        &self,
+        (shift, vertical): (u32, bool),
+        (start, scale): (f64, f64),
+        (input, output): (usize, usize),
+    ) -> Weights {
+        let step = (1 << shift) as f64;
+        let offset = self.siting.offset(shift, vertical);
+        let position = |i: usize| {
+            let luma = start + (i as f64 * step + offset + 0.5) / scale - 0.5;
+            (luma - offset) / step
+        };
+        Weights::new(self.filter, input, output, position, scale.recip())
+    }
+
+    fn resample(
+        &self,
+        values: &[f32],
+        width: usize,
+        horizontal: &Weights,
+        vertical: &Weights,
+    ) -> Vec<f32> {
+        let rows = vertical.len();
+        let mut output = Vec::with_capacity(horizontal.len() * rows);
+        for first in (0..rows).step_by(self.tile_height) {
+            let band = first..(first + self.tile_height).min(rows);
+            let span = vertical.span(band.clone());
+            let input = &values[span.start * width..span.end * width];
+            let input = resample::resample_rows(input, width, span.len(), horizontal);
+            output.extend(resample::resample_band(
+                &input,
+                horizontal.len(),
+                span.start,
+                vertical,
+                band,
+            ));
+        }
+        output
+    }
+}
@@ -1,5 +1,10 @@
+pub mod audio;
 pub mod color;
+pub mod image;
 pub mod structures;
 
 pub mod frame;
 pub mod profile;
+pub mod project;
+pub mod service;
+pub mod timecode;
@@ -0,0 +1,56 @@
+use std::{fmt, io, path::PathBuf};
+
+/// Errors returned while loading, saving or validating a [`super::Profile`].
+#[derive(Debug)]
+pub enum ProfileError {
+    /// File could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// File content is not a valid profile document.
+    Parse { path: PathBuf, message: String },
+    /// Profile could not be encoded.
+    Serialize(String),
+    /// File extension is neither `json` nor `toml`.
+    UnsupportedFormat(PathBuf),
+    /// Ratio field has a zero denominator.
+    ZeroDenominator(&'static str),
+    /// Field must be a finite value greater than zero.
+    NotPositive(&'static str),
+    /// `color.matrix` is [`crate::color::ColorSpace::Invalid`].
+    InvalidColorSpace,
+}
+
+impl ProfileError {
+    /// Name of the profile field that failed validation, if any.
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            ProfileError::ZeroDenominator(field) | ProfileError::NotPositive(field) => Some(field),
+            ProfileError::InvalidColorSpace => Some("color.matrix"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            ProfileError::Parse { path, message } => write!(f, "{}: {message}", path.display()),
+            ProfileError::Serialize(message) => write!(f, "failed to encode profile: {message}"),
+            ProfileError::UnsupportedFormat(path) => {
+                write!(f, "{}: unsupported profile format", path.display())
+            }
+            ProfileError::ZeroDenominator(field) => write!(f, "`{field}` has zero denominator"),
+            ProfileError::NotPositive(field) => write!(f, "`{field}` must be greater than zero"),
+            ProfileError::InvalidColorSpace => write!(f, "`color.matrix` is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProfileError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Read and write profiles from user files.
+//!
+//! Files use the same schema as the built-in presets in `profiles/`, either
+//! as JSON or TOML depending on the file extension.
+
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Profile,
+    error::ProfileError,
+    presets::{Preset, Presets},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProfileDocument {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    description: String,
+    #[serde(flatten)]
+    profile: Profile,
+}
+
+fn read_document(path: &Path) -> Result<ProfileDocument, ProfileError> {
+    let format =
+        Format::from_path(path).ok_or_else(|| ProfileError::UnsupportedFormat(path.into()))?;
+    let content = fs::read_to_string(path).map_err(|source| ProfileError::Io {
+        path: path.into(),
+        source,
+    })?;
+
+    let document: ProfileDocument = match format {
+        Format::Json => serde_json::from_str(&content).map_err(|err| err.to_string()),
+        Format::Toml => toml::from_str(&content).map_err(|err| err.to_string()),
+    }
+    .map_err(|message| ProfileError::Parse {
+        path: path.into(),
+        message,
+    })?;
+
+    document.profile.validate()?;
+    Ok(document)
+}
+
+fn write_document(path: &Path, document: &ProfileDocument) -> Result<(), ProfileError> {
+    let format =
+        Format::from_path(path).ok_or_else(|| ProfileError::UnsupportedFormat(path.into()))?;
+
+    let content = match format {
+        Format::Json => serde_json::to_string_pretty(document).map_err(|err| err.to_string()),
+        Format::Toml => toml::to_string_pretty(document).map_err(|err| err.to_string()),
+    }
+    .map_err(ProfileError::Serialize)?;
+
+    fs::write(path, content).map_err(|source| ProfileError::Io {
+        path: path.into(),
+        source,
+    })
+}
+
+impl Profile {
+    /// Load and validate profile from `.json` or `.toml` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Profile, ProfileError> {
+        read_document(path.as_ref()).map(|document| document.profile)
+    }
+
+    /// Validate and save profile to `.json` or `.toml` file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ProfileError> {
+        self.validate()?;
+        write_document(
+            path.as_ref(),
+            &ProfileDocument {
+                description: String::new(),
+                profile: self.clone(),
+            },
+        )
+    }
+}
+
+impl Preset {
+    /// Load preset from file. The file stem becomes the id and the parent
+    /// directory name the category.
+    pub fn load(path: impl AsRef<Path>) -> Result<Preset, ProfileError> {
+        let path = path.as_ref();
+        let document = read_document(path)?;
+
+        let id = path.file_stem().and_then(OsStr::to_str);
+        let category = path
+            .parent()
+            .and_then(Path::file_name)
+            .and_then(OsStr::to_str);
+
+        Ok(Preset {
+            id: id.unwrap_or_default().to_string(),
+            category: category.unwrap_or_default().to_string(),
+            description: document.description,
+            profile: document.profile,
+        })
+    }
+
+    /// Validate and save preset to `.json` or `.toml` file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ProfileError> {
+        self.profile.validate()?;
+        write_document(
+            path.as_ref(),
+            &ProfileDocument {
+                description: self.description.clone(),
+                profile: self.profile.clone(),
+            },
+        )
+    }
+}
+
+impl Presets {
+    /// Recursively load every `.json` and `.toml` preset found in `dir`.
+    ///
+    /// Presets with an id already in the registry replace the existing one,
+    /// so user directories can override built-in presets. Returns the number
+    /// of loaded presets.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize, ProfileError> {
+        let mut loaded = 0;
+        let mut dirs_stack: Vec<PathBuf> = vec![dir.as_ref().to_path_buf()];
+
+        while let Some(last_dir) = dirs_stack.pop() {
+            let read_dir = fs::read_dir(&last_dir).map_err(|source| ProfileError::Io {
+                path: last_dir.clone(),
+                source,
+            })?;
+
+            let mut entries = Vec::new();
+            for entry in read_dir {
+                let entry = entry.map_err(|source| ProfileError::Io {
+                    path: last_dir.clone(),
+                    source,
+                })?;
+                entries.push(entry.path());
+            }
+            entries.sort();
+
+            for entry in entries {
+                if entry.is_dir() {
+                    dirs_stack.push(entry);
+                } else if Format::from_path(&entry).is_some() {
+                    self.insert(Preset::load(&entry)?);
+                    loaded += 1;
+                }
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Built-in presets extended with the presets found in `dirs`.
+    pub fn with_user_dirs<P: AsRef<Path>>(dirs: &[P]) -> Result<Presets, ProfileError> {
+        let mut presets = Presets::builtin();
+        for dir in dirs {
+            presets.load_dir(dir)?;
+        }
+        Ok(presets)
+    }
+}
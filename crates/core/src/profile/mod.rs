@@ -0,0 +1,304 @@
+//! Module save/read/write video profile.
+//!
+//! Built-in presets are generated from `profiles/` at build time, see
+//! [`presets`].
+
+pub mod error;
+pub mod loader;
+pub mod presets;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::{ColorInfo, ColorSpace};
+use error::ProfileError;
+use mcf_math::geometry;
+
+pub use mcf_math::rational::Ratio;
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(C)]
+/// Usage for later render and return producer.
+pub enum RenderType {
+    /// Detail information by [interlace video](https://en.wikipedia.org/wiki/Interlaced_video)
+    /// Default for new profiles
+    Interlace,
+    /// Detail information by [progressive scan](https://en.wikipedia.org/wiki/Progressive_scan)
+    #[default]
+    Progressive,
+}
+
+/// Temporal order of the two fields of an interlaced frame.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(C)]
+pub enum FieldOrder {
+    #[default]
+    TopFieldFirst,
+    BottomFieldFirst,
+}
+
+/// Which aspect of a profile is authoritative, the other one is derived
+/// from it and the frame size.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(C)]
+pub enum ExplicitType {
+    /// Sample aspect is set, display aspect is computed.
+    #[default]
+    Computed,
+    /// Display aspect is set explicitly, sample aspect is computed.
+    Explicitly,
+}
+
+/// `value` when it is a positive whole number.
+fn whole(value: f32) -> Option<i64> {
+    (value > 0.0 && value.fract() == 0.0).then_some(value as i64)
+}
+
+/// `ratio` when greater than zero.
+fn positive(ratio: Ratio) -> Option<Ratio> {
+    match ratio.denominator != 0 && ratio.to_f64() > 0.0 {
+        true => Some(ratio.reduced()),
+        false => None,
+    }
+}
+
+/// Structure keeping base properties video format.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[repr(C)]
+pub struct Profile {
+    pub frame: geometry::Frame,
+    pub frame_rate: Ratio,
+    pub sample_aspect: Ratio,
+    pub display_aspect: Ratio,
+    /// Older documents with a bare `color_space` matrix are still accepted.
+    #[serde(alias = "color_space")]
+    pub color: ColorInfo,
+    pub explicit_type: ExplicitType,
+    pub render_type: RenderType,
+    /// Only meaningful with [`RenderType::Interlace`].
+    #[serde(default)]
+    pub field_order: FieldOrder,
+}
+
+impl Profile {
+    /// Get the **video frame rate** as a floating point value.
+    pub fn calculate_fps(&self) -> f32 {
+        self.frame_rate.to_f32()
+    }
+
+    /// Get the **sample aspect ratio** as a floating point value.
+    pub fn calculate_sar(&self) -> f32 {
+        self.sample_aspect.to_f32()
+    }
+
+    /// Get the **display aspect ratio** as floating point value.
+    pub fn calculate_dar(&self) -> f32 {
+        self.display_aspect.to_f32()
+    }
+
+    /// Width divided by height of the frame, `None` unless both are
+    /// positive whole numbers.
+    pub fn frame_aspect(&self) -> Option<Ratio> {
+        Some(Ratio::new(
+            whole(self.frame.width)?,
+            whole(self.frame.height)?,
+        ))
+    }
+
+    /// Display aspect of the frame size and sample aspect.
+    pub fn display_aspect_from_sample(&self) -> Option<Ratio> {
+        Some(self.frame_aspect()? * positive(self.sample_aspect)?)
+    }
+
+    /// Sample aspect of the frame size and display aspect.
+    pub fn sample_aspect_from_display(&self) -> Option<Ratio> {
+        Some(positive(self.display_aspect)? / self.frame_aspect()?)
+    }
+
+    /// Width giving the display aspect with the sample aspect at the frame
+    /// height, rounded to whole pixels.
+    pub fn width_from_aspect(&self) -> Option<f32> {
+        let height = Ratio::from_integer(whole(self.frame.height)?);
+        let width = height * positive(self.display_aspect)? / positive(self.sample_aspect)?;
+        Some(width.round() as f32)
+    }
+
+    /// Sample aspect per [`ExplicitType`], falling back to the other aspect
+    /// when the authoritative one is unknown.
+    pub fn effective_sample_aspect(&self) -> Option<Ratio> {
+        let stored = positive(self.sample_aspect);
+        match self.explicit_type {
+            ExplicitType::Computed => stored.or_else(|| self.sample_aspect_from_display()),
+            ExplicitType::Explicitly => self.sample_aspect_from_display().or(stored),
+        }
+    }
+
+    /// Display aspect per [`ExplicitType`], falling back to the other
+    /// aspect when the authoritative one is unknown.
+    pub fn effective_display_aspect(&self) -> Option<Ratio> {
+        let stored = positive(self.display_aspect);
+        match self.explicit_type {
+            ExplicitType::Computed => self.display_aspect_from_sample().or(stored),
+            ExplicitType::Explicitly => stored.or_else(|| self.display_aspect_from_sample()),
+        }
+    }
+
+    /// Store the effective sample and display aspects, so both agree with
+    /// the frame size.
+    pub fn resolve_aspect(&mut self) -> Result<(), ProfileError> {
+        let sample_aspect = self
+            .effective_sample_aspect()
+            .ok_or(ProfileError::NotPositive("sample_aspect"))?;
+        self.sample_aspect = sample_aspect;
+        self.display_aspect = self
+            .display_aspect_from_sample()
+            .ok_or(ProfileError::NotPositive("frame"))?;
+        Ok(())
+    }
+
+    /// Check that the profile can be used for rendering.
+    ///
+    /// Returns the first offending field, so invalid profiles are rejected
+    /// before they produce `NaN` in the calculations above.
+    pub fn validate(&self) -> Result<(), ProfileError> {
+        let positive = |value: f32, field| {
+            if value.is_finite() && value > 0.0 {
+                Ok(())
+            } else {
+                Err(ProfileError::NotPositive(field))
+            }
+        };
+        let ratio = |ratio: &Ratio, field| {
+            if ratio.denominator == 0 {
+                return Err(ProfileError::ZeroDenominator(field));
+            }
+            if ratio.number == 0 || ratio.number.signum() != ratio.denominator.signum() {
+                return Err(ProfileError::NotPositive(field));
+            }
+            Ok(())
+        };
+
+        positive(self.frame.width, "frame.width")?;
+        positive(self.frame.height, "frame.height")?;
+        ratio(&self.frame_rate, "frame_rate")?;
+        ratio(&self.sample_aspect, "sample_aspect")?;
+        ratio(&self.display_aspect, "display_aspect")?;
+
+        if self.color.matrix == ColorSpace::Invalid {
+            return Err(ProfileError::InvalidColorSpace);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct ProfileBuilder(Profile);
+
+impl Default for ProfileBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProfileBuilder {
+    pub fn new() -> Self {
+        Self(Profile::default())
+    }
+
+    pub fn set_frame_rate(&mut self, frame_rate: Ratio) -> &mut Self {
+        self.0.frame_rate = frame_rate;
+        self
+    }
+
+    pub fn set_frame(&mut self, frame: geometry::Frame) -> &mut Self {
+        self.0.frame = frame;
+        self
+    }
+
+    pub fn set_sample_aspect(&mut self, sample_aspect: Ratio) -> &mut Self {
+        self.0.sample_aspect = sample_aspect;
+        self
+    }
+
+    pub fn set_display_aspect(&mut self, display_aspect: Ratio) -> &mut Self {
+        self.0.display_aspect = display_aspect;
+        self
+    }
+
+    pub fn set_color(&mut self, color: ColorInfo) -> &mut Self {
+        self.0.color = color;
+        self
+    }
+
+    /// Set color info with the usual primaries, transfer and range of the
+    /// `color_space` matrix.
+    pub fn set_color_space(&mut self, color_space: ColorSpace) -> &mut Self {
+        self.0.color = ColorInfo::from(color_space);
+        self
+    }
+
+    pub fn set_explicit_type(&mut self, explicit_type: ExplicitType) -> &mut Self {
+        self.0.explicit_type = explicit_type;
+        self
+    }
+
+    pub fn set_render_type(&mut self, render_type: RenderType) -> &mut Self {
+        self.0.render_type = render_type;
+        self
+    }
+
+    pub fn set_field_order(&mut self, field_order: FieldOrder) -> &mut Self {
+        self.0.field_order = field_order;
+        self
+    }
+
+    pub fn build(&self) -> Profile {
+        self.0.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExplicitType, presets};
+
+    #[test]
+    fn preset_aspects_agree() {
+        for preset in presets::list() {
+            let profile = &preset.profile;
+            assert_eq!(
+                profile.display_aspect_from_sample(),
+                profile.effective_display_aspect(),
+                "{}",
+                preset.id
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_aspect_round_trips() {
+        for preset in presets::list() {
+            for explicit_type in [ExplicitType::Computed, ExplicitType::Explicitly] {
+                let mut profile = preset.profile.clone();
+                profile.explicit_type = explicit_type.clone();
+                profile.resolve_aspect().unwrap();
+
+                assert_eq!(profile.frame, preset.profile.frame, "{}", preset.id);
+                assert_eq!(
+                    profile.sample_aspect,
+                    preset.profile.sample_aspect.reduced(),
+                    "{} {explicit_type:?}",
+                    preset.id
+                );
+                assert_eq!(
+                    profile.display_aspect,
+                    preset.profile.display_aspect.reduced(),
+                    "{} {explicit_type:?}",
+                    preset.id
+                );
+                assert_eq!(
+                    profile.sample_aspect_from_display(),
+                    Some(profile.sample_aspect)
+                );
+            }
+        }
+    }
+}
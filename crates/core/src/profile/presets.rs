@@ -0,0 +1,122 @@
+//! Catalog of built-in video profiles.
+//!
+//! Presets live as JSON files in `profiles/<category>/<id>.json` and are
+//! compiled into the crate by `build/generate_profiles.rs`. The file stem is
+//! the stable id used for lookups.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::{ExplicitType, Profile, ProfileBuilder, Ratio, RenderType};
+use crate::color::{ColorInfo, ColorPrimaries, ColorRange, ColorSpace, TransferCharacteristics};
+use mcf_math::geometry;
+
+include!(concat!(env!("OUT_DIR"), "/profiles.rs"));
+
+/// Named profile with its catalog metadata.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    /// Stable identifier, e.g. `hd_1080p_25`.
+    pub id: String,
+    /// Group the preset belongs to, e.g. `hd`, `uhd`, `social`.
+    pub category: String,
+    /// Human readable description.
+    pub description: String,
+    pub profile: Profile,
+}
+
+/// Registry of presets keyed by id.
+#[derive(Debug, Default, Clone)]
+pub struct Presets {
+    inner: HashMap<String, Preset>,
+}
+
+impl Presets {
+    /// Create registry filled with the presets compiled into the crate.
+    pub fn builtin() -> Self {
+        Self {
+            inner: get_profiles(),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Preset> {
+        self.inner.get(id)
+    }
+
+    pub fn get_profile(&self, id: &str) -> Option<&Profile> {
+        self.inner.get(id).map(|preset| &preset.profile)
+    }
+
+    /// Find preset by description, ignoring ASCII case.
+    pub fn find_by_description(&self, description: &str) -> Option<&Preset> {
+        self.inner
+            .values()
+            .find(|preset| preset.description.eq_ignore_ascii_case(description))
+    }
+
+    /// All presets sorted by id.
+    pub fn list(&self) -> Vec<&Preset> {
+        let mut presets = self.inner.values().collect::<Vec<_>>();
+        presets.sort_by(|a, b| a.id.cmp(&b.id));
+        presets
+    }
+
+    /// Presets of one category sorted by id.
+    pub fn list_category(&self, category: &str) -> Vec<&Preset> {
+        self.list()
+            .into_iter()
+            .filter(|preset| preset.category == category)
+            .collect()
+    }
+
+    /// Sorted list of known categories.
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories = self
+            .inner
+            .values()
+            .map(|preset| preset.category.as_str())
+            .collect::<Vec<_>>();
+        categories.sort_unstable();
+        categories.dedup();
+        categories
+    }
+
+    /// Insert preset, replacing and returning the one with the same id.
+    pub fn insert(&mut self, preset: Preset) -> Option<Preset> {
+        self.inner.insert(preset.id.clone(), preset)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Preset> {
+        self.inner.remove(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+/// Shared registry of the built-in presets.
+pub fn builtin() -> &'static Presets {
+    static BUILTIN: OnceLock<Presets> = OnceLock::new();
+    BUILTIN.get_or_init(Presets::builtin)
+}
+
+/// Get built-in preset by id.
+pub fn get(id: &str) -> Option<&'static Preset> {
+    builtin().get(id)
+}
+
+/// Find built-in preset by description.
+pub fn find_by_description(description: &str) -> Option<&'static Preset> {
+    builtin().find_by_description(description)
+}
+
+/// All built-in presets sorted by id.
+pub fn list() -> Vec<&'static Preset> {
+    builtin().list()
+}
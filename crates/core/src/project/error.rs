@@ -0,0 +1,67 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::service::ServiceError;
+
+/// Errors returned while loading, saving or building a [`super::Project`].
+#[derive(Debug)]
+pub enum ProjectError {
+    /// File could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// Content is not a valid project document.
+    Parse(String),
+    /// Project could not be encoded.
+    Serialize(String),
+    /// Document was written by a newer schema.
+    UnsupportedVersion { found: u32, supported: u32 },
+    /// No migration upgrades documents of this version.
+    MissingMigration(u32),
+    /// Migration from `from` to the next version failed.
+    Migration { from: u32, message: String },
+    /// Two producers share an id.
+    DuplicateId(String),
+    /// Id does not name an earlier producer.
+    UnknownReference(String),
+    /// No factory is registered under this service name.
+    UnknownService(String),
+    /// Service could not be created or configured.
+    Service(ServiceError),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            ProjectError::Parse(message) => write!(f, "invalid project: {message}"),
+            ProjectError::Serialize(message) => write!(f, "failed to encode project: {message}"),
+            ProjectError::UnsupportedVersion { found, supported } => {
+                write!(f, "project version {found} is newer than {supported}")
+            }
+            ProjectError::MissingMigration(version) => {
+                write!(f, "no migration from project version {version}")
+            }
+            ProjectError::Migration { from, message } => {
+                write!(f, "migration from project version {from} failed: {message}")
+            }
+            ProjectError::DuplicateId(id) => write!(f, "duplicate producer id `{id}`"),
+            ProjectError::UnknownReference(id) => write!(f, "unknown producer `{id}`"),
+            ProjectError::UnknownService(name) => write!(f, "unknown service `{name}`"),
+            ProjectError::Service(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProjectError::Io { source, .. } => Some(source),
+            ProjectError::Service(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ServiceError> for ProjectError {
+    fn from(error: ServiceError) -> Self {
+        ProjectError::Service(error)
+    }
+}
@@ -0,0 +1,480 @@
+//! Versioned JSON documents describing a whole composition: profile,
+//! producers, playlists, tractors, filters, transitions and their
+//! properties.
+//!
+//! Producers are listed in dependency order and referenced by id, so a
+//! producer used by several entries is shared. Producers and tracks may
+//! carry their own filters. Documents written by [`Project::to_json`]
+//! reload to the same bytes.
+//!
+//! Documents are built into graphs but not exported back from them:
+//! services do not record the registry name they were created with, so
+//! edits are made to the [`Project`] and the graph rebuilt.
+
+pub mod error;
+mod properties;
+pub mod registry;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    profile::Profile,
+    service::{
+        Filter, Service, SharedProducer,
+        filtered::FilteredProducer,
+        lock,
+        playlist::{Playlist, PlaylistEntry},
+        shared,
+        tractor::Tractor,
+    },
+    structures::object::Object,
+};
+use error::ProjectError;
+use registry::Registry;
+
+/// Version written by this crate.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Service created by name from a [`Registry`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceNode {
+    pub service: String,
+    #[serde(default, with = "properties")]
+    pub properties: Object,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryNode {
+    /// Producer id, `None` for a blank lasting `out_point - in_point + 1`
+    /// frames.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<String>,
+    pub in_point: i64,
+    pub out_point: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackNode {
+    pub producer: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub muted: bool,
+    /// Filters of this track only, the producer is left as is.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<ServiceNode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionNode {
+    #[serde(flatten)]
+    pub service: ServiceNode,
+    pub a_track: usize,
+    pub b_track: usize,
+    pub in_point: i64,
+    pub out_point: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProducerNode {
+    Service {
+        id: String,
+        service: String,
+        #[serde(default, with = "properties")]
+        properties: Object,
+        /// In and out points, the whole producer when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        range: Option<(i64, i64)>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        filters: Vec<ServiceNode>,
+    },
+    Playlist {
+        id: String,
+        #[serde(default, with = "properties")]
+        properties: Object,
+        entries: Vec<EntryNode>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        filters: Vec<ServiceNode>,
+    },
+    Tractor {
+        id: String,
+        #[serde(default, with = "properties")]
+        properties: Object,
+        tracks: Vec<TrackNode>,
+        #[serde(default)]
+        transitions: Vec<TransitionNode>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        filters: Vec<ServiceNode>,
+    },
+}
+
+impl ProducerNode {
+    pub fn id(&self) -> &str {
+        match self {
+            ProducerNode::Service { id, .. }
+            | ProducerNode::Playlist { id, .. }
+            | ProducerNode::Tractor { id, .. } => id,
+        }
+    }
+
+    pub fn properties(&self) -> &Object {
+        match self {
+            ProducerNode::Service { properties, .. }
+            | ProducerNode::Playlist { properties, .. }
+            | ProducerNode::Tractor { properties, .. } => properties,
+        }
+    }
+
+    /// Filters applied to the frames of the producer wherever it is used.
+    pub fn filters(&self) -> &[ServiceNode] {
+        match self {
+            ProducerNode::Service { filters, .. }
+            | ProducerNode::Playlist { filters, .. }
+            | ProducerNode::Tractor { filters, .. } => filters,
+        }
+    }
+}
+
+/// Upgrade step of a document from one version to the next.
+pub type Migration = Box<dyn Fn(&mut Value) -> Result<(), String> + Send + Sync>;
+
+/// Upgrade steps keyed by the version they upgrade from.
+#[derive(Default)]
+pub struct Migrations {
+    steps: BTreeMap<u32, Migration>,
+}
+
+impl Migrations {
+    /// Steps of the schema versions of this crate.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the step upgrading documents of version `from`.
+    pub fn add(
+        &mut self,
+        from: u32,
+        step: impl Fn(&mut Value) -> Result<(), String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.steps.insert(from, Box::new(step));
+        self
+    }
+
+    /// Upgrade `document` to [`SCHEMA_VERSION`], updating its `version`.
+    pub fn migrate(&self, document: &mut Value) -> Result<(), ProjectError> {
+        let mut version = document
+            .get("version")
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| ProjectError::Parse("missing `version`".into()))?;
+
+        if version > SCHEMA_VERSION {
+            return Err(ProjectError::UnsupportedVersion {
+                found: version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+
+        while version < SCHEMA_VERSION {
+            let step = self
+                .steps
+                .get(&version)
+                .ok_or(ProjectError::MissingMigration(version))?;
+            step(document).map_err(|message| ProjectError::Migration {
+                from: version,
+                message,
+            })?;
+            version += 1;
+            document["version"] = Value::from(version);
+        }
+        Ok(())
+    }
+}
+
+/// Runtime graph built from a [`Project`].
+pub struct Composition {
+    pub profile: Profile,
+    /// Every producer by id.
+    pub producers: HashMap<String, SharedProducer>,
+    pub output: SharedProducer,
+    /// Filters applied to the output, in order.
+    pub filters: Vec<Box<dyn Filter>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub version: u32,
+    pub profile: Profile,
+    /// Session data such as the playhead, ignored when building.
+    #[serde(default, with = "properties")]
+    pub properties: Object,
+    pub producers: Vec<ProducerNode>,
+    /// Filters of the output.
+    #[serde(default)]
+    pub filters: Vec<ServiceNode>,
+    /// Id of the producer played.
+    pub output: String,
+}
+
+impl Project {
+    pub fn new(profile: &Profile, output: &str) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            profile: profile.clone(),
+            properties: Object::default(),
+            producers: Vec::new(),
+            filters: Vec::new(),
+            output: output.to_string(),
+        }
+    }
+
+    pub fn producer(&self, id: &str) -> Option<&ProducerNode> {
+        self.producers.iter().find(|node| node.id() == id)
+    }
+
+    /// Parse `json`, upgrading older versions with `migrations`.
+    pub fn from_json(json: &str, migrations: &Migrations) -> Result<Self, ProjectError> {
+        let mut document: Value =
+            serde_json::from_str(json).map_err(|err| ProjectError::Parse(err.to_string()))?;
+        migrations.migrate(&mut document)?;
+
+        let project: Project =
+            serde_json::from_value(document).map_err(|err| ProjectError::Parse(err.to_string()))?;
+        project
+            .profile
+            .validate()
+            .map_err(|err| ProjectError::Parse(err.to_string()))?;
+        Ok(project)
+    }
+
+    /// Pretty printed document ending with a newline.
+    pub fn to_json(&self) -> Result<String, ProjectError> {
+        let mut json = serde_json::to_string_pretty(self)
+            .map_err(|err| ProjectError::Serialize(err.to_string()))?;
+        json.push('\n');
+        Ok(json)
+    }
+
+    pub fn load(path: impl AsRef<Path>, migrations: &Migrations) -> Result<Self, ProjectError> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|source| ProjectError::Io {
+            path: path.into(),
+            source,
+        })?;
+        Self::from_json(&json, migrations)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ProjectError> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json()?).map_err(|source| ProjectError::Io {
+            path: path.into(),
+            source,
+        })
+    }
+
+    /// Create the services of the document with `registry`.
+    pub fn build(&self, registry: &Registry) -> Result<Composition, ProjectError> {
+        let profile = &self.profile;
+        let mut producers: HashMap<String, SharedProducer> = HashMap::new();
+        let get = |producers: &HashMap<String, SharedProducer>, id: &str| {
+            producers
+                .get(id)
+                .cloned()
+                .ok_or_else(|| ProjectError::UnknownReference(id.to_string()))
+        };
+
+        for node in &self.producers {
+            if producers.contains_key(node.id()) {
+                return Err(ProjectError::DuplicateId(node.id().to_string()));
+            }
+
+            let producer = match node {
+                ProducerNode::Service {
+                    service,
+                    properties,
+                    range,
+                    ..
+                } => {
+                    let producer = registry.producer(service, profile, properties)?;
+                    if let Some((in_point, out_point)) = range {
+                        lock(&producer).set_in_out(*in_point, *out_point)?;
+                    }
+                    producer
+                }
+                ProducerNode::Playlist {
+                    properties,
+                    entries,
+                    ..
+                } => {
+                    let mut playlist = Playlist::new(profile);
+                    for entry in entries {
+                        match &entry.producer {
+                            Some(id) => playlist.push(PlaylistEntry::clip(
+                                get(&producers, id)?,
+                                entry.in_point,
+                                entry.out_point,
+                            )?),
+                            None => playlist.append_blank(entry.out_point - entry.in_point + 1),
+                        }
+                    }
+                    playlist.configure(properties)?;
+                    shared(playlist)
+                }
+                ProducerNode::Tractor {
+                    properties,
+                    tracks,
+                    transitions,
+                    ..
+                } => {
+                    let mut tractor = Tractor::new(profile);
+                    for track in tracks {
+                        let producer = get(&producers, &track.producer)?;
+                        let index =
+                            tractor.add_track(self.filtered(producer, &track.filters, registry)?);
+                        tractor.set_hidden(index, track.hidden)?;
+                        tractor.set_muted(index, track.muted)?;
+                    }
+                    for node in transitions {
+                        let transition = registry.transition(
+                            &node.service.service,
+                            profile,
+                            &node.service.properties,
+                        )?;
+                        tractor.add_transition(
+                            transition,
+                            node.a_track,
+                            node.b_track,
+                            node.in_point,
+                            node.out_point,
+                        )?;
+                    }
+                    tractor.configure(properties)?;
+                    shared(tractor)
+                }
+            };
+            let producer = self.filtered(producer, node.filters(), registry)?;
+            producers.insert(node.id().to_string(), producer);
+        }
+
+        let output = get(&producers, &self.output)?;
+        let filters = self.create_filters(&self.filters, registry)?;
+
+        Ok(Composition {
+            profile: profile.clone(),
+            producers,
+            output,
+            filters,
+        })
+    }
+
+    fn create_filters(
+        &self,
+        nodes: &[ServiceNode],
+        registry: &Registry,
+    ) -> Result<Vec<Box<dyn Filter>>, ProjectError> {
+        nodes
+            .iter()
+            .map(|node| registry.filter(&node.service, &self.profile, &node.properties))
+            .collect()
+    }
+
+    /// `producer` behind the filters of `nodes`, as is without filters.
+    fn filtered(
+        &self,
+        producer: SharedProducer,
+        nodes: &[ServiceNode],
+        registry: &Registry,
+    ) -> Result<SharedProducer, ProjectError> {
+        if nodes.is_empty() {
+            return Ok(producer);
+        }
+        let filters = self.create_filters(nodes, registry)?;
+        Ok(shared(FilteredProducer::new(producer, filters)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{frame::Frame, profile::presets, service};
+
+    /// Doubles the aspect ratio of every frame.
+    #[derive(Default)]
+    struct Widen {
+        properties: Object,
+    }
+
+    impl Service for Widen {
+        fn properties(&self) -> &Object {
+            &self.properties
+        }
+
+        fn properties_mut(&mut self) -> &mut Object {
+            &mut self.properties
+        }
+    }
+
+    impl Filter for Widen {
+        fn process(&mut self, frame: &mut Frame) -> service::Result<()> {
+            frame.aspect_ratio *= 2.0;
+            Ok(())
+        }
+    }
+
+    const DOCUMENT: &str = r#"{
+  "producers": [
+    {
+      "type": "service",
+      "id": "color",
+      "service": "color",
+      "range": [0, 9],
+      "filters": [{ "service": "widen" }]
+    },
+    {
+      "type": "tractor",
+      "id": "main",
+      "tracks": [{ "producer": "color", "filters": [{ "service": "widen" }] }]
+    }
+  ],
+  "output": "main"
+}"#;
+
+    fn project() -> Project {
+        let mut document: Value = serde_json::from_str(DOCUMENT).unwrap();
+        document["version"] = Value::from(SCHEMA_VERSION);
+        document["profile"] =
+            serde_json::to_value(&presets::get("hd_720p_25").unwrap().profile).unwrap();
+        Project::from_json(&document.to_string(), &Migrations::new()).unwrap()
+    }
+
+    #[test]
+    fn filter_nodes_round_trip() {
+        let json = project().to_json().unwrap();
+        assert_eq!(json.matches("\"widen\"").count(), 2);
+        let reloaded = Project::from_json(&json, &Migrations::new()).unwrap();
+        assert_eq!(reloaded.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn filter_nodes_wrap_producers_and_tracks() {
+        let mut registry = Registry::default();
+        registry.register_filter("widen", |_, _| Ok(Box::new(Widen::default())));
+        let composition = project().build(&registry).unwrap();
+
+        let mut color = lock(&composition.producers["color"]);
+        assert_eq!(color.playtime(), 10);
+        let aspect = color.get_frame(0).unwrap().aspect_ratio;
+        drop(color);
+
+        let frame = lock(&composition.output).get_frame(0).unwrap();
+        assert_eq!(frame.aspect_ratio, aspect * 2.0);
+    }
+}
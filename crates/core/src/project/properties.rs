@@ -0,0 +1,51 @@
+//! Serde helpers writing an [`Object`] as a plain map sorted by key, so
+//! documents are byte-stable whatever the hash order of the properties.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::structures::object::{Object, ObjectValues};
+
+pub fn serialize<S: Serializer>(object: &Object, serializer: S) -> Result<S::Ok, S::Error> {
+    let sorted = object
+        .keys()
+        .filter_map(|key| Some((key, object.get(key)?)))
+        .collect::<BTreeMap<_, _>>();
+    serializer.collect_map(sorted)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Object, D::Error> {
+    let values = HashMap::<String, ObjectValues>::deserialize(deserializer)?;
+    let mut object = Object::default();
+    for (key, value) in values {
+        object.set(&key, value);
+    }
+    Ok(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        profile::presets,
+        project::{Migrations, Project},
+        structures::object::ObjectValues,
+    };
+
+    #[test]
+    fn properties_are_sorted_and_stable() {
+        let profile = &presets::get("hd_1080p_25").unwrap().profile;
+        let mut project = Project::new(profile, "main");
+        for (index, key) in ["zoom", "alpha", "mid", "beta", "omega"].iter().enumerate() {
+            project.properties.set(key, ObjectValues::Int(index as i32));
+        }
+
+        let json = project.to_json().unwrap();
+        let keys = ["\"alpha\"", "\"beta\"", "\"mid\"", "\"omega\"", "\"zoom\""]
+            .map(|key| json.find(key).unwrap());
+        assert!(keys.is_sorted());
+
+        let reloaded = Project::from_json(&json, &Migrations::new()).unwrap();
+        assert_eq!(reloaded.to_json().unwrap(), json);
+    }
+}
@@ -0,0 +1,185 @@
+//! Factories creating services from their name in a project document.
+
+use std::collections::HashMap;
+
+use super::error::ProjectError;
+use crate::{
+    image::fit::FitMode,
+    profile::Profile,
+    service::{
+        self, Filter, SharedProducer, Transition,
+        fit::FitFilter,
+        generator::{
+            audio::{AudioSettings, NoiseColor, NoiseProducer, ToneProducer},
+            video::{BarsProducer, BarsStyle, ColorProducer, CounterProducer},
+        },
+        lock,
+        sequence::SequenceProducer,
+        shared,
+        transition::{AudioCrossfade, Dissolve, FadeCurve},
+        wav::WavProducer,
+        y4m::Y4mProducer,
+    },
+    structures::object::{Object, ObjectValues},
+};
+
+pub type ProducerFactory =
+    Box<dyn Fn(&Profile, &Object) -> service::Result<SharedProducer> + Send + Sync>;
+pub type FilterFactory =
+    Box<dyn Fn(&Profile, &Object) -> service::Result<Box<dyn Filter>> + Send + Sync>;
+pub type TransitionFactory =
+    Box<dyn Fn(&Profile, &Object) -> service::Result<Box<dyn Transition>> + Send + Sync>;
+
+/// Path of file based producers.
+fn resource(properties: &Object) -> service::Result<String> {
+    match properties.get("resource") {
+        Some(ObjectValues::String(path)) => Ok(path.clone()),
+        _ => Err(service::ServiceError::invalid_property(
+            "resource",
+            "expected a path",
+        )),
+    }
+}
+
+/// Factories by service name. Factories create the service, the
+/// properties are applied afterwards with [`service::Service::configure`].
+pub struct Registry {
+    producers: HashMap<String, ProducerFactory>,
+    filters: HashMap<String, FilterFactory>,
+    transitions: HashMap<String, TransitionFactory>,
+}
+
+impl Default for Registry {
+    /// Registry of the services of this crate.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register_producer("color", |profile, _| {
+            Ok(shared(ColorProducer::new(profile, [0.0, 0.0, 0.0, 1.0])))
+        });
+        registry.register_producer("bars", |profile, _| {
+            Ok(shared(BarsProducer::new(profile, BarsStyle::Smpte)))
+        });
+        registry.register_producer("counter", |profile, _| {
+            Ok(shared(CounterProducer::new(profile)))
+        });
+        registry.register_producer("tone", |profile, _| {
+            Ok(shared(ToneProducer::new(profile, AudioSettings::default())))
+        });
+        registry.register_producer("noise", |profile, _| {
+            Ok(shared(NoiseProducer::new(
+                profile,
+                AudioSettings::default(),
+                NoiseColor::White,
+            )))
+        });
+        registry.register_producer("y4m", |_, properties| {
+            Ok(shared(Y4mProducer::open(resource(properties)?)?))
+        });
+        registry.register_producer("wav", |profile, properties| {
+            Ok(shared(WavProducer::open(profile, resource(properties)?)?))
+        });
+        registry.register_producer("sequence", |profile, properties| {
+            Ok(shared(SequenceProducer::open(
+                profile,
+                &resource(properties)?,
+            )?))
+        });
+
+        registry.register_filter("fit", |profile, _| {
+            Ok(Box::new(FitFilter::new(profile, FitMode::default())))
+        });
+
+        registry.register_transition("dissolve", |_, _| Ok(Box::new(Dissolve::new())));
+        registry.register_transition("audio_crossfade", |_, _| {
+            Ok(Box::new(AudioCrossfade::new(FadeCurve::default())))
+        });
+
+        registry
+    }
+}
+
+impl Registry {
+    /// Registry without any service.
+    pub fn empty() -> Self {
+        Self {
+            producers: HashMap::new(),
+            filters: HashMap::new(),
+            transitions: HashMap::new(),
+        }
+    }
+
+    /// Register `factory` under `name`, replacing a previous one.
+    pub fn register_producer(
+        &mut self,
+        name: &str,
+        factory: impl Fn(&Profile, &Object) -> service::Result<SharedProducer> + Send + Sync + 'static,
+    ) {
+        self.producers.insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn register_filter(
+        &mut self,
+        name: &str,
+        factory: impl Fn(&Profile, &Object) -> service::Result<Box<dyn Filter>> + Send + Sync + 'static,
+    ) {
+        self.filters.insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn register_transition(
+        &mut self,
+        name: &str,
+        factory: impl Fn(&Profile, &Object) -> service::Result<Box<dyn Transition>>
+        + Send
+        + Sync
+        + 'static,
+    ) {
+        self.transitions.insert(name.to_string(), Box::new(factory));
+    }
+
+    /// Create and configure producer `name`.
+    pub fn producer(
+        &self,
+        name: &str,
+        profile: &Profile,
+        properties: &Object,
+    ) -> Result<SharedProducer, ProjectError> {
+        let factory = self
+            .producers
+            .get(name)
+            .ok_or_else(|| ProjectError::UnknownService(name.to_string()))?;
+        let producer = factory(profile, properties)?;
+        lock(&producer).configure(properties)?;
+        Ok(producer)
+    }
+
+    pub fn filter(
+        &self,
+        name: &str,
+        profile: &Profile,
+        properties: &Object,
+    ) -> Result<Box<dyn Filter>, ProjectError> {
+        let factory = self
+            .filters
+            .get(name)
+            .ok_or_else(|| ProjectError::UnknownService(name.to_string()))?;
+        let mut filter = factory(profile, properties)?;
+        filter.configure(properties)?;
+        Ok(filter)
+    }
+
+    pub fn transition(
+        &self,
+        name: &str,
+        profile: &Profile,
+        properties: &Object,
+    ) -> Result<Box<dyn Transition>, ProjectError> {
+        let factory = self
+            .transitions
+            .get(name)
+            .ok_or_else(|| ProjectError::UnknownService(name.to_string()))?;
+        let mut transition = factory(profile, properties)?;
+        transition.configure(properties)?;
+        Ok(transition)
+    }
+}
@@ -0,0 +1,80 @@
+//! Producer passing the frames of another one through filters.
+
+use super::{Filter, Producer, ProducerState, Result, Service, SharedProducer, lock};
+use crate::{frame::Frame, structures::object::Object};
+
+/// Frames of `producer` processed by `filters` in order.
+///
+/// Takes the profile, length and in and out points of the wrapped producer
+/// when created.
+pub struct FilteredProducer {
+    state: ProducerState,
+    producer: SharedProducer,
+    filters: Vec<Box<dyn Filter>>,
+}
+
+impl FilteredProducer {
+    pub fn new(producer: SharedProducer, filters: Vec<Box<dyn Filter>>) -> Result<Self> {
+        let state = {
+            let producer = lock(&producer);
+            let mut state = ProducerState::new(producer.profile(), producer.length());
+            state.set_in_out(producer.in_point(), producer.out_point())?;
+            state
+        };
+
+        Ok(Self {
+            state,
+            producer,
+            filters,
+        })
+    }
+
+    pub fn producer(&self) -> &SharedProducer {
+        &self.producer
+    }
+
+    pub fn filters(&self) -> &[Box<dyn Filter>] {
+        &self.filters
+    }
+
+    pub fn push_filter(&mut self, filter: Box<dyn Filter>) {
+        self.filters.push(filter);
+    }
+}
+
+impl Service for FilteredProducer {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+}
+
+impl Producer for FilteredProducer {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, position: i64) -> Result<Frame> {
+        let mut frame = lock(&self.producer).produce(position)?;
+        for filter in &mut self.filters {
+            filter.process(&mut frame)?;
+        }
+        Ok(frame)
+    }
+}
+
+impl std::fmt::Debug for FilteredProducer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FilteredProducer")
+            .field("state", &self.state)
+            .field("filters", &self.filters.len())
+            .finish()
+    }
+}
@@ -0,0 +1,114 @@
+//! Conforming of frames to the frame size and aspect of a profile.
+
+use super::{
+    Filter, Result, Service, ServiceError, enum_property, generator::parse_color, string_property,
+};
+use crate::{
+    frame::Frame,
+    image::{
+        fit::{self, FitMode, FitPlan},
+        resample::ResampleFilter,
+        scale::Scaler,
+    },
+    profile::Profile,
+    structures::object::{Object, ObjectValues},
+};
+use mcf_math::geometry;
+
+/// Filter scaling images to the frame of its profile.
+///
+/// Properties: `mode` ([`FitMode`]), `filter` ([`ResampleFilter`]) and
+/// `background` (`#rrggbb[aa]` or a name, black by default).
+#[derive(Debug)]
+pub struct FitFilter {
+    profile: Profile,
+    properties: Object,
+    mode: FitMode,
+    scaler: Scaler,
+    background: [f64; 4],
+}
+
+impl FitFilter {
+    pub fn new(profile: &Profile, mode: FitMode) -> Self {
+        Self {
+            profile: profile.clone(),
+            properties: Object::default(),
+            mode,
+            scaler: Scaler::default(),
+            background: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    pub fn mode(&self) -> FitMode {
+        self.mode
+    }
+}
+
+impl Service for FitFilter {
+    fn properties(&self) -> &Object {
+        &self.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        match key {
+            "mode" => self.mode = enum_property(key, &value)?,
+            "filter" => {
+                self.scaler
+                    .set_filter(enum_property::<ResampleFilter>(key, &value)?);
+            }
+            "background" => {
+                self.background = parse_color(string_property(key, &value)?)
+                    .ok_or_else(|| ServiceError::invalid_property(key, "invalid color"))?;
+            }
+            _ => {}
+        }
+
+        self.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Filter for FitFilter {
+    fn process(&mut self, frame: &mut Frame) -> Result<()> {
+        let Some(image) = &frame.image else {
+            return Ok(());
+        };
+
+        // Pixels of the image have the sample aspect of the frame profile.
+        let source = geometry::Frame {
+            width: image.width() as geometry::Coordinate,
+            height: image.height() as geometry::Coordinate,
+        };
+        let source_aspect = fit::sample_aspect(&frame.profile);
+        let target_aspect = fit::sample_aspect(&self.profile);
+        let size = (
+            self.profile.frame.width as usize,
+            self.profile.frame.height as usize,
+        );
+
+        if source != self.profile.frame || source_aspect != target_aspect {
+            let plan = FitPlan::new(
+                self.mode,
+                (&source, source_aspect),
+                (&self.profile.frame, target_aspect),
+            );
+            let image = fit::fit(
+                image,
+                &plan,
+                size,
+                &self.scaler,
+                self.background,
+                &frame.color,
+            )?;
+            frame.image = Some(image);
+        }
+
+        frame.profile = self.profile.clone();
+        frame.viewport = self.profile.frame.clone();
+        Ok(())
+    }
+}
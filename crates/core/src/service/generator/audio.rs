@@ -0,0 +1,329 @@
+//! Sine tone and noise.
+
+use std::f64::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use super::{DEFAULT_LENGTH, base_frame, set_timeline_property};
+use crate::{
+    audio::{
+        AudioBuffer, ChannelLayout, SampleFormat, SampleLayout, convert::Dither, sample_offset,
+        samples_per_frame,
+    },
+    frame::Frame,
+    profile::Profile,
+    service::{
+        Producer, ProducerState, Result, Service, ServiceError, enum_property, float_property,
+        int_property,
+    },
+    structures::{
+        buffer::Buffer,
+        object::{Object, ObjectValues},
+    },
+};
+
+/// Output format of audio generators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioSettings {
+    pub format: SampleFormat,
+    pub layout: SampleLayout,
+    pub sample_rate: u32,
+    pub channel_layout: ChannelLayout,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            format: SampleFormat::default(),
+            layout: SampleLayout::default(),
+            sample_rate: 48000,
+            channel_layout: ChannelLayout::default(),
+        }
+    }
+}
+
+impl AudioSettings {
+    /// Apply `format`, `layout`, `sample_rate` and `channels`, returns
+    /// `false` for other keys.
+    fn set_property(&mut self, key: &str, value: &ObjectValues) -> Result<bool> {
+        match key {
+            "format" => self.format = enum_property(key, value)?,
+            "layout" => self.layout = enum_property(key, value)?,
+            "sample_rate" => {
+                self.sample_rate = u32::try_from(int_property(key, value)?)
+                    .ok()
+                    .filter(|rate| *rate > 0)
+                    .ok_or_else(|| ServiceError::invalid_property(key, "must be positive"))?;
+            }
+            "channels" => {
+                let count = usize::try_from(int_property(key, value)?)
+                    .ok()
+                    .filter(|count| (1..=u16::MAX as usize).contains(count))
+                    .ok_or_else(|| ServiceError::invalid_property(key, "out of range"))?;
+                self.channel_layout = ChannelLayout::from_count(count);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Frame holding interleaved float `samples` converted to the settings.
+    fn frame(&self, profile: &Profile, samples: Vec<f32>) -> Result<Frame> {
+        let count = samples.len() / self.channel_layout.channel_count();
+        let audio = AudioBuffer::from_buffer(
+            SampleLayout::Interleaved,
+            self.sample_rate,
+            self.channel_layout,
+            count,
+            Buffer::from(samples),
+        )?;
+        let audio = audio
+            .to_format(self.format, Dither::None)?
+            .to_layout(self.layout);
+
+        let mut frame = base_frame(profile);
+        frame.set_audio(audio);
+        Ok(frame.build())
+    }
+}
+
+/// Amplitude of `level` in dBFS.
+fn amplitude(level: f64) -> f64 {
+    10f64.powf(level / 20.0)
+}
+
+/// Sine tone, continuous across frames.
+///
+/// Properties: `frequency` in Hz, `level` in dBFS, `format`, `layout`,
+/// `sample_rate`, `channels`, `length`.
+#[derive(Debug)]
+pub struct ToneProducer {
+    state: ProducerState,
+    settings: AudioSettings,
+    frequency: f64,
+    level: f64,
+}
+
+impl ToneProducer {
+    /// 1 kHz at -20 dBFS.
+    pub fn new(profile: &Profile, settings: AudioSettings) -> Self {
+        Self {
+            state: ProducerState::new(profile, DEFAULT_LENGTH),
+            settings,
+            frequency: 1000.0,
+            level: -20.0,
+        }
+    }
+
+    pub fn frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    /// Peak level in dBFS.
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+}
+
+impl Service for ToneProducer {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        match key {
+            "frequency" => {
+                let frequency = float_property(key, &value)?;
+                if !(frequency > 0.0 && frequency.is_finite()) {
+                    return Err(ServiceError::invalid_property(key, "must be positive"));
+                }
+                self.frequency = frequency;
+            }
+            "level" => self.level = float_property(key, &value)?,
+            _ => {
+                if !self.settings.set_property(key, &value)? {
+                    set_timeline_property(&mut self.state, key, &value)?;
+                }
+            }
+        }
+
+        self.state.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Producer for ToneProducer {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, position: i64) -> Result<Frame> {
+        let rate = self.settings.sample_rate;
+        let frame_rate = self.state.profile.frame_rate;
+        let start = sample_offset(frame_rate, rate, position);
+        let count = samples_per_frame(frame_rate, rate, position);
+        let channels = self.settings.channel_layout.channel_count();
+
+        // Phase wraps per cycle so it stays exact far from the start.
+        let period = rate as f64 / self.frequency;
+        let amplitude = amplitude(self.level);
+        let mut samples = Vec::with_capacity(count * channels);
+        for index in 0..count as i64 {
+            let phase = ((start + index) as f64).rem_euclid(period) / period;
+            let value = ((phase * TAU).sin() * amplitude) as f32;
+            samples.extend(std::iter::repeat_n(value, channels));
+        }
+
+        self.settings.frame(&self.state.profile, samples)
+    }
+}
+
+/// Spectrum of [`NoiseProducer`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoiseColor {
+    /// Flat spectrum.
+    #[default]
+    White,
+    /// -3 dB per octave.
+    Pink,
+}
+
+/// Samples of pink noise filter warm up before the first one of a frame.
+const PINK_WARM_UP: i64 = 4096;
+
+/// Uniform value in `-1.0..1.0` for sample `index`, SplitMix64 based so any
+/// position can be generated without state.
+fn white(seed: u64, index: u64) -> f64 {
+    let mut z = seed.wrapping_add(index.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+}
+
+/// Paul Kellett's economy pink noise filter.
+#[derive(Default)]
+struct Pink([f64; 3]);
+
+impl Pink {
+    fn next(&mut self, white: f64) -> f64 {
+        let [b0, b1, b2] = &mut self.0;
+        *b0 = 0.99765 * *b0 + white * 0.0990460;
+        *b1 = 0.96300 * *b1 + white * 0.2965164;
+        *b2 = 0.57000 * *b2 + white * 1.0526913;
+        (*b0 + *b1 + *b2 + white * 0.1848) * 0.2
+    }
+}
+
+/// White or pink noise, the same for a position whatever the seeks.
+///
+/// Channels are uncorrelated. Properties: `noise` (`White` or `Pink`),
+/// `level` peak in dBFS, `seed`, `format`, `layout`, `sample_rate`,
+/// `channels`, `length`.
+#[derive(Debug)]
+pub struct NoiseProducer {
+    state: ProducerState,
+    settings: AudioSettings,
+    color: NoiseColor,
+    level: f64,
+    seed: u64,
+}
+
+impl NoiseProducer {
+    /// Noise at -20 dBFS peak.
+    pub fn new(profile: &Profile, settings: AudioSettings, color: NoiseColor) -> Self {
+        Self {
+            state: ProducerState::new(profile, DEFAULT_LENGTH),
+            settings,
+            color,
+            level: -20.0,
+            seed: 0,
+        }
+    }
+
+    pub fn color(&self) -> NoiseColor {
+        self.color
+    }
+}
+
+impl Service for NoiseProducer {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        match key {
+            "noise" => self.color = enum_property(key, &value)?,
+            "level" => self.level = float_property(key, &value)?,
+            "seed" => self.seed = int_property(key, &value)? as u64,
+            _ => {
+                if !self.settings.set_property(key, &value)? {
+                    set_timeline_property(&mut self.state, key, &value)?;
+                }
+            }
+        }
+
+        self.state.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Producer for NoiseProducer {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, position: i64) -> Result<Frame> {
+        let rate = self.settings.sample_rate;
+        let frame_rate = self.state.profile.frame_rate;
+        let start = sample_offset(frame_rate, rate, position);
+        let count = samples_per_frame(frame_rate, rate, position) as i64;
+        let channels = self.settings.channel_layout.channel_count();
+        let amplitude = amplitude(self.level);
+
+        let mut samples = vec![0.0f32; count as usize * channels];
+        for channel in 0..channels {
+            let noise = |index: i64| {
+                let index = (index as u64).wrapping_mul(channels as u64) + channel as u64;
+                white(self.seed, index)
+            };
+
+            match self.color {
+                NoiseColor::White => {
+                    for index in 0..count {
+                        samples[index as usize * channels + channel] =
+                            (noise(start + index) * amplitude) as f32;
+                    }
+                }
+                NoiseColor::Pink => {
+                    let mut filter = Pink::default();
+                    for index in start - PINK_WARM_UP..start {
+                        filter.next(noise(index));
+                    }
+                    for index in 0..count {
+                        let value = filter.next(noise(start + index)).clamp(-1.0, 1.0);
+                        samples[index as usize * channels + channel] = (value * amplitude) as f32;
+                    }
+                }
+            }
+        }
+
+        self.settings.frame(&self.state.profile, samples)
+    }
+}
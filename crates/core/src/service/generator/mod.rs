@@ -0,0 +1,104 @@
+//! Test signal producers for any [`Profile`].
+//!
+//! Generators are configured through their properties, e.g. `length`,
+//! `color` or `frequency`, see each producer.
+
+pub mod audio;
+pub mod video;
+
+use super::{ProducerState, Result, ServiceError, int_property};
+use crate::{
+    frame::{Frame, FrameBuilder},
+    image::{Image, format::PixelFormat},
+    profile::Profile,
+    structures::object::ObjectValues,
+};
+
+/// Length of generators in frames, as they have no natural end.
+pub const DEFAULT_LENGTH: i64 = 15000;
+
+/// Apply the `length`, `in` and `out` properties shared by every
+/// generator, returns `false` for other keys.
+pub(crate) fn set_timeline_property(
+    state: &mut ProducerState,
+    key: &str,
+    value: &ObjectValues,
+) -> Result<bool> {
+    match key {
+        "length" => {
+            let length = int_property(key, value)?;
+            if length <= 0 {
+                return Err(ServiceError::invalid_property(key, "must be positive"));
+            }
+            state.set_length(length);
+        }
+        "in" => state.set_in_out(int_property(key, value)?, state.out_point())?,
+        "out" => state.set_in_out(state.in_point(), int_property(key, value)?)?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Non-linear RGBA of `#rrggbb`, `#rrggbbaa`, `0xrrggbbaa` or a basic
+/// color name.
+pub fn parse_color(value: &str) -> Option<[f64; 4]> {
+    let value = value.trim().to_ascii_lowercase();
+
+    let hex = match value.as_str() {
+        "black" => "000000",
+        "white" => "ffffff",
+        "gray" | "grey" => "808080",
+        "red" => "ff0000",
+        "green" => "00ff00",
+        "blue" => "0000ff",
+        "yellow" => "ffff00",
+        "cyan" => "00ffff",
+        "magenta" => "ff00ff",
+        "transparent" => "00000000",
+        value => value
+            .strip_prefix('#')
+            .or_else(|| value.strip_prefix("0x"))?,
+    };
+
+    if !matches!(hex.len(), 6 | 8) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channel = |index: usize| {
+        hex.get(index * 2..index * 2 + 2)
+            .map_or(Some(255), |digits| u8::from_str_radix(digits, 16).ok())
+            .map(|value| value as f64 / 255.0)
+    };
+    Some([channel(0)?, channel(1)?, channel(2)?, channel(3)?])
+}
+
+/// Pixel format used by video generators when none is set: RGBA for RGB
+/// profiles, 4:2:0 otherwise.
+pub fn default_pixel_format(profile: &Profile) -> PixelFormat {
+    match profile.color.matrix {
+        crate::color::ColorSpace::Rgb => PixelFormat::Rgba8,
+        _ => PixelFormat::Yuv420p,
+    }
+}
+
+/// Frame size of `profile` in pixels.
+pub(crate) fn frame_size(profile: &Profile) -> (usize, usize) {
+    (
+        profile.frame.width.max(1.0) as usize,
+        profile.frame.height.max(1.0) as usize,
+    )
+}
+
+pub(crate) fn base_frame(profile: &Profile) -> FrameBuilder {
+    let mut builder = FrameBuilder::new();
+    builder.set_profile(profile);
+    builder.set_color(profile.color);
+    builder.set_viewport(profile.frame.clone());
+    builder
+}
+
+pub(crate) fn video_frame(profile: &Profile, image: Image) -> Frame {
+    let mut builder = base_frame(profile);
+    builder.set_image(image);
+    builder.build()
+}
@@ -0,0 +1,421 @@
+//! Solid color, color bars and frame counter.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    DEFAULT_LENGTH, base_frame, default_pixel_format, frame_size, parse_color,
+    set_timeline_property, video_frame,
+};
+use crate::{
+    frame::Frame,
+    image::{Image, draw, format::PixelFormat},
+    profile::Profile,
+    service::{
+        Producer, ProducerState, Result, Service, ServiceError, enum_property, string_property,
+    },
+    structures::object::{Object, ObjectValues},
+    timecode::Timebase,
+};
+
+/// Image of a generator rendered once and shared by every frame.
+#[derive(Debug, Default)]
+struct Cache(Option<Image>);
+
+impl Cache {
+    fn get_or_render(&mut self, render: impl FnOnce() -> Result<Image>) -> Result<Image> {
+        if self.0.is_none() {
+            self.0 = Some(render()?.into_shared());
+        }
+        Ok(self.0.clone().expect("image rendered"))
+    }
+}
+
+/// Apply the `format` property, returns `false` for other keys.
+fn set_format_property(format: &mut PixelFormat, key: &str, value: &ObjectValues) -> Result<bool> {
+    if key != "format" {
+        return Ok(false);
+    }
+    *format = enum_property(key, value)?;
+    Ok(true)
+}
+
+/// Solid color.
+///
+/// Properties: `color` (`#rrggbb[aa]` or a name), `format`, `length`.
+#[derive(Debug)]
+pub struct ColorProducer {
+    state: ProducerState,
+    rgba: [f64; 4],
+    format: PixelFormat,
+    cache: Cache,
+}
+
+impl ColorProducer {
+    pub fn new(profile: &Profile, rgba: [f64; 4]) -> Self {
+        Self {
+            state: ProducerState::new(profile, DEFAULT_LENGTH),
+            rgba,
+            format: default_pixel_format(profile),
+            cache: Cache::default(),
+        }
+    }
+
+    pub fn rgba(&self) -> [f64; 4] {
+        self.rgba
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+}
+
+impl Service for ColorProducer {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        if key == "color" {
+            self.rgba = parse_color(string_property(key, &value)?)
+                .ok_or_else(|| ServiceError::invalid_property(key, "invalid color"))?;
+            self.cache = Cache::default();
+        } else if set_format_property(&mut self.format, key, &value)? {
+            self.cache = Cache::default();
+        } else {
+            set_timeline_property(&mut self.state, key, &value)?;
+        }
+
+        self.state.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Producer for ColorProducer {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, _position: i64) -> Result<Frame> {
+        let profile = &self.state.profile;
+        let image = self.cache.get_or_render(|| {
+            let (width, height) = frame_size(profile);
+            let mut image = Image::new(self.format, width, height)?;
+            draw::fill(&mut image, self.rgba, &profile.color)?;
+            Ok(image)
+        })?;
+
+        Ok(video_frame(profile, image))
+    }
+}
+
+/// Layout of [`BarsProducer`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BarsStyle {
+    /// SMPTE EG 1 bars with castellations, -I, +Q and PLUGE.
+    #[default]
+    Smpte,
+    /// EBU 100/0/75/0 bars.
+    Ebu,
+}
+
+const WHITE_75: [f64; 4] = [0.75, 0.75, 0.75, 1.0];
+const YELLOW_75: [f64; 4] = [0.75, 0.75, 0.0, 1.0];
+const CYAN_75: [f64; 4] = [0.0, 0.75, 0.75, 1.0];
+const GREEN_75: [f64; 4] = [0.0, 0.75, 0.0, 1.0];
+const MAGENTA_75: [f64; 4] = [0.75, 0.0, 0.75, 1.0];
+const RED_75: [f64; 4] = [0.75, 0.0, 0.0, 1.0];
+const BLUE_75: [f64; 4] = [0.0, 0.0, 0.75, 1.0];
+const BLACK: [f64; 4] = [0.0, 0.0, 0.0, 1.0];
+const WHITE: [f64; 4] = [1.0, 1.0, 1.0, 1.0];
+/// -I and +Q, approximated in RGB.
+const MINUS_I: [f64; 4] = [0.0, 0.129, 0.298, 1.0];
+const PLUS_Q: [f64; 4] = [0.196, 0.0, 0.416, 1.0];
+const SUPER_BLACK: [f64; 4] = [-0.04, -0.04, -0.04, 1.0];
+const NEAR_BLACK: [f64; 4] = [0.04, 0.04, 0.04, 1.0];
+
+/// Draw bars of the given relative widths across rows `y0..y1`.
+fn draw_row(
+    image: &mut Image,
+    rows: (usize, usize),
+    bars: &[(f64, [f64; 4])],
+    profile: &Profile,
+) -> Result<()> {
+    let width = image.width() as f64;
+    let mut start = 0.0;
+
+    for &(share, rgba) in bars {
+        let x0 = (start * width).round() as usize;
+        start += share;
+        let x1 = (start * width).round() as usize;
+        draw::fill_rect(
+            image,
+            (x0, rows.0, x1 - x0, rows.1 - rows.0),
+            rgba,
+            &profile.color,
+        )?;
+    }
+    Ok(())
+}
+
+/// Color bars encoded with the matrix and range of the profile.
+///
+/// Properties: `style` (`Smpte` or `Ebu`), `format`, `length`.
+#[derive(Debug)]
+pub struct BarsProducer {
+    state: ProducerState,
+    style: BarsStyle,
+    format: PixelFormat,
+    cache: Cache,
+}
+
+impl BarsProducer {
+    pub fn new(profile: &Profile, style: BarsStyle) -> Self {
+        Self {
+            state: ProducerState::new(profile, DEFAULT_LENGTH),
+            style,
+            format: default_pixel_format(profile),
+            cache: Cache::default(),
+        }
+    }
+
+    pub fn style(&self) -> BarsStyle {
+        self.style
+    }
+
+    fn render(&self) -> Result<Image> {
+        let profile = &self.state.profile;
+        let (width, height) = frame_size(profile);
+        let mut image = Image::new(self.format, width, height)?;
+        let seventh = 1.0 / 7.0;
+
+        match self.style {
+            BarsStyle::Ebu => {
+                let bars = [
+                    WHITE, YELLOW_75, CYAN_75, GREEN_75, MAGENTA_75, RED_75, BLUE_75, BLACK,
+                ];
+                let bars = bars.map(|rgba| (1.0 / 8.0, rgba));
+                draw_row(&mut image, (0, height), &bars, profile)?;
+            }
+            BarsStyle::Smpte => {
+                let top = height * 2 / 3;
+                let middle = height * 3 / 4;
+
+                let bars = [
+                    WHITE_75, YELLOW_75, CYAN_75, GREEN_75, MAGENTA_75, RED_75, BLUE_75,
+                ]
+                .map(|rgba| (seventh, rgba));
+                draw_row(&mut image, (0, top), &bars, profile)?;
+
+                let castellations = [BLUE_75, BLACK, MAGENTA_75, BLACK, CYAN_75, BLACK, WHITE_75]
+                    .map(|rgba| (seventh, rgba));
+                draw_row(&mut image, (top, middle), &castellations, profile)?;
+
+                let wide = seventh * 5.0 / 4.0;
+                let pluge = seventh / 3.0;
+                let bottom = [
+                    (wide, MINUS_I),
+                    (wide, WHITE),
+                    (wide, PLUS_Q),
+                    (wide, BLACK),
+                    (pluge, SUPER_BLACK),
+                    (pluge, BLACK),
+                    (pluge, NEAR_BLACK),
+                    (seventh, BLACK),
+                ];
+                draw_row(&mut image, (middle, height), &bottom, profile)?;
+            }
+        }
+
+        Ok(image)
+    }
+}
+
+impl Service for BarsProducer {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        if key == "style" {
+            self.style = enum_property(key, &value)?;
+            self.cache = Cache::default();
+        } else if set_format_property(&mut self.format, key, &value)? {
+            self.cache = Cache::default();
+        } else {
+            set_timeline_property(&mut self.state, key, &value)?;
+        }
+
+        self.state.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Producer for BarsProducer {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, _position: i64) -> Result<Frame> {
+        let mut cache = std::mem::take(&mut self.cache);
+        let image = cache.get_or_render(|| self.render());
+        self.cache = cache;
+
+        Ok(video_frame(&self.state.profile, image?))
+    }
+}
+
+/// 3x5 bitmaps of the timecode characters, one row per byte, high bit left.
+fn glyph(character: char) -> [u8; 5] {
+    match character {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        ';' => [0b000, 0b010, 0b000, 0b010, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        _ => [0; 5],
+    }
+}
+
+/// Timecode burn with a bar sweeping once per second.
+///
+/// The frame also carries the label in its `timecode` property.
+/// Properties: `color`, `background`, `format`, `length`.
+#[derive(Debug)]
+pub struct CounterProducer {
+    state: ProducerState,
+    foreground: [f64; 4],
+    background: [f64; 4],
+    format: PixelFormat,
+}
+
+impl CounterProducer {
+    pub fn new(profile: &Profile) -> Self {
+        Self {
+            state: ProducerState::new(profile, DEFAULT_LENGTH),
+            foreground: WHITE,
+            background: [0.1, 0.1, 0.1, 1.0],
+            format: default_pixel_format(profile),
+        }
+    }
+
+    fn draw_text(&self, image: &mut Image, text: &str) -> Result<()> {
+        let color = self.state.profile.color;
+        let columns = text.chars().count() * 4 - 1;
+        let scale = (image.width() / (columns + 2))
+            .min(image.height() / 10)
+            .max(1);
+        let x = (image.width() - (columns * scale).min(image.width())) / 2;
+        let y = (image.height() - (5 * scale).min(image.height())) / 2;
+
+        for (index, character) in text.chars().enumerate() {
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) != 0 {
+                        let rect = (
+                            x + (index * 4 + column) * scale,
+                            y + row * scale,
+                            scale,
+                            scale,
+                        );
+                        draw::fill_rect(image, rect, self.foreground, &color)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Service for CounterProducer {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        let color = || {
+            parse_color(string_property(key, &value)?)
+                .ok_or_else(|| ServiceError::invalid_property(key, "invalid color"))
+        };
+
+        match key {
+            "color" => self.foreground = color()?,
+            "background" => self.background = color()?,
+            _ => {
+                if !set_format_property(&mut self.format, key, &value)? {
+                    set_timeline_property(&mut self.state, key, &value)?;
+                }
+            }
+        }
+
+        self.state.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Producer for CounterProducer {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, position: i64) -> Result<Frame> {
+        let profile = &self.state.profile;
+        let timebase = Timebase::from_profile(profile)
+            .map_err(|err| ServiceError::invalid_property("frame_rate", err.to_string()))?;
+        let label = timebase.to_timecode(position).to_string();
+
+        let (width, height) = frame_size(profile);
+        let mut image = Image::new(self.format, width, height)?;
+        draw::fill(&mut image, self.background, &profile.color)?;
+        self.draw_text(&mut image, &label)?;
+
+        let fps = timebase.nominal_fps();
+        let sweep = width * (position.rem_euclid(fps) + 1) as usize / fps as usize;
+        let bar = (height / 40).max(1);
+        draw::fill_rect(
+            &mut image,
+            (0, height - bar, sweep, bar),
+            self.foreground,
+            &profile.color,
+        )?;
+
+        let mut frame = base_frame(profile);
+        frame.set_image(image);
+        let mut frame = frame.build();
+        frame
+            .properties
+            .set("timecode", ObjectValues::String(label));
+        Ok(frame)
+    }
+}
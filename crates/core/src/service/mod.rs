@@ -0,0 +1,430 @@
+//! Services producing, processing and consuming [`Frame`]s.
+//!
+//! Every service is configured through an [`Object`] property bag, so
+//! plugins share one contract.
+
+pub mod filtered;
+pub mod fit;
+pub mod generator;
+pub mod playlist;
+pub mod render;
+pub mod sequence;
+pub mod tractor;
+pub mod transition;
+pub mod wav;
+pub mod y4m;
+
+use std::{
+    fmt,
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{
+    audio::AudioError,
+    frame::Frame,
+    image::ImageError,
+    profile::Profile,
+    structures::object::{Object, ObjectValues},
+};
+
+#[derive(Debug)]
+pub enum ServiceError {
+    /// Property has an unsupported type or value.
+    InvalidProperty {
+        key: String,
+        reason: String,
+    },
+    /// Position is outside of the producer.
+    OutOfRange(i64),
+    /// In point is after the out point or outside of the producer.
+    InvalidRange {
+        in_point: i64,
+        out_point: i64,
+    },
+    /// Entry index is out of range.
+    InvalidEntry(usize),
+    /// Entries do not continue each other.
+    CannotJoin(usize),
+    /// Frames cannot be combined, e.g. images of different sizes.
+    Mismatch(String),
+    /// Stream or file content is malformed or unsupported.
+    InvalidData(String),
+    Image(ImageError),
+    Audio(AudioError),
+    Io(std::io::Error),
+}
+
+impl ServiceError {
+    pub fn invalid_property(key: &str, reason: impl Into<String>) -> Self {
+        ServiceError::InvalidProperty {
+            key: key.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::InvalidProperty { key, reason } => {
+                write!(f, "invalid property `{key}`: {reason}")
+            }
+            ServiceError::OutOfRange(position) => write!(f, "position {position} is out of range"),
+            ServiceError::InvalidRange {
+                in_point,
+                out_point,
+            } => write!(f, "invalid range {in_point}..={out_point}"),
+            ServiceError::InvalidEntry(index) => write!(f, "no entry at index {index}"),
+            ServiceError::CannotJoin(index) => {
+                write!(f, "entry {index} does not continue the previous entry")
+            }
+            ServiceError::Mismatch(reason) => write!(f, "frames do not match: {reason}"),
+            ServiceError::InvalidData(reason) => write!(f, "invalid data: {reason}"),
+            ServiceError::Image(err) => write!(f, "{err}"),
+            ServiceError::Audio(err) => write!(f, "{err}"),
+            ServiceError::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ServiceError::Image(err) => Some(err),
+            ServiceError::Audio(err) => Some(err),
+            ServiceError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ImageError> for ServiceError {
+    fn from(err: ImageError) -> Self {
+        ServiceError::Image(err)
+    }
+}
+
+impl From<AudioError> for ServiceError {
+    fn from(err: AudioError) -> Self {
+        ServiceError::Audio(err)
+    }
+}
+
+impl From<std::io::Error> for ServiceError {
+    fn from(err: std::io::Error) -> Self {
+        ServiceError::Io(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ServiceError>;
+
+pub(crate) fn int_property(key: &str, value: &ObjectValues) -> Result<i64> {
+    match value {
+        ObjectValues::Int(value) => Ok(*value as i64),
+        ObjectValues::Int64(value) => Ok(*value),
+        ObjectValues::Double(value) if value.fract() == 0.0 => Ok(*value as i64),
+        ObjectValues::String(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| ServiceError::invalid_property(key, "expected an integer")),
+        _ => Err(ServiceError::invalid_property(key, "expected an integer")),
+    }
+}
+
+pub(crate) fn float_property(key: &str, value: &ObjectValues) -> Result<f64> {
+    match value {
+        ObjectValues::Int(value) => Ok(*value as f64),
+        ObjectValues::Int64(value) => Ok(*value as f64),
+        ObjectValues::Double(value) => Ok(*value as f64),
+        ObjectValues::String(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| ServiceError::invalid_property(key, "expected a number")),
+        _ => Err(ServiceError::invalid_property(key, "expected a number")),
+    }
+}
+
+pub(crate) fn string_property<'a>(key: &str, value: &'a ObjectValues) -> Result<&'a str> {
+    match value {
+        ObjectValues::String(value) => Ok(value),
+        _ => Err(ServiceError::invalid_property(key, "expected a string")),
+    }
+}
+
+/// Value of an enum deriving `Deserialize` from its variant name, e.g.
+/// `Yuv420p` for a [`PixelFormat`].
+pub(crate) fn enum_property<T: serde::de::DeserializeOwned>(
+    key: &str,
+    value: &ObjectValues,
+) -> Result<T> {
+    let name = string_property(key, value)?;
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| ServiceError::invalid_property(key, format!("unknown value `{name}`")))
+}
+
+/// Common part of every service.
+pub trait Service {
+    fn properties(&self) -> &Object;
+
+    fn properties_mut(&mut self) -> &mut Object;
+
+    /// Set one property. Services reading their configuration from the
+    /// properties override this to validate and apply the value.
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        self.properties_mut().set(key, value);
+        Ok(())
+    }
+
+    /// Set every property of `properties`.
+    fn configure(&mut self, properties: &Object) -> Result<()> {
+        let mut keys = properties.keys().collect::<Vec<_>>();
+        keys.sort();
+
+        for key in keys {
+            if let Some(value) = properties.get(key) {
+                self.set_property(key, value.clone())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Timeline state shared by producers: length, in/out points, playhead
+/// and speed.
+#[derive(Debug, Clone)]
+pub struct ProducerState {
+    pub profile: Profile,
+    pub properties: Object,
+    length: i64,
+    in_point: i64,
+    out_point: i64,
+    position: i64,
+    speed: i8,
+}
+
+impl ProducerState {
+    /// State of a producer with `length` frames at the profile rate, using
+    /// the whole length.
+    pub fn new(profile: &Profile, length: i64) -> Self {
+        let length = length.max(0);
+
+        Self {
+            profile: profile.clone(),
+            properties: Object::default(),
+            length,
+            in_point: 0,
+            out_point: length - 1,
+            position: 0,
+            speed: 1,
+        }
+    }
+
+    pub fn length(&self) -> i64 {
+        self.length
+    }
+
+    /// Change the length, clamping the in and out points.
+    pub fn set_length(&mut self, length: i64) {
+        let whole = self.out_point == self.length - 1;
+
+        self.length = length.max(0);
+        self.in_point = self.in_point.clamp(0, self.length);
+        self.out_point = if whole {
+            self.length - 1
+        } else {
+            self.out_point.min(self.length - 1)
+        };
+    }
+
+    pub fn in_point(&self) -> i64 {
+        self.in_point
+    }
+
+    pub fn out_point(&self) -> i64 {
+        self.out_point
+    }
+
+    pub fn set_in_out(&mut self, in_point: i64, out_point: i64) -> Result<()> {
+        if in_point < 0 || out_point >= self.length || in_point > out_point + 1 {
+            return Err(ServiceError::InvalidRange {
+                in_point,
+                out_point,
+            });
+        }
+
+        self.in_point = in_point;
+        self.out_point = out_point;
+        self.position = self.position.clamp(0, self.playtime());
+        Ok(())
+    }
+
+    /// Frames between the in and out points.
+    pub fn playtime(&self) -> i64 {
+        self.out_point - self.in_point + 1
+    }
+
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    pub fn speed(&self) -> i8 {
+        self.speed
+    }
+}
+
+/// Source of frames.
+///
+/// Positions are frame numbers at the profile rate relative to the in
+/// point, `0..playtime`.
+pub trait Producer: Service + Send {
+    fn state(&self) -> &ProducerState;
+
+    fn state_mut(&mut self) -> &mut ProducerState;
+
+    /// Frame at `position` of the whole source, in `0..length`.
+    fn produce(&mut self, position: i64) -> Result<Frame>;
+
+    fn profile(&self) -> &Profile {
+        &self.state().profile
+    }
+
+    /// Frames of the whole source.
+    fn length(&self) -> i64 {
+        self.state().length()
+    }
+
+    fn in_point(&self) -> i64 {
+        self.state().in_point()
+    }
+
+    fn out_point(&self) -> i64 {
+        self.state().out_point()
+    }
+
+    fn set_in_out(&mut self, in_point: i64, out_point: i64) -> Result<()> {
+        self.state_mut().set_in_out(in_point, out_point)
+    }
+
+    fn playtime(&self) -> i64 {
+        self.state().playtime()
+    }
+
+    /// Position of the next frame returned by [`Producer::next_frame`].
+    fn position(&self) -> i64 {
+        self.state().position()
+    }
+
+    /// Move the playhead, `playtime` is the end of the producer.
+    fn seek(&mut self, position: i64) -> Result<()> {
+        if !(0..=self.playtime()).contains(&position) {
+            return Err(ServiceError::OutOfRange(position));
+        }
+        self.state_mut().position = position;
+        Ok(())
+    }
+
+    fn speed(&self) -> i8 {
+        self.state().speed()
+    }
+
+    /// Frames the playhead advances per [`Producer::next_frame`].
+    fn set_speed(&mut self, speed: i8) {
+        self.state_mut().speed = speed;
+    }
+
+    /// Frame at `position`, stamped with the position and speed.
+    fn get_frame(&mut self, position: i64) -> Result<Frame> {
+        if !(0..self.playtime()).contains(&position) {
+            return Err(ServiceError::OutOfRange(position));
+        }
+
+        let mut frame = self.produce(self.in_point() + position)?;
+        frame.set_position(position);
+        frame.set_speed(self.speed());
+        Ok(frame)
+    }
+
+    /// Frame at the playhead, advancing it by the speed. `None` at either
+    /// end of the producer.
+    fn next_frame(&mut self) -> Result<Option<Frame>> {
+        let position = self.position();
+        if !(0..self.playtime()).contains(&position) {
+            return Ok(None);
+        }
+
+        let frame = self.get_frame(position)?;
+        let next = (position + self.speed() as i64).clamp(-1, self.playtime());
+        self.state_mut().position = next;
+        Ok(Some(frame))
+    }
+}
+
+/// Producer used from several places, e.g. split playlist entries.
+pub type SharedProducer = Arc<Mutex<dyn Producer>>;
+
+pub fn shared(producer: impl Producer + 'static) -> SharedProducer {
+    Arc::new(Mutex::new(producer))
+}
+
+/// Lock `producer`, ignoring poisoning by a panicked user.
+pub fn lock(producer: &SharedProducer) -> MutexGuard<'_, dyn Producer + 'static> {
+    producer
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Processing applied to frames, e.g. a color correction.
+pub trait Filter: Service + Send {
+    fn process(&mut self, frame: &mut Frame) -> Result<()>;
+}
+
+/// Blend of two frames over a time range, e.g. a dissolve.
+pub trait Transition: Service + Send {
+    /// Blend `b` into `a`. `progress` goes from `0.0`, only `a`, to `1.0`,
+    /// only `b`, and covers the duration of the frame.
+    fn process(&mut self, a: &mut Frame, b: &Frame, progress: Range<f64>) -> Result<()>;
+}
+
+/// Destination of frames, pulled at the rate of its profile.
+pub trait Consumer: Service + Send {
+    fn profile(&self) -> &Profile;
+
+    fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn consume(&mut self, frame: &Frame) -> Result<()>;
+
+    /// Flush pending output.
+    fn stop(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Pull every remaining frame of `producer` through `filters`, returns
+    /// the number of frames consumed.
+    ///
+    /// A paused producer, at speed `0`, never reaches its end and is
+    /// rejected before the consumer is started.
+    fn run(&mut self, producer: &mut dyn Producer, filters: &mut [Box<dyn Filter>]) -> Result<i64> {
+        if producer.speed() == 0 {
+            return Err(ServiceError::invalid_property(
+                "speed",
+                "producer is paused",
+            ));
+        }
+        self.start()?;
+
+        let mut count = 0;
+        while let Some(mut frame) = producer.next_frame()? {
+            for filter in filters.iter_mut() {
+                filter.process(&mut frame)?;
+            }
+            self.consume(&frame)?;
+            count += 1;
+        }
+
+        self.stop()?;
+        Ok(count)
+    }
+}
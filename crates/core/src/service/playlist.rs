@@ -0,0 +1,456 @@
+//! Sequence of clips and blanks played one after another.
+
+use std::sync::Arc;
+
+use super::{Producer, ProducerState, Result, Service, ServiceError, SharedProducer, lock};
+use crate::{
+    frame::{Frame, FrameBuilder},
+    profile::{Profile, Ratio},
+    structures::object::Object,
+};
+
+/// Clip of a producer between in and out points, or a blank gap.
+///
+/// Clip points are frames of the producer, at its own profile rate.
+#[derive(Clone)]
+pub struct PlaylistEntry {
+    producer: Option<SharedProducer>,
+    in_point: i64,
+    out_point: i64,
+}
+
+impl PlaylistEntry {
+    /// Clip of `producer` from `in_point` to `out_point` inclusive.
+    pub fn clip(producer: SharedProducer, in_point: i64, out_point: i64) -> Result<Self> {
+        let length = lock(&producer).length();
+        if in_point < 0 || out_point >= length || in_point > out_point {
+            return Err(ServiceError::InvalidRange {
+                in_point,
+                out_point,
+            });
+        }
+
+        Ok(Self {
+            producer: Some(producer),
+            in_point,
+            out_point,
+        })
+    }
+
+    /// Clip of the producer between its own in and out points.
+    pub fn whole(producer: SharedProducer) -> Result<Self> {
+        let (in_point, out_point) = {
+            let producer = lock(&producer);
+            (producer.in_point(), producer.out_point())
+        };
+        Self::clip(producer, in_point, out_point)
+    }
+
+    /// Gap of `length` frames at the playlist rate.
+    pub fn blank(length: i64) -> Self {
+        Self {
+            producer: None,
+            in_point: 0,
+            out_point: length.max(1) - 1,
+        }
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.producer.is_none()
+    }
+
+    pub fn producer(&self) -> Option<&SharedProducer> {
+        self.producer.as_ref()
+    }
+
+    pub fn in_point(&self) -> i64 {
+        self.in_point
+    }
+
+    pub fn out_point(&self) -> i64 {
+        self.out_point
+    }
+
+    /// Frames between the in and out points, at the producer rate.
+    pub fn playtime(&self) -> i64 {
+        self.out_point - self.in_point + 1
+    }
+
+    fn frame_rate(&self) -> Option<Ratio> {
+        self.producer
+            .as_ref()
+            .map(|producer| lock(producer).profile().frame_rate)
+    }
+}
+
+impl std::fmt::Debug for PlaylistEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlaylistEntry")
+            .field("blank", &self.is_blank())
+            .field("in_point", &self.in_point)
+            .field("out_point", &self.out_point)
+            .finish()
+    }
+}
+
+/// Producer playing its entries in order.
+///
+/// Positions are frames at the playlist profile rate. Clips at other rates
+/// are stretched to their duration, repeating or skipping frames.
+pub struct Playlist {
+    state: ProducerState,
+    entries: Vec<PlaylistEntry>,
+    /// First playlist frame of every entry followed by the total length,
+    /// rebuilt on every edit.
+    starts: Vec<i64>,
+}
+
+impl Playlist {
+    pub fn new(profile: &Profile) -> Self {
+        Self {
+            state: ProducerState::new(profile, 0),
+            entries: Vec::new(),
+            starts: vec![0],
+        }
+    }
+
+    pub fn entries(&self) -> &[PlaylistEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, index: usize) -> Option<&PlaylistEntry> {
+        self.entries.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Append the producer between its in and out points.
+    pub fn append(&mut self, producer: SharedProducer) -> Result<()> {
+        self.push(PlaylistEntry::whole(producer)?);
+        Ok(())
+    }
+
+    pub fn append_clip(
+        &mut self,
+        producer: SharedProducer,
+        in_point: i64,
+        out_point: i64,
+    ) -> Result<()> {
+        self.push(PlaylistEntry::clip(producer, in_point, out_point)?);
+        Ok(())
+    }
+
+    pub fn append_blank(&mut self, length: i64) {
+        self.push(PlaylistEntry::blank(length));
+    }
+
+    pub fn push(&mut self, entry: PlaylistEntry) {
+        self.entries.push(entry);
+        self.update_length();
+    }
+
+    /// Insert `entry` before `index`, `len()` appends.
+    pub fn insert(&mut self, index: usize, entry: PlaylistEntry) -> Result<()> {
+        if index > self.entries.len() {
+            return Err(ServiceError::InvalidEntry(index));
+        }
+
+        self.entries.insert(index, entry);
+        self.update_length();
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<PlaylistEntry> {
+        if index >= self.entries.len() {
+            return Err(ServiceError::InvalidEntry(index));
+        }
+
+        let entry = self.entries.remove(index);
+        self.update_length();
+        Ok(entry)
+    }
+
+    /// Move entry `from` so it ends up at index `to`.
+    pub fn move_entry(&mut self, from: usize, to: usize) -> Result<()> {
+        for index in [from, to] {
+            if index >= self.entries.len() {
+                return Err(ServiceError::InvalidEntry(index));
+            }
+        }
+
+        let entry = self.entries.remove(from);
+        self.entries.insert(to, entry);
+        self.update_length();
+        Ok(())
+    }
+
+    /// Change the in and out points of entry `index`.
+    pub fn resize(&mut self, index: usize, in_point: i64, out_point: i64) -> Result<()> {
+        let entry = self
+            .entries
+            .get(index)
+            .ok_or(ServiceError::InvalidEntry(index))?;
+
+        let entry = match &entry.producer {
+            Some(producer) => PlaylistEntry::clip(producer.clone(), in_point, out_point)?,
+            None if in_point <= out_point => PlaylistEntry::blank(out_point - in_point + 1),
+            None => {
+                return Err(ServiceError::InvalidRange {
+                    in_point,
+                    out_point,
+                });
+            }
+        };
+
+        self.entries[index] = entry;
+        self.update_length();
+        Ok(())
+    }
+
+    /// Split entry `index` before its frame `position`, relative to its in
+    /// point at the producer rate. The second part becomes `index + 1`.
+    pub fn split(&mut self, index: usize, position: i64) -> Result<()> {
+        let entry = self
+            .entries
+            .get(index)
+            .ok_or(ServiceError::InvalidEntry(index))?;
+
+        if position <= 0 || position >= entry.playtime() {
+            return Err(ServiceError::OutOfRange(position));
+        }
+
+        let split = entry.in_point + position;
+        let second = PlaylistEntry {
+            in_point: split,
+            ..entry.clone()
+        };
+
+        self.entries[index].out_point = split - 1;
+        self.entries.insert(index + 1, second);
+        self.update_length();
+        Ok(())
+    }
+
+    /// Join entry `index` with the `count` entries following it. Clips must
+    /// continue each other in the same producer.
+    pub fn join(&mut self, index: usize, count: usize) -> Result<()> {
+        let last = index + count;
+        if last >= self.entries.len() {
+            return Err(ServiceError::InvalidEntry(last));
+        }
+
+        for next in index + 1..=last {
+            let (previous, entry) = (&self.entries[next - 1], &self.entries[next]);
+            let continues = match (&previous.producer, &entry.producer) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b) && entry.in_point == previous.out_point + 1,
+                (None, None) => true,
+                _ => false,
+            };
+            if !continues {
+                return Err(ServiceError::CannotJoin(next));
+            }
+        }
+
+        let joined = self.entries.drain(index + 1..=last).collect::<Vec<_>>();
+        let entry = &mut self.entries[index];
+        if entry.is_blank() {
+            entry.out_point += joined.iter().map(PlaylistEntry::playtime).sum::<i64>();
+        } else if let Some(last) = joined.last() {
+            entry.out_point = last.out_point;
+        }
+
+        self.update_length();
+        Ok(())
+    }
+
+    /// Length of `entry` in playlist frames.
+    fn entry_length(&self, entry: &PlaylistEntry) -> i64 {
+        match entry.frame_rate() {
+            Some(rate) if rate != self.state.profile.frame_rate => {
+                let duration = Ratio::from_integer(entry.playtime()) / rate;
+                (duration * self.state.profile.frame_rate).round().max(1)
+            }
+            _ => entry.playtime(),
+        }
+    }
+
+    /// First playlist frame of entry `index`, `len()` gives the total length.
+    pub fn entry_start(&self, index: usize) -> Option<i64> {
+        self.starts.get(index).copied()
+    }
+
+    /// Entry index and frame relative to its in point, at the producer rate,
+    /// of playlist `position`.
+    pub fn locate(&self, position: i64) -> Option<(usize, i64)> {
+        if position < 0 {
+            return None;
+        }
+
+        if position >= self.starts[self.entries.len()] {
+            return None;
+        }
+
+        let index = self.starts.partition_point(|&start| start <= position) - 1;
+        let entry = &self.entries[index];
+        let local = position - self.starts[index];
+        let local = match entry.frame_rate() {
+            Some(rate) if rate != self.state.profile.frame_rate => {
+                (Ratio::from_integer(local) * rate / self.state.profile.frame_rate).floor()
+            }
+            _ => local,
+        };
+        Some((index, local.min(entry.playtime() - 1)))
+    }
+
+    /// Rebuild the entry offsets and the playlist length after an edit.
+    fn update_length(&mut self) {
+        let mut start = 0;
+        let mut starts = Vec::with_capacity(self.entries.len() + 1);
+        starts.push(start);
+        for entry in &self.entries {
+            start += self.entry_length(entry);
+            starts.push(start);
+        }
+
+        self.starts = starts;
+        self.state.set_length(start);
+    }
+
+    fn blank_frame(&self) -> Frame {
+        let mut builder = FrameBuilder::new();
+        builder.set_profile(&self.state.profile);
+        builder.set_color(self.state.profile.color);
+        builder.build()
+    }
+}
+
+impl Service for Playlist {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+}
+
+impl Producer for Playlist {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, position: i64) -> Result<Frame> {
+        let (index, local) = self
+            .locate(position)
+            .ok_or(ServiceError::OutOfRange(position))?;
+        let entry = &self.entries[index];
+
+        let mut frame = match &entry.producer {
+            Some(producer) => lock(producer).produce(entry.in_point + local)?,
+            None => self.blank_frame(),
+        };
+        frame.set_speed(self.state.speed());
+        Ok(frame)
+    }
+}
+
+impl std::fmt::Debug for Playlist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Playlist")
+            .field("state", &self.state)
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        profile::presets,
+        service::{generator::video::ColorProducer, shared},
+    };
+
+    fn profile(id: &str) -> Profile {
+        presets::get(id).unwrap().profile.clone()
+    }
+
+    /// Linear scan over the entries, as `locate` did before caching.
+    fn scan(playlist: &Playlist, position: i64) -> Option<(usize, i64)> {
+        if position < 0 {
+            return None;
+        }
+
+        let mut start = 0;
+        for (index, entry) in playlist.entries().iter().enumerate() {
+            let length = playlist.entry_length(entry);
+            if position < start + length {
+                let local = match entry.frame_rate() {
+                    Some(rate) if rate != playlist.state.profile.frame_rate => {
+                        (Ratio::from_integer(position - start) * rate
+                            / playlist.state.profile.frame_rate)
+                            .floor()
+                    }
+                    _ => position - start,
+                };
+                return Some((index, local.min(entry.playtime() - 1)));
+            }
+            start += length;
+        }
+        None
+    }
+
+    fn assert_consistent(playlist: &Playlist) {
+        for position in -1..=playlist.length() {
+            assert_eq!(playlist.locate(position), scan(playlist, position));
+        }
+        let mut start = 0;
+        for (index, entry) in playlist.entries().iter().enumerate() {
+            assert_eq!(playlist.entry_start(index), Some(start));
+            start += playlist.entry_length(entry);
+        }
+        assert_eq!(
+            playlist.entry_start(playlist.len()),
+            Some(playlist.length())
+        );
+        assert_eq!(playlist.entry_start(playlist.len() + 1), None);
+    }
+
+    #[test]
+    fn offsets_follow_edits() {
+        let pal = profile("hd_1080p_25");
+        let fast = shared(ColorProducer::new(&profile("hd_1080p_50"), [1.0; 4]));
+        let slow = shared(ColorProducer::new(&pal, [0.0; 4]));
+
+        let mut playlist = Playlist::new(&pal);
+        assert_consistent(&playlist);
+        playlist.append_clip(slow.clone(), 0, 9).unwrap();
+        playlist.append_blank(5);
+        playlist.append_clip(fast.clone(), 10, 29).unwrap();
+        assert_consistent(&playlist);
+        assert_eq!(playlist.length(), 25);
+
+        playlist.move_entry(2, 0).unwrap();
+        assert_consistent(&playlist);
+        playlist.split(1, 4).unwrap();
+        assert_consistent(&playlist);
+        playlist.resize(3, 0, 1).unwrap();
+        assert_consistent(&playlist);
+        playlist.join(1, 1).unwrap();
+        assert_consistent(&playlist);
+        playlist.insert(1, PlaylistEntry::blank(3)).unwrap();
+        assert_consistent(&playlist);
+        playlist.remove(0).unwrap();
+        assert_consistent(&playlist);
+    }
+}
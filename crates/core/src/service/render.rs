@@ -0,0 +1,588 @@
+//! Threaded driver pulling frames from a producer into a consumer.
+//!
+//! Worker threads render up to `lookahead` frames ahead of the consumer
+//! into a reorder buffer. The consumer thread either takes them in order
+//! as fast as possible, or paced to the wall clock at the profile frame
+//! rate, dropping frames it fell behind on and repeating the last one
+//! while the next is not ready, showing that one as soon as it arrives.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::{Consumer, Filter, Result, ServiceError, SharedProducer, lock};
+use crate::frame::Frame;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderMode {
+    /// Pace to the wall clock, for playback.
+    #[default]
+    RealTime,
+    /// Consume every frame in order without waiting, for export.
+    AsFastAsPossible,
+}
+
+/// Counters of a [`RenderLoop`] since it was created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Frames passed to the consumer, repeats included.
+    pub consumed: i64,
+    /// Positions skipped because they were not ready in time.
+    pub dropped: i64,
+    /// Times the previous frame was consumed again.
+    pub repeated: i64,
+}
+
+/// Details of a consumed frame, passed to the frame-ready callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameReport {
+    /// Position the frame was consumed at.
+    pub position: i64,
+    /// The frame is a repeat of the previous one.
+    pub repeated: bool,
+    /// Positions dropped right before this one.
+    pub dropped: i64,
+}
+
+pub type FrameCallback = Box<dyn FnMut(&Frame, &FrameReport) + Send>;
+
+/// Consumer and callback, moved into the consumer thread while running.
+struct Output {
+    consumer: Box<dyn Consumer>,
+    callback: Option<FrameCallback>,
+}
+
+struct State {
+    running: bool,
+    /// Incremented by seeks, frames of older generations are discarded.
+    generation: u64,
+    /// Next position handed to a worker.
+    dispatch: i64,
+    /// Next position the consumer takes.
+    display: i64,
+    end: i64,
+    ready: BTreeMap<i64, Frame>,
+    /// Wall clock position `clock.1` was due at `clock.0`.
+    clock: (Instant, i64),
+    stats: RenderStats,
+    error: Option<ServiceError>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    /// Record the first error and stop every thread.
+    fn fail(&self, error: ServiceError) {
+        let mut state = self.lock();
+        state.error.get_or_insert(error);
+        state.running = false;
+        self.changed.notify_all();
+    }
+}
+
+/// Driver of a producer, filters and consumer with start, stop and seek.
+pub struct RenderLoop {
+    producer: SharedProducer,
+    filters: Arc<Mutex<Vec<Box<dyn Filter>>>>,
+    output: Option<Output>,
+    mode: RenderMode,
+    lookahead: usize,
+    workers: usize,
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+    consumer_thread: Option<JoinHandle<Output>>,
+}
+
+impl RenderLoop {
+    pub fn new(producer: SharedProducer, consumer: Box<dyn Consumer>) -> Self {
+        Self {
+            producer,
+            filters: Arc::default(),
+            output: Some(Output {
+                consumer,
+                callback: None,
+            }),
+            mode: RenderMode::default(),
+            lookahead: 4,
+            workers: 1,
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    running: false,
+                    generation: 0,
+                    dispatch: 0,
+                    display: 0,
+                    end: 0,
+                    ready: BTreeMap::new(),
+                    clock: (Instant::now(), 0),
+                    stats: RenderStats::default(),
+                    error: None,
+                }),
+                changed: Condvar::new(),
+            }),
+            threads: Vec::new(),
+            consumer_thread: None,
+        }
+    }
+
+    pub fn mode(&self) -> RenderMode {
+        self.mode
+    }
+
+    /// Takes effect on the next start.
+    pub fn set_mode(&mut self, mode: RenderMode) {
+        self.mode = mode;
+    }
+
+    pub fn lookahead(&self) -> usize {
+        self.lookahead
+    }
+
+    /// Maximum frames rendered ahead of the consumer, at least one. Takes
+    /// effect on the next start.
+    pub fn set_lookahead(&mut self, frames: usize) {
+        self.lookahead = frames.max(1);
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Number of rendering threads, at least one. Takes effect on the
+    /// next start.
+    ///
+    /// The producer and the filter chain are shared, so each is used by a
+    /// single worker at a time. More workers only let one frame be filtered
+    /// while the next is produced; beyond two they rarely help.
+    pub fn set_workers(&mut self, count: usize) {
+        self.workers = count.max(1);
+    }
+
+    /// Append a filter applied by the workers in order.
+    pub fn add_filter(&mut self, filter: Box<dyn Filter>) {
+        self.filters
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .push(filter);
+    }
+
+    /// Called from the consumer thread after each frame is consumed.
+    /// Ignored while running.
+    pub fn on_frame(&mut self, callback: impl FnMut(&Frame, &FrameReport) + Send + 'static) {
+        if let Some(output) = &mut self.output {
+            output.callback = Some(Box::new(callback));
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.shared.lock().running
+    }
+
+    /// Next position to be consumed.
+    pub fn position(&self) -> i64 {
+        self.shared.lock().display
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.shared.lock().stats
+    }
+
+    /// Start rendering from the current position, or from the start once
+    /// the producer was played to its end.
+    pub fn start(&mut self) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+        if self.consumer_thread.is_some() {
+            self.join()?;
+        }
+        let Some(mut output) = self.output.take() else {
+            return Ok(());
+        };
+        if let Err(error) = output.consumer.start() {
+            self.output = Some(output);
+            return Err(error);
+        }
+
+        let end = lock(&self.producer).playtime();
+        {
+            let mut state = self.shared.lock();
+            state.end = end;
+            if state.display >= state.end {
+                state.display = 0;
+            }
+            state.running = true;
+            state.generation += 1;
+            state.dispatch = state.display;
+            state.ready.clear();
+            state.clock = (Instant::now(), state.display);
+            state.error = None;
+        }
+
+        let fps = output.consumer.profile().frame_rate.to_f64();
+        for _ in 0..self.workers {
+            let (shared, producer, filters) = (
+                self.shared.clone(),
+                self.producer.clone(),
+                self.filters.clone(),
+            );
+            let lookahead = self.lookahead as i64;
+            self.threads.push(thread::spawn(move || {
+                work(&shared, &producer, &filters, lookahead)
+            }));
+        }
+
+        let (shared, mode) = (self.shared.clone(), self.mode);
+        self.consumer_thread = Some(thread::spawn(move || {
+            consume(&shared, &mut output, mode, fps);
+            if let Err(error) = output.consumer.stop() {
+                shared.fail(error);
+            }
+            output
+        }));
+        Ok(())
+    }
+
+    /// Stop rendering, returns the first error of the threads.
+    pub fn stop(&mut self) -> Result<()> {
+        {
+            let mut state = self.shared.lock();
+            state.running = false;
+            self.shared.changed.notify_all();
+        }
+        self.join()
+    }
+
+    /// Block until the producer was played to its end or rendering
+    /// stopped, returns the first error of the threads.
+    pub fn wait(&mut self) -> Result<RenderStats> {
+        self.join()?;
+        Ok(self.stats())
+    }
+
+    /// Continue from `position`, running or not.
+    pub fn seek(&self, position: i64) -> Result<()> {
+        let mut state = self.shared.lock();
+        if position < 0 || (state.running && position > state.end) {
+            return Err(ServiceError::OutOfRange(position));
+        }
+
+        state.generation += 1;
+        state.ready.clear();
+        state.display = position;
+        state.dispatch = position;
+        state.clock = (Instant::now(), position);
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    fn join(&mut self) -> Result<()> {
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+        if let Some(thread) = self.consumer_thread.take() {
+            match thread.join() {
+                Ok(output) => self.output = Some(output),
+                Err(_) => return Err(ServiceError::InvalidData("consumer panicked".into())),
+            }
+        }
+
+        let mut state = self.shared.lock();
+        state.running = false;
+        state.ready.clear();
+        match state.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RenderLoop {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+fn work(
+    shared: &Shared,
+    producer: &SharedProducer,
+    filters: &Mutex<Vec<Box<dyn Filter>>>,
+    lookahead: i64,
+) {
+    loop {
+        let (position, generation) = {
+            let mut state = shared.lock();
+            while state.running
+                && (state.dispatch >= state.end || state.dispatch >= state.display + lookahead)
+            {
+                state = shared
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(|error| error.into_inner());
+            }
+            if !state.running {
+                return;
+            }
+            state.dispatch += 1;
+            (state.dispatch - 1, state.generation)
+        };
+
+        // Release the producer before filtering so another worker can
+        // produce the next frame meanwhile.
+        let produced = lock(producer).get_frame(position);
+        let rendered = produced.and_then(|mut frame| {
+            let mut filters = filters.lock().unwrap_or_else(|error| error.into_inner());
+            for filter in filters.iter_mut() {
+                filter.process(&mut frame)?;
+            }
+            Ok(frame)
+        });
+
+        match rendered {
+            Ok(frame) => {
+                let mut state = shared.lock();
+                if state.generation == generation && position >= state.display {
+                    state.ready.insert(position, frame);
+                    shared.changed.notify_all();
+                }
+            }
+            Err(error) => return shared.fail(error),
+        }
+    }
+}
+
+fn consume(shared: &Shared, output: &mut Output, mode: RenderMode, fps: f64) {
+    let mut last: Option<Frame> = None;
+    // Wall clock tick the last frame was consumed at, in real time mode.
+    let mut shown: Option<i64> = None;
+    let mut state = shared.lock();
+
+    while state.running {
+        if state.display >= state.end {
+            state.running = false;
+            shared.changed.notify_all();
+            break;
+        }
+        let position = state.display;
+
+        let (frame, report) = match mode {
+            RenderMode::AsFastAsPossible => {
+                let Some(frame) = state.ready.remove(&position) else {
+                    state = shared
+                        .changed
+                        .wait(state)
+                        .unwrap_or_else(|error| error.into_inner());
+                    continue;
+                };
+                let report = FrameReport {
+                    position,
+                    repeated: false,
+                    dropped: 0,
+                };
+                (frame, report)
+            }
+            RenderMode::RealTime => {
+                let (start, start_position) = state.clock;
+                let due = start + Duration::from_secs_f64((position - start_position) as f64 / fps);
+                let now = Instant::now();
+                if now < due {
+                    state = shared
+                        .changed
+                        .wait_timeout(state, due - now)
+                        .unwrap_or_else(|error| error.into_inner())
+                        .0;
+                    continue;
+                }
+
+                // Skip to the position due now when running late.
+                let elapsed = (now - start).as_secs_f64() * fps;
+                let tick = start_position + elapsed.floor() as i64;
+                let target = tick.clamp(position, state.end - 1);
+                let dropped = target - position;
+                state.ready.retain(|&ready, _| ready >= target);
+                state.display = target;
+                state.dispatch = state.dispatch.max(target);
+                state.stats.dropped += dropped;
+
+                let (frame, repeated) = match (state.ready.remove(&target), &last) {
+                    (Some(frame), _) => (frame, false),
+                    // Repeat once per tick, `target` stays due until it arrives.
+                    (None, Some(last)) if shown != Some(tick) => (last.clone(), true),
+                    (None, Some(_)) => {
+                        let next = start
+                            + Duration::from_secs_f64((tick + 1 - start_position) as f64 / fps);
+                        state = shared
+                            .changed
+                            .wait_timeout(state, next.saturating_duration_since(now))
+                            .unwrap_or_else(|error| error.into_inner())
+                            .0;
+                        continue;
+                    }
+                    (None, None) => {
+                        // Nothing to show yet, wait for the first frame.
+                        state = shared
+                            .changed
+                            .wait(state)
+                            .unwrap_or_else(|error| error.into_inner());
+                        state.clock = (Instant::now(), state.display);
+                        continue;
+                    }
+                };
+
+                state.stats.repeated += repeated as i64;
+                shown = Some(tick);
+                let report = FrameReport {
+                    position: target,
+                    repeated,
+                    dropped,
+                };
+                (frame, report)
+            }
+        };
+
+        if !report.repeated {
+            state.display = report.position + 1;
+        }
+        state.stats.consumed += 1;
+        let generation = state.generation;
+        shared.changed.notify_all();
+        drop(state);
+
+        if let Err(error) = output.consumer.consume(&frame) {
+            return shared.fail(error);
+        }
+        if let Some(callback) = &mut output.callback {
+            callback(&frame, &report);
+        }
+        last = Some(frame);
+
+        state = shared.lock();
+        if state.generation != generation {
+            last = None;
+            shown = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{self, Receiver, SyncSender};
+
+    use super::*;
+    use crate::{
+        frame::FrameBuilder,
+        profile::{Profile, Ratio, presets},
+        service::{Producer, ProducerState, Service, shared},
+        structures::object::Object,
+    };
+
+    /// Producer holding back frame 1 until told to go on.
+    struct Gated {
+        state: ProducerState,
+        gate: Mutex<Receiver<()>>,
+    }
+
+    impl Service for Gated {
+        fn properties(&self) -> &Object {
+            &self.state.properties
+        }
+
+        fn properties_mut(&mut self) -> &mut Object {
+            &mut self.state.properties
+        }
+    }
+
+    impl Producer for Gated {
+        fn state(&self) -> &ProducerState {
+            &self.state
+        }
+
+        fn state_mut(&mut self) -> &mut ProducerState {
+            &mut self.state
+        }
+
+        fn produce(&mut self, position: i64) -> Result<Frame> {
+            if position == 1 {
+                let _ = self.gate.lock().unwrap().recv();
+            }
+            Ok(FrameBuilder::new().build())
+        }
+    }
+
+    struct Null {
+        profile: Profile,
+        properties: Object,
+    }
+
+    impl Service for Null {
+        fn properties(&self) -> &Object {
+            &self.properties
+        }
+
+        fn properties_mut(&mut self) -> &mut Object {
+            &mut self.properties
+        }
+    }
+
+    impl Consumer for Null {
+        fn profile(&self) -> &Profile {
+            &self.profile
+        }
+
+        fn consume(&mut self, _frame: &Frame) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn late_frame_is_shown_after_repeat() {
+        let mut profile = presets::get("hd_1080p_25").unwrap().profile.clone();
+        profile.frame_rate = Ratio::new(5, 1);
+        let (open, gate): (SyncSender<()>, _) = mpsc::sync_channel(1);
+        let producer = shared(Gated {
+            state: ProducerState::new(&profile, 3),
+            gate: Mutex::new(gate),
+        });
+        let consumer = Box::new(Null {
+            profile,
+            properties: Object::default(),
+        });
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let mut render = RenderLoop::new(producer, consumer);
+        render.on_frame({
+            let reports = reports.clone();
+            move |_, report| {
+                if report.repeated {
+                    let _ = open.try_send(());
+                }
+                reports.lock().unwrap().push(*report);
+            }
+        });
+        render.start().unwrap();
+        let stats = render.wait().unwrap();
+
+        let reports = reports.lock().unwrap();
+        let shown = reports
+            .iter()
+            .filter(|report| !report.repeated)
+            .map(|report| report.position)
+            .collect::<Vec<_>>();
+        assert_eq!(shown, [0, 1, 2]);
+        assert!(
+            reports
+                .iter()
+                .any(|report| report.repeated && report.position == 1)
+        );
+        assert_eq!(stats.dropped, 0);
+    }
+}
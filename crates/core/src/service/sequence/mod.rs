@@ -0,0 +1,536 @@
+//! Still images and numbered image sequences such as `shot_%05d.ppm`.
+
+pub mod netpbm;
+#[cfg(feature = "png")]
+pub mod png;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    Consumer, Producer, ProducerState, Result, Service, ServiceError,
+    generator::{DEFAULT_LENGTH, base_frame, frame_size, set_timeline_property},
+    int_property,
+};
+use crate::{
+    frame::Frame,
+    image::{Image, chroma::ChromaOptions, convert, draw, format::PixelFormat},
+    profile::Profile,
+    structures::object::{Object, ObjectValues},
+};
+
+/// Packed samples of a gray or RGB picture, shared by the codecs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Raster {
+    pub width: usize,
+    pub height: usize,
+    /// Samples per pixel: 1 gray, 2 gray and alpha, 3 RGB, 4 RGBA.
+    pub depth: usize,
+    /// Largest sample value, `255` or `65535`.
+    pub maxval: u16,
+    pub samples: Vec<u16>,
+}
+
+impl Raster {
+    /// Samples of `image`, which must be gray or RGB.
+    pub fn from_image(image: &Image) -> Result<Self> {
+        let (depth, maxval) = match image.format() {
+            PixelFormat::Gray8 => (1, 255),
+            PixelFormat::Gray16 => (1, 65535),
+            PixelFormat::Rgb24 => (3, 255),
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 => (4, 255),
+            PixelFormat::Rgba16 | PixelFormat::RgbaF32 => (4, 65535),
+            format => {
+                return Err(ServiceError::Mismatch(format!(
+                    "{format:?} images cannot be stored as stills"
+                )));
+            }
+        };
+
+        let (width, height) = (image.width(), image.height());
+        let mut samples = Vec::with_capacity(width * height * depth);
+        let unexpected = || ServiceError::Mismatch("unexpected sample type".into());
+
+        for y in 0..height {
+            match image.format() {
+                PixelFormat::Gray8 | PixelFormat::Rgb24 | PixelFormat::Rgba8 => samples.extend(
+                    image
+                        .row::<u8>(0, y)
+                        .ok_or_else(unexpected)?
+                        .iter()
+                        .map(|&s| s as u16),
+                ),
+                PixelFormat::Bgra8 => samples.extend(
+                    image
+                        .row::<u8>(0, y)
+                        .ok_or_else(unexpected)?
+                        .chunks_exact(4)
+                        .flat_map(|p| [p[2], p[1], p[0], p[3]].map(u16::from)),
+                ),
+                PixelFormat::RgbaF32 => samples.extend(
+                    image
+                        .row::<f32>(0, y)
+                        .ok_or_else(unexpected)?
+                        .iter()
+                        .map(|&s| (s.clamp(0.0, 1.0) * 65535.0).round() as u16),
+                ),
+                _ => samples.extend_from_slice(image.row::<u16>(0, y).ok_or_else(unexpected)?),
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            depth,
+            maxval,
+            samples,
+        })
+    }
+
+    /// Image of the raster, Gray8/Gray16, Rgb24, Rgba8 or Rgba16.
+    pub fn into_image(self) -> Result<Image> {
+        let raster = match self.depth {
+            2 => self.with_depth(4).expect("gray alpha expands to RGBA"),
+            3 if self.maxval > 255 => self.with_depth(4).expect("RGB expands to RGBA"),
+            _ => self,
+        };
+        let maxval = if raster.maxval > 255 { 65535 } else { 255 };
+        let raster = raster.with_maxval(maxval);
+
+        let format = match (raster.depth, raster.maxval) {
+            (1, 255) => PixelFormat::Gray8,
+            (1, _) => PixelFormat::Gray16,
+            (3, _) => PixelFormat::Rgb24,
+            (4, 255) => PixelFormat::Rgba8,
+            (4, _) => PixelFormat::Rgba16,
+            (depth, _) => {
+                return Err(ServiceError::InvalidData(format!(
+                    "{depth} samples per pixel"
+                )));
+            }
+        };
+
+        let mut image = Image::new(format, raster.width, raster.height)?;
+        let row_len = raster.width * raster.depth;
+        for (y, row) in raster.samples.chunks_exact(row_len).enumerate() {
+            if raster.maxval == 255 {
+                let target = image.row_mut::<u8>(0, y).expect("8-bit image");
+                for (to, from) in target.iter_mut().zip(row) {
+                    *to = *from as u8;
+                }
+            } else {
+                image
+                    .row_mut::<u16>(0, y)
+                    .expect("16-bit image")
+                    .copy_from_slice(row);
+            }
+        }
+        Ok(image)
+    }
+
+    /// Convert to `depth` samples per pixel, `None` when this would drop
+    /// color.
+    pub fn with_depth(self, depth: usize) -> Option<Self> {
+        if depth == self.depth {
+            return Some(self);
+        }
+
+        let opaque = self.maxval;
+        let convert = |pixel: &[u16]| -> Option<Vec<u16>> {
+            Some(match (self.depth, depth, pixel) {
+                (1, 2, &[v]) => vec![v, opaque],
+                (1, 3, &[v]) => vec![v, v, v],
+                (1, 4, &[v]) => vec![v, v, v, opaque],
+                (2, 1, &[v, _]) => vec![v],
+                (2, 3, &[v, _]) => vec![v, v, v],
+                (2, 4, &[v, a]) => vec![v, v, v, a],
+                (3, 4, &[r, g, b]) => vec![r, g, b, opaque],
+                (4, 3, &[r, g, b, _]) => vec![r, g, b],
+                _ => return None,
+            })
+        };
+
+        let mut samples = Vec::with_capacity(self.width * self.height * depth);
+        for pixel in self.samples.chunks_exact(self.depth) {
+            samples.extend(convert(pixel)?);
+        }
+
+        Some(Self {
+            depth,
+            samples,
+            ..self
+        })
+    }
+
+    /// Rescale the samples to `maxval`.
+    pub fn with_maxval(mut self, maxval: u16) -> Self {
+        if maxval != self.maxval {
+            let (from, to) = (self.maxval as u32, maxval as u32);
+            for sample in &mut self.samples {
+                *sample = ((*sample as u32 * to + from / 2) / from) as u16;
+            }
+            self.maxval = maxval;
+        }
+        self
+    }
+}
+
+/// File format of a still, chosen by extension when writing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StillFormat {
+    Pgm,
+    Ppm,
+    Pam,
+    #[cfg(feature = "png")]
+    Png,
+}
+
+impl StillFormat {
+    /// Format of the extension of `path`; `.pnm` writes PPM.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        Some(match extension.as_str() {
+            "pgm" => StillFormat::Pgm,
+            "ppm" | "pnm" => StillFormat::Ppm,
+            "pam" => StillFormat::Pam,
+            #[cfg(feature = "png")]
+            "png" => StillFormat::Png,
+            _ => return None,
+        })
+    }
+
+    pub fn encode(&self, raster: &Raster) -> Result<Vec<u8>> {
+        match self {
+            StillFormat::Pgm => netpbm::encode(raster, netpbm::NetpbmKind::Pgm),
+            StillFormat::Ppm => netpbm::encode(raster, netpbm::NetpbmKind::Ppm),
+            StillFormat::Pam => netpbm::encode(raster, netpbm::NetpbmKind::Pam),
+            #[cfg(feature = "png")]
+            StillFormat::Png => png::encode(raster),
+        }
+    }
+}
+
+/// Decode a still of any supported format, detected from its contents.
+pub fn decode(data: &[u8]) -> Result<Raster> {
+    match data {
+        [b'P', ..] => netpbm::decode(data),
+        #[cfg(feature = "png")]
+        [0x89, b'P', b'N', b'G', ..] => png::decode(data),
+        _ => Err(ServiceError::InvalidData("unknown image format".into())),
+    }
+}
+
+pub fn read_image(path: impl AsRef<Path>) -> Result<Image> {
+    decode(&fs::read(path)?)?.into_image()
+}
+
+/// Write `image` in the format of the extension of `path`.
+pub fn write_image(image: &Image, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let format = StillFormat::from_path(path).ok_or_else(|| unknown_extension(path))?;
+    fs::write(path, format.encode(&Raster::from_image(image)?)?)?;
+    Ok(())
+}
+
+fn unknown_extension(path: &Path) -> ServiceError {
+    ServiceError::InvalidData(format!("unsupported image file {}", path.display()))
+}
+
+/// File name pattern with a printf style `%d` or `%0Nd` frame number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequencePattern {
+    prefix: String,
+    /// Minimum digits, zero padded.
+    width: usize,
+    suffix: String,
+}
+
+impl SequencePattern {
+    /// Parse `pattern`, `None` without a number placeholder. `%%` is a
+    /// literal percent sign.
+    pub fn parse(pattern: &str) -> Option<Self> {
+        let mut prefix = String::new();
+        let mut chars = pattern.char_indices().peekable();
+
+        while let Some((_, c)) = chars.next() {
+            if c != '%' {
+                prefix.push(c);
+                continue;
+            }
+            if chars.next_if(|(_, c)| *c == '%').is_some() {
+                prefix.push('%');
+                continue;
+            }
+
+            let mut digits = String::new();
+            while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                digits.push(c);
+            }
+            let (end, 'd') = chars.next()? else {
+                return None;
+            };
+            if !digits.is_empty() && !digits.starts_with('0') {
+                return None;
+            }
+
+            return Some(Self {
+                prefix,
+                width: digits.parse().unwrap_or(0),
+                suffix: pattern[end + 1..].replace("%%", "%"),
+            });
+        }
+        None
+    }
+
+    pub fn path(&self, number: u64) -> PathBuf {
+        PathBuf::from(format!(
+            "{}{number:0width$}{}",
+            self.prefix,
+            self.suffix,
+            width = self.width
+        ))
+    }
+
+    /// Numbers of the files of the sequence in its directory, sorted.
+    pub fn scan(&self) -> Result<Vec<u64>> {
+        let (directory, name_prefix) = match self.prefix.rfind(std::path::is_separator) {
+            Some(index) => (&self.prefix[..=index], &self.prefix[index + 1..]),
+            None => (".", self.prefix.as_str()),
+        };
+        if self.suffix.contains(std::path::is_separator) {
+            return Err(ServiceError::InvalidData(
+                "frame number must be in the file name".into(),
+            ));
+        }
+
+        let mut numbers = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let name = entry?.file_name();
+            let Some(digits) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(name_prefix))
+                .and_then(|name| name.strip_suffix(self.suffix.as_str()))
+            else {
+                continue;
+            };
+            if let Ok(number) = digits.parse::<u64>()
+                && digits.bytes().all(|b| b.is_ascii_digit())
+                && format!("{number:0width$}", width = self.width) == digits
+            {
+                numbers.push(number);
+            }
+        }
+
+        numbers.sort_unstable();
+        Ok(numbers)
+    }
+}
+
+/// Producer of a still or an image sequence.
+///
+/// A sequence plays its files in number order, one per profile frame,
+/// skipping gaps in the numbering. A still lasts `length` frames.
+pub struct SequenceProducer {
+    state: ProducerState,
+    files: Vec<PathBuf>,
+    still: bool,
+    cache: Option<(usize, Image)>,
+}
+
+impl SequenceProducer {
+    /// Open the sequence of `pattern`, or the still at `pattern` when it
+    /// has no frame number.
+    pub fn open(profile: &Profile, pattern: &str) -> Result<Self> {
+        let Some(sequence) = SequencePattern::parse(pattern) else {
+            let image = read_image(pattern)?.into_shared();
+            return Ok(Self {
+                state: ProducerState::new(profile, DEFAULT_LENGTH),
+                files: vec![PathBuf::from(pattern)],
+                still: true,
+                cache: Some((0, image)),
+            });
+        };
+
+        let files: Vec<_> = sequence
+            .scan()?
+            .into_iter()
+            .map(|number| sequence.path(number))
+            .collect();
+        if files.is_empty() {
+            return Err(ServiceError::InvalidData(format!(
+                "no files match {pattern}"
+            )));
+        }
+
+        Ok(Self {
+            state: ProducerState::new(profile, files.len() as i64),
+            files,
+            still: false,
+            cache: None,
+        })
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn is_still(&self) -> bool {
+        self.still
+    }
+}
+
+impl Service for SequenceProducer {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        if self.still {
+            set_timeline_property(&mut self.state, key, &value)?;
+        }
+        self.state.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Producer for SequenceProducer {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, position: i64) -> Result<Frame> {
+        if !(0..self.state.length()).contains(&position) {
+            return Err(ServiceError::OutOfRange(position));
+        }
+        let index = if self.still { 0 } else { position as usize };
+
+        let image = match &self.cache {
+            Some((cached, image)) if *cached == index => image.clone(),
+            _ => {
+                let image = read_image(&self.files[index])?.into_shared();
+                self.cache = Some((index, image.clone()));
+                image
+            }
+        };
+
+        let mut frame = base_frame(&self.state.profile);
+        frame.set_image(image);
+        Ok(frame.build())
+    }
+}
+
+/// Consumer writing every frame as an image file.
+///
+/// Frame `n` is written to the file numbered `begin + n` of the pattern,
+/// or to the pattern itself when it has no number. The format follows
+/// the extension. YUV images are converted to RGB with the color of the
+/// frame, frames without image are written black.
+pub struct SequenceConsumer {
+    profile: Profile,
+    properties: Object,
+    pattern: String,
+    sequence: Option<SequencePattern>,
+    format: StillFormat,
+    begin: i64,
+    black: Option<Image>,
+}
+
+impl SequenceConsumer {
+    pub fn new(profile: &Profile, pattern: &str) -> Result<Self> {
+        let format =
+            StillFormat::from_path(pattern).ok_or_else(|| unknown_extension(Path::new(pattern)))?;
+
+        Ok(Self {
+            profile: profile.clone(),
+            properties: Object::default(),
+            pattern: pattern.to_string(),
+            sequence: SequencePattern::parse(pattern),
+            format,
+            begin: 0,
+            black: None,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl Service for SequenceConsumer {
+    fn properties(&self) -> &Object {
+        &self.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        if key == "begin" {
+            let begin = int_property(key, &value)?;
+            if begin < 0 {
+                return Err(ServiceError::invalid_property(key, "must not be negative"));
+            }
+            self.begin = begin;
+        }
+        self.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Consumer for SequenceConsumer {
+    fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    fn consume(&mut self, frame: &Frame) -> Result<()> {
+        let number = self.begin + frame.position();
+        let path = match &self.sequence {
+            Some(sequence) => {
+                sequence.path(u64::try_from(number).map_err(|_| ServiceError::OutOfRange(number))?)
+            }
+            None => PathBuf::from(&self.pattern),
+        };
+
+        let image = match &frame.image {
+            Some(image) => image,
+            None => {
+                if self.black.is_none() {
+                    let (width, height) = frame_size(&self.profile);
+                    let mut black = Image::new(PixelFormat::Rgb24, width, height)?;
+                    draw::fill(&mut black, [0.0, 0.0, 0.0, 1.0], &self.profile.color)?;
+                    self.black = Some(black);
+                }
+                self.black.as_ref().expect("black frame")
+            }
+        };
+
+        let converted;
+        let image = match image.format() {
+            format if format.is_yuv() => {
+                let rgb = match format.bit_depth() {
+                    8 => PixelFormat::Rgb24,
+                    _ => PixelFormat::Rgba16,
+                };
+                let options = ChromaOptions::for_profile(&self.profile);
+                converted =
+                    convert::convert_with(image, &frame.color, rgb, &frame.color, &options)?;
+                &converted
+            }
+            _ => image,
+        };
+
+        fs::write(path, self.format.encode(&Raster::from_image(image)?)?)?;
+        Ok(())
+    }
+}
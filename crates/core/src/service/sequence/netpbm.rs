@@ -0,0 +1,223 @@
+//! Netpbm PGM, PPM and PAM files. Plain (ASCII) PGM and PPM are read,
+//! files are written in the binary forms.
+
+use super::Raster;
+use crate::service::{Result, ServiceError};
+
+/// Largest width or height accepted when decoding.
+const MAX_DIMENSION: usize = 1 << 16;
+
+fn invalid(reason: impl Into<String>) -> ServiceError {
+    ServiceError::InvalidData(reason.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetpbmKind {
+    /// Gray, `P5`.
+    Pgm,
+    /// RGB, `P6`.
+    Ppm,
+    /// Gray or RGB with optional alpha, `P7`.
+    Pam,
+}
+
+/// Cursor over the header tokens of a file.
+struct Header<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Header<'a> {
+    fn skip_space(&mut self) {
+        while let Some(&byte) = self.data.get(self.at) {
+            match byte {
+                b'#' => {
+                    while self.data.get(self.at).is_some_and(|&byte| byte != b'\n') {
+                        self.at += 1;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() => self.at += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<&'a str> {
+        self.skip_space();
+        let start = self.at;
+        while self
+            .data
+            .get(self.at)
+            .is_some_and(|byte| !byte.is_ascii_whitespace())
+        {
+            self.at += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.at])
+            .ok()
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| invalid("truncated header"))
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        let token = self.token()?;
+        token
+            .parse()
+            .map_err(|_| invalid(format!("invalid number {token:?}")))
+    }
+}
+
+pub fn decode(data: &[u8]) -> Result<Raster> {
+    let mut header = Header { data, at: 2 };
+    let magic = data.get(..2).ok_or_else(|| invalid("empty file"))?;
+
+    let (width, height, depth, maxval, plain) = match magic {
+        b"P2" | b"P5" | b"P3" | b"P6" => {
+            let depth = if matches!(magic, b"P2" | b"P5") { 1 } else { 3 };
+            let (width, height, maxval) = (header.number()?, header.number()?, header.number()?);
+            // One whitespace byte separates the header from the samples.
+            header.at += 1;
+            (width, height, depth, maxval, matches!(magic, b"P2" | b"P3"))
+        }
+        b"P7" => {
+            let (mut width, mut height, mut depth, mut maxval) = (0, 0, 0, 0);
+            loop {
+                match header.token()? {
+                    "WIDTH" => width = header.number()?,
+                    "HEIGHT" => height = header.number()?,
+                    "DEPTH" => depth = header.number()?,
+                    "MAXVAL" => maxval = header.number()?,
+                    "TUPLTYPE" => {
+                        header.token()?;
+                    }
+                    "ENDHDR" => break,
+                    token => return Err(invalid(format!("unknown PAM header {token:?}"))),
+                }
+            }
+            while data.get(header.at).is_some_and(|&byte| byte != b'\n') {
+                header.at += 1;
+            }
+            header.at += 1;
+            (width, height, depth, maxval, false)
+        }
+        _ => return Err(invalid("unsupported Netpbm format")),
+    };
+
+    if width == 0
+        || height == 0
+        || width > MAX_DIMENSION
+        || height > MAX_DIMENSION
+        || !(1..=4).contains(&depth)
+    {
+        return Err(invalid(format!("invalid size {width}x{height}x{depth}")));
+    }
+    let maxval = u16::try_from(maxval)
+        .ok()
+        .filter(|maxval| *maxval > 0)
+        .ok_or_else(|| invalid(format!("invalid maxval {maxval}")))?;
+
+    let count = width
+        .checked_mul(height)
+        .and_then(|count| count.checked_mul(depth))
+        .ok_or_else(|| invalid(format!("invalid size {width}x{height}x{depth}")))?;
+    let samples = if plain {
+        (0..count)
+            .map(|_| {
+                header
+                    .number()
+                    .map(|sample| sample.min(maxval as usize) as u16)
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        let size = if maxval > 255 { 2 } else { 1 };
+        let bytes = count
+            .checked_mul(size)
+            .and_then(|len| header.at.checked_add(len))
+            .and_then(|end| data.get(header.at..end))
+            .ok_or_else(|| invalid("truncated samples"))?;
+        match size {
+            1 => bytes.iter().map(|&sample| sample as u16).collect(),
+            _ => bytes
+                .chunks_exact(2)
+                .map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+                .collect(),
+        }
+    };
+
+    let raster = Raster {
+        width,
+        height,
+        depth,
+        maxval,
+        samples,
+    };
+    Ok(raster.with_maxval(if maxval > 255 { 65535 } else { 255 }))
+}
+
+/// Encode `raster` as `kind`. PGM needs a gray raster; alpha is dropped
+/// from PPM.
+pub fn encode(raster: &Raster, kind: NetpbmKind) -> Result<Vec<u8>> {
+    let depth = match (kind, raster.depth) {
+        (NetpbmKind::Pgm, _) => 1,
+        (NetpbmKind::Ppm, _) => 3,
+        (NetpbmKind::Pam, depth) => depth,
+    };
+    let raster = raster.clone().with_depth(depth).ok_or_else(|| {
+        ServiceError::Mismatch(format!(
+            "cannot store {} samples per pixel as {kind:?}",
+            raster.depth
+        ))
+    })?;
+
+    let (width, height, maxval) = (raster.width, raster.height, raster.maxval);
+    let mut data = match kind {
+        NetpbmKind::Pgm => format!("P5\n{width} {height}\n{maxval}\n"),
+        NetpbmKind::Ppm => format!("P6\n{width} {height}\n{maxval}\n"),
+        NetpbmKind::Pam => {
+            let tuple_type = match depth {
+                1 => "GRAYSCALE",
+                2 => "GRAYSCALE_ALPHA",
+                3 => "RGB",
+                _ => "RGB_ALPHA",
+            };
+            format!(
+                "P7\nWIDTH {width}\nHEIGHT {height}\nDEPTH {depth}\nMAXVAL {maxval}\nTUPLTYPE {tuple_type}\nENDHDR\n"
+            )
+        }
+    }
+    .into_bytes();
+
+    if maxval > 255 {
+        data.extend(
+            raster
+                .samples
+                .iter()
+                .flat_map(|sample| sample.to_be_bytes()),
+        );
+    } else {
+        data.extend(raster.samples.iter().map(|&sample| sample as u8));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_oversized_headers() {
+        let huge = format!("P5\n{} {}\n255\n", usize::MAX, usize::MAX);
+        assert!(decode(huge.as_bytes()).is_err());
+        assert!(decode(b"P6\n65537 1\n255\n").is_err());
+        assert!(decode(b"P7\nWIDTH 65536\nHEIGHT 65536\nDEPTH 4\nMAXVAL 65535\nENDHDR\n").is_err());
+    }
+
+    #[test]
+    fn round_trips_binary_and_reads_plain() {
+        let raster = decode(b"P2\n2 1\n15\n0 15\n").unwrap();
+        assert_eq!((raster.width, raster.height, raster.depth), (2, 1, 1));
+        assert_eq!(raster.samples, [0, 255]);
+
+        let encoded = encode(&raster, NetpbmKind::Pam).unwrap();
+        assert_eq!(decode(&encoded).unwrap(), raster);
+    }
+}
@@ -0,0 +1,89 @@
+//! PNG files through the `png` crate.
+
+use std::io::Cursor;
+
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+use super::Raster;
+use crate::service::{Result, ServiceError};
+
+fn invalid(error: impl std::fmt::Display) -> ServiceError {
+    ServiceError::InvalidData(format!("PNG: {error}"))
+}
+
+/// Decode `data`, expanding palettes and low bit depths.
+pub fn decode(data: &[u8]) -> Result<Raster> {
+    let mut decoder = Decoder::new(Cursor::new(data));
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+
+    let mut bytes = vec![
+        0;
+        reader
+            .output_buffer_size()
+            .ok_or_else(|| invalid("too large"))?
+    ];
+    let info = reader.next_frame(&mut bytes).map_err(invalid)?;
+    bytes.truncate(info.buffer_size());
+
+    let depth = match info.color_type {
+        ColorType::Grayscale => 1,
+        ColorType::GrayscaleAlpha => 2,
+        ColorType::Rgb => 3,
+        ColorType::Rgba => 4,
+        ColorType::Indexed => return Err(invalid("unexpanded palette")),
+    };
+    let (maxval, samples) = match info.bit_depth {
+        BitDepth::Sixteen => (
+            65535,
+            bytes
+                .chunks_exact(2)
+                .map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+                .collect(),
+        ),
+        _ => (255, bytes.iter().map(|&sample| sample as u16).collect()),
+    };
+
+    Ok(Raster {
+        width: info.width as usize,
+        height: info.height as usize,
+        depth,
+        maxval,
+        samples,
+    })
+}
+
+pub fn encode(raster: &Raster) -> Result<Vec<u8>> {
+    let color = match raster.depth {
+        1 => ColorType::Grayscale,
+        2 => ColorType::GrayscaleAlpha,
+        3 => ColorType::Rgb,
+        _ => ColorType::Rgba,
+    };
+    let sixteen = raster.maxval > 255;
+
+    let mut data = Vec::new();
+    let mut encoder = Encoder::new(&mut data, raster.width as u32, raster.height as u32);
+    encoder.set_color(color);
+    encoder.set_depth(if sixteen {
+        BitDepth::Sixteen
+    } else {
+        BitDepth::Eight
+    });
+
+    let bytes: Vec<u8> = if sixteen {
+        let raster = raster.clone().with_maxval(65535);
+        raster
+            .samples
+            .iter()
+            .flat_map(|sample| sample.to_be_bytes())
+            .collect()
+    } else {
+        raster.samples.iter().map(|&sample| sample as u8).collect()
+    };
+
+    let mut writer = encoder.write_header().map_err(invalid)?;
+    writer.write_image_data(&bytes).map_err(invalid)?;
+    writer.finish().map_err(invalid)?;
+    Ok(data)
+}
@@ -0,0 +1,281 @@
+//! Multitrack container pulling the same position from every track.
+
+use std::ops::Range;
+
+use super::{
+    Producer, ProducerState, Result, Service, ServiceError, SharedProducer, Transition, lock,
+};
+use crate::{
+    audio::AudioBuffer,
+    frame::{Frame, FrameBuilder},
+    image::Image,
+    profile::Profile,
+    structures::object::Object,
+};
+
+/// Producer of one track, higher tracks are on top.
+pub struct Track {
+    producer: SharedProducer,
+    /// Video of the track is ignored.
+    pub hidden: bool,
+    /// Audio of the track is ignored.
+    pub muted: bool,
+}
+
+impl Track {
+    pub fn producer(&self) -> &SharedProducer {
+        &self.producer
+    }
+}
+
+/// Transition between two tracks, placed on the tractor timeline.
+pub struct TransitionEntry {
+    transition: Box<dyn Transition>,
+    /// Track blended into.
+    pub a_track: usize,
+    /// Track blended from.
+    pub b_track: usize,
+    pub in_point: i64,
+    pub out_point: i64,
+}
+
+impl TransitionEntry {
+    pub fn transition(&self) -> &dyn Transition {
+        self.transition.as_ref()
+    }
+
+    pub fn transition_mut(&mut self) -> &mut dyn Transition {
+        self.transition.as_mut()
+    }
+
+    fn contains(&self, position: i64) -> bool {
+        (self.in_point..=self.out_point).contains(&position)
+    }
+
+    /// Progress over the duration of frame `position`.
+    fn progress(&self, position: i64) -> Range<f64> {
+        let length = (self.out_point - self.in_point + 1) as f64;
+        let offset = (position - self.in_point) as f64;
+        offset / length..(offset + 1.0) / length
+    }
+}
+
+/// Producer combining tracks with transitions.
+///
+/// Video comes from the highest visible track with an image, audio from
+/// the highest unmuted track with samples. Active transitions blend their
+/// `b_track` into their `a_track` first, the `b_track` is then consumed.
+pub struct Tractor {
+    state: ProducerState,
+    tracks: Vec<Track>,
+    transitions: Vec<TransitionEntry>,
+}
+
+impl Tractor {
+    pub fn new(profile: &Profile) -> Self {
+        Self {
+            state: ProducerState::new(profile, 0),
+            tracks: Vec::new(),
+            transitions: Vec::new(),
+        }
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn track_mut(&mut self, index: usize) -> Option<&mut Track> {
+        self.tracks.get_mut(index)
+    }
+
+    /// Add a track on top, returns its index.
+    pub fn add_track(&mut self, producer: SharedProducer) -> usize {
+        self.tracks.push(Track {
+            producer,
+            hidden: false,
+            muted: false,
+        });
+        self.update_length();
+        self.tracks.len() - 1
+    }
+
+    /// Remove a track with the transitions using it.
+    pub fn remove_track(&mut self, index: usize) -> Result<Track> {
+        if index >= self.tracks.len() {
+            return Err(ServiceError::InvalidEntry(index));
+        }
+
+        self.transitions
+            .retain(|entry| entry.a_track != index && entry.b_track != index);
+        for entry in &mut self.transitions {
+            entry.a_track -= (entry.a_track > index) as usize;
+            entry.b_track -= (entry.b_track > index) as usize;
+        }
+
+        let track = self.tracks.remove(index);
+        self.update_length();
+        Ok(track)
+    }
+
+    pub fn set_hidden(&mut self, index: usize, hidden: bool) -> Result<()> {
+        let track = self
+            .tracks
+            .get_mut(index)
+            .ok_or(ServiceError::InvalidEntry(index))?;
+        track.hidden = hidden;
+        Ok(())
+    }
+
+    pub fn set_muted(&mut self, index: usize, muted: bool) -> Result<()> {
+        let track = self
+            .tracks
+            .get_mut(index)
+            .ok_or(ServiceError::InvalidEntry(index))?;
+        track.muted = muted;
+        Ok(())
+    }
+
+    pub fn transitions(&self) -> &[TransitionEntry] {
+        &self.transitions
+    }
+
+    /// Blend `b_track` into `a_track` from `in_point` to `out_point`
+    /// inclusive, returns the transition index.
+    pub fn add_transition(
+        &mut self,
+        transition: Box<dyn Transition>,
+        a_track: usize,
+        b_track: usize,
+        in_point: i64,
+        out_point: i64,
+    ) -> Result<usize> {
+        for track in [a_track, b_track] {
+            if track >= self.tracks.len() {
+                return Err(ServiceError::InvalidEntry(track));
+            }
+        }
+        if a_track == b_track || in_point < 0 || in_point > out_point {
+            return Err(ServiceError::InvalidRange {
+                in_point,
+                out_point,
+            });
+        }
+
+        self.transitions.push(TransitionEntry {
+            transition,
+            a_track,
+            b_track,
+            in_point,
+            out_point,
+        });
+        Ok(self.transitions.len() - 1)
+    }
+
+    pub fn remove_transition(&mut self, index: usize) -> Result<TransitionEntry> {
+        if index >= self.transitions.len() {
+            return Err(ServiceError::InvalidEntry(index));
+        }
+        Ok(self.transitions.remove(index))
+    }
+
+    /// Recompute the length from the tracks, needed after editing a track
+    /// producer.
+    pub fn update_length(&mut self) {
+        let length = self
+            .tracks
+            .iter()
+            .map(|track| lock(&track.producer).playtime())
+            .max()
+            .unwrap_or(0);
+        self.state.set_length(length);
+    }
+
+    fn track_frame(&self, track: &Track, position: i64) -> Result<Option<Frame>> {
+        let mut producer = lock(&track.producer);
+        if position >= producer.playtime() {
+            return Ok(None);
+        }
+
+        // Shared buffers keep the copies for transitions cheap.
+        let mut frame = producer.get_frame(position)?;
+        frame.image = match track.hidden {
+            true => None,
+            false => frame.image.map(Image::into_shared),
+        };
+        frame.audio = match track.muted {
+            true => None,
+            false => frame.audio.map(AudioBuffer::into_shared),
+        };
+        Ok(Some(frame))
+    }
+}
+
+impl Service for Tractor {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+}
+
+impl Producer for Tractor {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, position: i64) -> Result<Frame> {
+        let mut frames = self
+            .tracks
+            .iter()
+            .map(|track| self.track_frame(track, position))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut consumed = vec![false; frames.len()];
+        for entry in &mut self.transitions {
+            if !entry.contains(position) {
+                continue;
+            }
+
+            let progress = entry.progress(position);
+            let b = frames[entry.b_track].clone();
+            match (&mut frames[entry.a_track], b) {
+                (Some(a), Some(b)) => entry.transition.process(a, &b, progress)?,
+                (a @ None, b) => *a = b,
+                (Some(_), None) => {}
+            }
+            consumed[entry.b_track] = true;
+        }
+
+        for (frame, consumed) in frames.iter_mut().zip(consumed) {
+            if consumed {
+                *frame = None;
+            }
+        }
+
+        let mut output = FrameBuilder::new();
+        output.set_profile(&self.state.profile);
+        output.set_color(self.state.profile.color);
+        let mut output = output.build();
+
+        if let Some(frame) = frames.iter().rev().flatten().find(|f| f.image.is_some()) {
+            output.viewport = frame.viewport.clone();
+            output.aspect_ratio = frame.aspect_ratio;
+            output.color = frame.color;
+            output.image = frame.image.clone();
+            output.properties = frame.properties.clone();
+        }
+        output.audio = frames
+            .iter()
+            .rev()
+            .flatten()
+            .find_map(|frame| frame.audio.clone());
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,274 @@
+//! Built-in transitions.
+
+use std::ops::Range;
+
+use super::{Result, Service, ServiceError, Transition};
+use crate::{
+    audio::{AudioBuffer, SampleFormat, SampleLayout, convert::Dither},
+    frame::Frame,
+    image::{Image, convert, format::SampleType},
+    structures::{
+        buffer::BufferElement,
+        object::{Object, ObjectValues},
+    },
+};
+
+/// Sample type that can be mixed linearly.
+trait Mix: BufferElement {
+    fn mix(a: Self, b: Self, t: f32) -> Self;
+}
+
+impl Mix for u8 {
+    fn mix(a: Self, b: Self, t: f32) -> Self {
+        (a as f32 + (b as f32 - a as f32) * t).round() as u8
+    }
+}
+
+impl Mix for u16 {
+    fn mix(a: Self, b: Self, t: f32) -> Self {
+        (a as f32 + (b as f32 - a as f32) * t).round() as u16
+    }
+}
+
+impl Mix for f32 {
+    fn mix(a: Self, b: Self, t: f32) -> Self {
+        a + (b - a) * t
+    }
+}
+
+/// Mix `b` into `a` row by row, leaving the stride padding of both alone.
+fn mix_planes<T: Mix>(a: &mut Image, b: &Image, t: f32) {
+    for index in 0..a.planes().len() {
+        for y in 0..a.planes()[index].height {
+            let (Some(dst), Some(src)) = (a.row_mut::<T>(index, y), b.row::<T>(index, y)) else {
+                continue;
+            };
+            for (dst, &src) in dst.iter_mut().zip(src) {
+                *dst = T::mix(*dst, src, t);
+            }
+        }
+    }
+}
+
+/// Cross dissolve mixing every sample of the two images. The second
+/// image is converted to the format and color of the first.
+#[derive(Debug, Default, Clone)]
+pub struct Dissolve {
+    properties: Object,
+}
+
+impl Dissolve {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Service for Dissolve {
+    fn properties(&self) -> &Object {
+        &self.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.properties
+    }
+}
+
+impl Transition for Dissolve {
+    fn process(&mut self, a: &mut Frame, b: &Frame, progress: Range<f64>) -> Result<()> {
+        let t = ((progress.start + progress.end) / 2.0).clamp(0.0, 1.0) as f32;
+
+        let (image, other) = match (&mut a.image, &b.image) {
+            (_, None) => return Ok(()),
+            (None, Some(other)) => {
+                a.image = Some(other.clone());
+                return Ok(());
+            }
+            (Some(image), Some(other)) => (image, other),
+        };
+
+        if image.width() != other.width() || image.height() != other.height() {
+            return Err(ServiceError::Mismatch(format!(
+                "{:?} {}x{} and {:?} {}x{}",
+                image.format(),
+                image.width(),
+                image.height(),
+                other.format(),
+                other.width(),
+                other.height()
+            )));
+        }
+
+        let converted;
+        let other = if image.format() != other.format() || a.color != b.color {
+            converted = convert::convert(other, &b.color, image.format(), &a.color)?;
+            &converted
+        } else {
+            other
+        };
+
+        match image.format().sample_type() {
+            SampleType::U8 => mix_planes::<u8>(image, other, t),
+            SampleType::U16 => mix_planes::<u16>(image, other, t),
+            SampleType::F32 => mix_planes::<f32>(image, other, t),
+        }
+        Ok(())
+    }
+}
+
+/// Gain curve of an [`AudioCrossfade`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FadeCurve {
+    #[default]
+    Linear,
+    /// Constant power, for uncorrelated material.
+    EqualPower,
+}
+
+impl FadeCurve {
+    /// Gains of `a` and `b` at `progress`.
+    fn gains(&self, progress: f64) -> (f64, f64) {
+        match self {
+            FadeCurve::Linear => (1.0 - progress, progress),
+            FadeCurve::EqualPower => {
+                let angle = progress * std::f64::consts::FRAC_PI_2;
+                (angle.cos(), angle.sin())
+            }
+        }
+    }
+}
+
+/// Crossfade of the audio, ramping the gains sample by sample.
+///
+/// The `curve` property selects `linear` or `equal_power`.
+#[derive(Debug, Default, Clone)]
+pub struct AudioCrossfade {
+    properties: Object,
+    curve: FadeCurve,
+}
+
+impl AudioCrossfade {
+    pub fn new(curve: FadeCurve) -> Self {
+        Self {
+            properties: Object::default(),
+            curve,
+        }
+    }
+
+    pub fn curve(&self) -> FadeCurve {
+        self.curve
+    }
+}
+
+impl Service for AudioCrossfade {
+    fn properties(&self) -> &Object {
+        &self.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        if key == "curve" {
+            self.curve = match &value {
+                ObjectValues::String(curve) if curve == "linear" => FadeCurve::Linear,
+                ObjectValues::String(curve) if curve == "equal_power" => FadeCurve::EqualPower,
+                _ => {
+                    return Err(ServiceError::invalid_property(
+                        key,
+                        "expected `linear` or `equal_power`",
+                    ));
+                }
+            };
+        }
+
+        self.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Transition for AudioCrossfade {
+    fn process(&mut self, a: &mut Frame, b: &Frame, progress: Range<f64>) -> Result<()> {
+        let (audio, other) = match (&mut a.audio, &b.audio) {
+            (_, None) => return Ok(()),
+            (None, Some(other)) => {
+                a.audio = Some(other.clone());
+                return Ok(());
+            }
+            (Some(audio), Some(other)) => (audio, other),
+        };
+
+        if audio.channel_count() != other.channel_count()
+            || audio.sample_rate() != other.sample_rate()
+        {
+            return Err(ServiceError::Mismatch(format!(
+                "{} channels at {} Hz and {} channels at {} Hz",
+                audio.channel_count(),
+                audio.sample_rate(),
+                other.channel_count(),
+                other.sample_rate()
+            )));
+        }
+
+        *audio = crossfade(audio, other, self.curve, progress)?;
+        Ok(())
+    }
+}
+
+fn crossfade(
+    a: &AudioBuffer,
+    b: &AudioBuffer,
+    curve: FadeCurve,
+    progress: Range<f64>,
+) -> Result<AudioBuffer> {
+    let mut mixed = a.to_format(SampleFormat::F32, Dither::None)?;
+    let b = b
+        .to_layout(a.layout())
+        .to_format(SampleFormat::F32, Dither::None)?;
+
+    let (samples, channels, layout) = (mixed.samples(), mixed.channel_count(), mixed.layout());
+    let step = (progress.end - progress.start) / samples.max(1) as f64;
+    let Some(data) = mixed.data_mut::<f32>() else {
+        return Ok(a.clone());
+    };
+
+    for index in 0..samples.min(b.samples()) {
+        let (gain_a, gain_b) = curve.gains(progress.start + step * index as f64);
+
+        for channel in 0..channels {
+            let position = match layout {
+                SampleLayout::Interleaved => index * channels + channel,
+                SampleLayout::Planar => channel * samples + index,
+            };
+            let other = b.sample::<f32>(channel, index).unwrap_or_default() as f64;
+            data[position] = (data[position] as f64 * gain_a + other * gain_b) as f32;
+        }
+    }
+
+    Ok(mixed.to_format(a.format(), Dither::None)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{image::format::PixelFormat, structures::buffer::Buffer};
+
+    #[test]
+    fn mix_skips_stride_padding() {
+        let buffer = Buffer::OwnedU8(vec![0, 0, 0, 7, 7, 7, 7, 7, 0, 0, 0, 7, 7, 7, 7, 7]);
+        let mut a = Image::from_buffer(PixelFormat::Gray8, 3, 2, &[8], buffer).unwrap();
+        let mut b = Image::new(PixelFormat::Gray8, 3, 2).unwrap();
+        for y in 0..2 {
+            b.row_mut::<u8>(0, y)
+                .unwrap()
+                .copy_from_slice(&[100, 150, 200]);
+        }
+
+        mix_planes::<u8>(&mut a, &b, 0.5);
+
+        assert_eq!(
+            a.buffer().as_slice::<u8>().unwrap(),
+            [50, 75, 100, 7, 7, 7, 7, 7, 50, 75, 100, 7, 7, 7, 7, 7]
+        );
+    }
+}
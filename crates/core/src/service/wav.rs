@@ -0,0 +1,800 @@
+//! WAVE audio files, including `WAVE_FORMAT_EXTENSIBLE` and RF64.
+//!
+//! Writers reserve a `JUNK` chunk that becomes the `ds64` chunk when the
+//! file grows beyond 4 GiB, as in EBU Tech 3306.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Consumer, Producer, ProducerState, Result, Service, ServiceError, enum_property,
+    generator::base_frame, int_property, y4m::ReadSeek,
+};
+use crate::{
+    audio::{
+        AudioBuffer, ChannelLayout, SampleFormat, SampleLayout, convert::Dither, sample_offset,
+        samples_per_frame,
+    },
+    frame::Frame,
+    profile::{Profile, Ratio},
+    structures::{
+        buffer::Buffer,
+        object::{Object, ObjectValues},
+    },
+};
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+/// Tail of the `KSDATAFORMAT_SUBTYPE_*` GUIDs after the format tag.
+const SUBFORMAT_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+/// Size of the `ds64` chunk without table, reserved as `JUNK`.
+const DS64_SIZE: u32 = 28;
+
+fn invalid(reason: impl Into<String>) -> ServiceError {
+    ServiceError::InvalidData(reason.into())
+}
+
+/// Sample encoding in the file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WavFormat {
+    /// Unsigned 8-bit.
+    Pcm8,
+    #[default]
+    Pcm16,
+    /// Read into and written from [`SampleFormat::S32`].
+    Pcm24,
+    Pcm32,
+    Float32,
+    /// Read only, converted to [`SampleFormat::F32`].
+    Float64,
+}
+
+impl WavFormat {
+    pub fn bits(&self) -> u16 {
+        match self {
+            WavFormat::Pcm8 => 8,
+            WavFormat::Pcm16 => 16,
+            WavFormat::Pcm24 => 24,
+            WavFormat::Pcm32 | WavFormat::Float32 => 32,
+            WavFormat::Float64 => 64,
+        }
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bits() as usize / 8
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, WavFormat::Float32 | WavFormat::Float64)
+    }
+
+    /// Format of the buffers read from the file.
+    pub fn sample_format(&self) -> SampleFormat {
+        match self {
+            WavFormat::Pcm8 => SampleFormat::U8,
+            WavFormat::Pcm16 => SampleFormat::S16,
+            WavFormat::Pcm24 | WavFormat::Pcm32 => SampleFormat::S32,
+            WavFormat::Float32 | WavFormat::Float64 => SampleFormat::F32,
+        }
+    }
+
+    /// Lossless file format of buffers in `format`.
+    pub fn from_sample_format(format: SampleFormat) -> Self {
+        match format {
+            SampleFormat::U8 => WavFormat::Pcm8,
+            SampleFormat::S16 => WavFormat::Pcm16,
+            SampleFormat::S32 => WavFormat::Pcm32,
+            SampleFormat::F32 => WavFormat::Float32,
+        }
+    }
+
+    fn from_tag(tag: u16, bits: u16) -> Option<Self> {
+        Some(match (tag, bits) {
+            (FORMAT_PCM, 8) => WavFormat::Pcm8,
+            (FORMAT_PCM, 16) => WavFormat::Pcm16,
+            (FORMAT_PCM, 24) => WavFormat::Pcm24,
+            (FORMAT_PCM, 32) => WavFormat::Pcm32,
+            (FORMAT_FLOAT, 32) => WavFormat::Float32,
+            (FORMAT_FLOAT, 64) => WavFormat::Float64,
+            _ => return None,
+        })
+    }
+}
+
+/// `dwChannelMask` of `layout`, `0` for discrete channels.
+pub fn channel_mask(layout: ChannelLayout) -> u32 {
+    match layout {
+        ChannelLayout::Mono => 0x4,
+        ChannelLayout::Stereo => 0x3,
+        ChannelLayout::Surround51 => 0x3f,
+        ChannelLayout::Surround71 => 0x63f,
+        ChannelLayout::Discrete(_) => 0,
+    }
+}
+
+/// Layout of `channels` with `mask`, discrete for unknown masks.
+pub fn layout_of_mask(mask: u32, channels: u16) -> ChannelLayout {
+    match (mask, channels) {
+        (0x4, 1) => ChannelLayout::Mono,
+        (0x3, 2) => ChannelLayout::Stereo,
+        (0x3f | 0x60f, 6) => ChannelLayout::Surround51,
+        (0x63f, 8) => ChannelLayout::Surround71,
+        _ => ChannelLayout::Discrete(channels),
+    }
+}
+
+/// Format of the samples of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavSpec {
+    pub format: WavFormat,
+    pub sample_rate: u32,
+    pub channel_layout: ChannelLayout,
+}
+
+impl WavSpec {
+    pub fn channel_count(&self) -> usize {
+        self.channel_layout.channel_count()
+    }
+
+    /// Bytes of one sample of every channel.
+    pub fn block_align(&self) -> usize {
+        self.format.bytes() * self.channel_count()
+    }
+
+    /// Extensible format is needed above two channels or 16 bits.
+    fn is_extensible(&self) -> bool {
+        self.channel_count() > 2 || matches!(self.format, WavFormat::Pcm24 | WavFormat::Pcm32)
+    }
+
+    fn fmt_chunk(&self) -> Vec<u8> {
+        let tag = if self.format.is_float() {
+            FORMAT_FLOAT
+        } else {
+            FORMAT_PCM
+        };
+        let block_align = self.block_align() as u16;
+
+        let mut chunk = Vec::with_capacity(40);
+        chunk.extend_from_slice(
+            &(if self.is_extensible() {
+                FORMAT_EXTENSIBLE
+            } else {
+                tag
+            })
+            .to_le_bytes(),
+        );
+        chunk.extend_from_slice(&(self.channel_count() as u16).to_le_bytes());
+        chunk.extend_from_slice(&self.sample_rate.to_le_bytes());
+        chunk.extend_from_slice(&(self.sample_rate * block_align as u32).to_le_bytes());
+        chunk.extend_from_slice(&block_align.to_le_bytes());
+        chunk.extend_from_slice(&self.format.bits().to_le_bytes());
+
+        if self.is_extensible() {
+            chunk.extend_from_slice(&22u16.to_le_bytes());
+            chunk.extend_from_slice(&self.format.bits().to_le_bytes());
+            chunk.extend_from_slice(&channel_mask(self.channel_layout).to_le_bytes());
+            chunk.extend_from_slice(&tag.to_le_bytes());
+            chunk.extend_from_slice(&SUBFORMAT_TAIL);
+        }
+        chunk
+    }
+
+    fn parse_fmt(chunk: &[u8]) -> Result<Self> {
+        if chunk.len() < 16 {
+            return Err(invalid("fmt chunk too short"));
+        }
+        let u16_at = |at: usize| u16::from_le_bytes([chunk[at], chunk[at + 1]]);
+        let u32_at = |at: usize| {
+            u32::from_le_bytes([chunk[at], chunk[at + 1], chunk[at + 2], chunk[at + 3]])
+        };
+
+        let (mut tag, channels, sample_rate, bits) = (u16_at(0), u16_at(2), u32_at(4), u16_at(14));
+        let mut channel_layout = ChannelLayout::from_count(channels as usize);
+
+        if tag == FORMAT_EXTENSIBLE {
+            if chunk.len() < 40 || chunk[26..40] != SUBFORMAT_TAIL {
+                return Err(invalid("unsupported extensible subformat"));
+            }
+            if u16_at(18) != bits {
+                return Err(invalid("valid bits differ from container size"));
+            }
+            channel_layout = layout_of_mask(u32_at(20), channels);
+            tag = u16_at(24);
+        }
+
+        let format = WavFormat::from_tag(tag, bits)
+            .ok_or_else(|| invalid(format!("unsupported format {tag} with {bits} bits")))?;
+        if channels == 0 || sample_rate == 0 {
+            return Err(invalid("no channels or zero sample rate"));
+        }
+
+        Ok(Self {
+            format,
+            sample_rate,
+            channel_layout,
+        })
+    }
+}
+
+/// Decode interleaved samples of `format` into a buffer of its sample
+/// format.
+fn decode(format: WavFormat, bytes: &[u8]) -> Buffer {
+    let samples = bytes.chunks_exact(format.bytes());
+    match format {
+        WavFormat::Pcm8 => Buffer::from(bytes.to_vec()),
+        WavFormat::Pcm16 => Buffer::from(
+            samples
+                .map(|s| i16::from_le_bytes([s[0], s[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        WavFormat::Pcm24 => Buffer::from(
+            samples
+                .map(|s| i32::from_le_bytes([0, s[0], s[1], s[2]]))
+                .collect::<Vec<_>>(),
+        ),
+        WavFormat::Pcm32 => Buffer::from(
+            samples
+                .map(|s| i32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+                .collect::<Vec<_>>(),
+        ),
+        WavFormat::Float32 => Buffer::from(
+            samples
+                .map(|s| f32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+                .collect::<Vec<_>>(),
+        ),
+        WavFormat::Float64 => Buffer::from(
+            samples
+                .map(|s| f64::from_le_bytes(s.try_into().expect("8 bytes")) as f32)
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+/// Encode interleaved `audio`, already in the sample format of `format`.
+fn encode(format: WavFormat, audio: &AudioBuffer) -> Result<Vec<u8>> {
+    let unsupported = || invalid(format!("cannot write {format:?}"));
+    Ok(match format {
+        WavFormat::Pcm8 => audio.data::<u8>().ok_or_else(unsupported)?.to_vec(),
+        WavFormat::Pcm16 => audio
+            .data::<i16>()
+            .ok_or_else(unsupported)?
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect(),
+        WavFormat::Pcm24 => audio
+            .data::<i32>()
+            .ok_or_else(unsupported)?
+            .iter()
+            .flat_map(|s| {
+                let [_, low, middle, high] = s.to_le_bytes();
+                [low, middle, high]
+            })
+            .collect(),
+        WavFormat::Pcm32 => audio
+            .data::<i32>()
+            .ok_or_else(unsupported)?
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect(),
+        WavFormat::Float32 => audio
+            .data::<f32>()
+            .ok_or_else(unsupported)?
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect(),
+        WavFormat::Float64 => return Err(unsupported()),
+    })
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Random access reader of WAVE and RF64 files.
+pub struct WavReader<R: Read + Seek> {
+    reader: R,
+    spec: WavSpec,
+    data_offset: u64,
+    /// Samples per channel.
+    len: u64,
+    position: u64,
+}
+
+impl<R: Read + Seek> WavReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut id = [0u8; 4];
+        reader.read_exact(&mut id)?;
+        let rf64 = match &id {
+            b"RIFF" => false,
+            b"RF64" | b"BW64" => true,
+            _ => return Err(invalid("not a RIFF file")),
+        };
+        read_u32(&mut reader)?;
+        reader.read_exact(&mut id)?;
+        if &id != b"WAVE" {
+            return Err(invalid("not a WAVE file"));
+        }
+
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(12))?;
+
+        let mut data_size64 = None;
+        let mut spec = None;
+        loop {
+            if reader.read(&mut id)? < 4 {
+                return Err(invalid("missing data chunk"));
+            }
+            let size = read_u32(&mut reader)?;
+            let start = reader.stream_position()?;
+
+            match &id {
+                b"ds64" => {
+                    read_u64(&mut reader)?;
+                    data_size64 = Some(read_u64(&mut reader)?);
+                }
+                b"fmt " => {
+                    let mut chunk = vec![0u8; size.min(1024) as usize];
+                    reader.read_exact(&mut chunk)?;
+                    spec = Some(WavSpec::parse_fmt(&chunk)?);
+                }
+                b"data" => {
+                    let spec = spec.ok_or_else(|| invalid("data chunk before fmt chunk"))?;
+                    let size = match (rf64, size, data_size64) {
+                        (true, u32::MAX, Some(size)) => size,
+                        _ => size as u64,
+                    };
+                    // Streamed files may carry a placeholder size.
+                    let size = size.min(end - start);
+
+                    return Ok(Self {
+                        reader,
+                        spec,
+                        data_offset: start,
+                        len: size / spec.block_align() as u64,
+                        position: 0,
+                    });
+                }
+                _ => {}
+            }
+
+            reader.seek(SeekFrom::Start(start + size as u64 + (size as u64 & 1)))?;
+        }
+    }
+
+    pub fn spec(&self) -> WavSpec {
+        self.spec
+    }
+
+    /// Samples per channel.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Move to sample `position` of every channel.
+    pub fn seek(&mut self, position: u64) -> Result<()> {
+        if position > self.len {
+            return Err(ServiceError::OutOfRange(position as i64));
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    /// Read up to `count` samples per channel, interleaved in the sample
+    /// format of the file.
+    pub fn read(&mut self, count: usize) -> Result<AudioBuffer> {
+        let count = (count as u64).min(self.len - self.position) as usize;
+        let block_align = self.spec.block_align();
+
+        self.reader.seek(SeekFrom::Start(
+            self.data_offset + self.position * block_align as u64,
+        ))?;
+        let mut bytes = vec![0u8; count * block_align];
+        self.reader.read_exact(&mut bytes)?;
+        self.position += count as u64;
+
+        Ok(AudioBuffer::from_buffer(
+            SampleLayout::Interleaved,
+            self.spec.sample_rate,
+            self.spec.channel_layout,
+            count,
+            decode(self.spec.format, &bytes),
+        )?)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Writer of WAVE files, switching to RF64 beyond 4 GiB.
+///
+/// Sizes are written by [`WavWriter::finish`], or on drop ignoring errors.
+pub struct WavWriter<W: Write + Seek> {
+    writer: Option<W>,
+    spec: WavSpec,
+    riff_start: u64,
+    /// Offset of the size field of the data chunk.
+    data_size_offset: u64,
+    data_bytes: u64,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W, spec: WavSpec) -> Result<Self> {
+        if spec.format == WavFormat::Float64 {
+            return Err(invalid("cannot write Float64"));
+        }
+
+        let fmt = spec.fmt_chunk();
+        let mut header = Vec::with_capacity(80);
+        header.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+        header.extend_from_slice(b"JUNK");
+        header.extend_from_slice(&DS64_SIZE.to_le_bytes());
+        header.extend_from_slice(&[0; DS64_SIZE as usize]);
+        header.extend_from_slice(b"fmt ");
+        header.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        header.extend_from_slice(&fmt);
+        header.extend_from_slice(b"data\0\0\0\0");
+
+        let start = writer.stream_position()?;
+        writer.write_all(&header)?;
+
+        Ok(Self {
+            writer: Some(writer),
+            spec,
+            riff_start: start,
+            data_size_offset: start + header.len() as u64 - 4,
+            data_bytes: 0,
+        })
+    }
+
+    pub fn spec(&self) -> WavSpec {
+        self.spec
+    }
+
+    /// Samples per channel written so far.
+    pub fn len(&self) -> u64 {
+        self.data_bytes / self.spec.block_align() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data_bytes == 0
+    }
+
+    /// Append `audio`, converted to the file format and interleaved.
+    pub fn write(&mut self, audio: &AudioBuffer) -> Result<()> {
+        if audio.channel_count() != self.spec.channel_count() {
+            return Err(ServiceError::Mismatch(format!(
+                "{} channels in a {} channel file",
+                audio.channel_count(),
+                self.spec.channel_count()
+            )));
+        }
+        if audio.sample_rate() != self.spec.sample_rate {
+            return Err(ServiceError::Mismatch(format!(
+                "{} Hz in a {} Hz file",
+                audio.sample_rate(),
+                self.spec.sample_rate
+            )));
+        }
+
+        let format = self.spec.format;
+        let audio = audio
+            .to_layout(SampleLayout::Interleaved)
+            .to_format(format.sample_format(), Dither::None)?;
+        let bytes = encode(format, &audio)?;
+
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| invalid("writer finished"))?;
+        writer.write_all(&bytes)?;
+        self.data_bytes += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Write the chunk sizes and return the inner writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.write_sizes()?;
+        Ok(self.writer.take().expect("writer present"))
+    }
+
+    fn write_sizes(&mut self) -> Result<()> {
+        let (riff_start, samples) = (self.riff_start, self.len());
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+
+        let pad = self.data_bytes & 1;
+        if pad == 1 {
+            writer.write_all(&[0])?;
+        }
+        let end = writer.stream_position()?;
+
+        let riff_size = end - riff_start - 8;
+
+        if riff_size > u32::MAX as u64 || self.data_bytes > u32::MAX as u64 {
+            writer.seek(SeekFrom::Start(riff_start))?;
+            writer.write_all(b"RF64")?;
+            writer.write_all(&u32::MAX.to_le_bytes())?;
+            writer.seek(SeekFrom::Start(riff_start + 12))?;
+            writer.write_all(b"ds64")?;
+            writer.write_all(&DS64_SIZE.to_le_bytes())?;
+            writer.write_all(&riff_size.to_le_bytes())?;
+            writer.write_all(&self.data_bytes.to_le_bytes())?;
+            writer.write_all(&samples.to_le_bytes())?;
+            writer.write_all(&0u32.to_le_bytes())?;
+            writer.seek(SeekFrom::Start(self.data_size_offset))?;
+            writer.write_all(&u32::MAX.to_le_bytes())?;
+        } else {
+            writer.seek(SeekFrom::Start(riff_start + 4))?;
+            writer.write_all(&(riff_size as u32).to_le_bytes())?;
+            writer.seek(SeekFrom::Start(self.data_size_offset))?;
+            writer.write_all(&(self.data_bytes as u32).to_le_bytes())?;
+        }
+
+        writer.seek(SeekFrom::Start(end))?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Drop for WavWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.write_sizes();
+    }
+}
+
+/// Producer of a WAVE file, one frame of samples per profile frame.
+///
+/// Frame `n` holds the samples from [`sample_offset`] of `n` up to the
+/// one of `n + 1`, so seeking is sample accurate at any frame rate. The
+/// last frame is padded with silence.
+pub struct WavProducer {
+    state: ProducerState,
+    reader: WavReader<Box<dyn ReadSeek>>,
+}
+
+impl WavProducer {
+    pub fn open(profile: &Profile, path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_reader(profile, BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader(profile: &Profile, reader: impl ReadSeek + 'static) -> Result<Self> {
+        let reader = WavReader::new(Box::new(reader) as Box<dyn ReadSeek>)?;
+        let spec = reader.spec();
+
+        let duration = Ratio::new(reader.len() as i64, spec.sample_rate as i64);
+        let length = -(-duration * profile.frame_rate).floor();
+
+        Ok(Self {
+            state: ProducerState::new(profile, length),
+            reader,
+        })
+    }
+
+    pub fn spec(&self) -> WavSpec {
+        self.reader.spec()
+    }
+}
+
+impl Service for WavProducer {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+}
+
+impl Producer for WavProducer {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, position: i64) -> Result<Frame> {
+        if !(0..self.state.length()).contains(&position) {
+            return Err(ServiceError::OutOfRange(position));
+        }
+
+        let spec = self.reader.spec();
+        let frame_rate = self.state.profile.frame_rate;
+        let start = sample_offset(frame_rate, spec.sample_rate, position) as u64;
+        let count = samples_per_frame(frame_rate, spec.sample_rate, position);
+
+        self.reader.seek(start.min(self.reader.len()))?;
+        let audio = self.reader.read(count)?;
+
+        let audio = if audio.samples() < count {
+            let mut padded = AudioBuffer::new(
+                audio.format(),
+                SampleLayout::Interleaved,
+                spec.sample_rate,
+                spec.channel_layout,
+                count,
+            )?;
+            let len = audio.samples() * spec.channel_count();
+            let copied = match audio.format() {
+                SampleFormat::U8 => copy_into::<u8>(&audio, &mut padded, len),
+                SampleFormat::S16 => copy_into::<i16>(&audio, &mut padded, len),
+                SampleFormat::S32 => copy_into::<i32>(&audio, &mut padded, len),
+                SampleFormat::F32 => copy_into::<f32>(&audio, &mut padded, len),
+            };
+            copied.ok_or_else(|| invalid("sample format mismatch"))?;
+            padded
+        } else {
+            audio
+        };
+
+        let mut frame = base_frame(&self.state.profile);
+        frame.set_audio(audio);
+        Ok(frame.build())
+    }
+}
+
+fn copy_into<T: crate::structures::buffer::BufferElement>(
+    from: &AudioBuffer,
+    to: &mut AudioBuffer,
+    len: usize,
+) -> Option<()> {
+    let from = from.data::<T>()?;
+    to.data_mut::<T>()?[..len].copy_from_slice(&from[..len]);
+    Some(())
+}
+
+/// Consumer writing the audio of frames to a WAVE file.
+///
+/// The file format follows the first audio buffer unless set with the
+/// `format` property, e.g. `Pcm24`. Frames without audio are written as
+/// silence, with the `sample_rate` and `channels` properties when no
+/// audio was seen yet.
+pub struct WavConsumer {
+    profile: Profile,
+    properties: Object,
+    format: Option<WavFormat>,
+    sample_rate: u32,
+    channels: usize,
+    output: Option<Box<dyn WriteSeek>>,
+    writer: Option<WavWriter<Box<dyn WriteSeek>>>,
+}
+
+/// Writers a [`WavConsumer`] can patch the header in.
+pub trait WriteSeek: Write + Seek + Send {}
+
+impl<T: Write + Seek + Send> WriteSeek for T {}
+
+impl WavConsumer {
+    pub fn create(profile: &Profile, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(profile, BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn new(profile: &Profile, output: impl WriteSeek + 'static) -> Self {
+        Self {
+            profile: profile.clone(),
+            properties: Object::default(),
+            format: None,
+            sample_rate: 48000,
+            channels: 2,
+            output: Some(Box::new(output)),
+            writer: None,
+        }
+    }
+
+    fn writer(&mut self, spec: WavSpec) -> Result<&mut WavWriter<Box<dyn WriteSeek>>> {
+        if self.writer.is_none() {
+            let output = self
+                .output
+                .take()
+                .ok_or_else(|| invalid("consumer already stopped"))?;
+            self.writer = Some(WavWriter::new(output, spec)?);
+        }
+        Ok(self.writer.as_mut().expect("writer created"))
+    }
+}
+
+impl Service for WavConsumer {
+    fn properties(&self) -> &Object {
+        &self.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        match key {
+            "format" => self.format = Some(enum_property(key, &value)?),
+            "sample_rate" => {
+                self.sample_rate = u32::try_from(int_property(key, &value)?)
+                    .ok()
+                    .filter(|rate| *rate > 0)
+                    .ok_or_else(|| ServiceError::invalid_property(key, "must be positive"))?;
+            }
+            "channels" => {
+                self.channels = usize::try_from(int_property(key, &value)?)
+                    .ok()
+                    .filter(|count| (1..=u16::MAX as usize).contains(count))
+                    .ok_or_else(|| ServiceError::invalid_property(key, "out of range"))?;
+            }
+            _ => {}
+        }
+
+        self.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Consumer for WavConsumer {
+    fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    fn consume(&mut self, frame: &Frame) -> Result<()> {
+        let spec = match (&self.writer, &frame.audio) {
+            (Some(writer), _) => writer.spec(),
+            (None, Some(audio)) => WavSpec {
+                format: self
+                    .format
+                    .unwrap_or(WavFormat::from_sample_format(audio.format())),
+                sample_rate: audio.sample_rate(),
+                channel_layout: audio.channel_layout(),
+            },
+            (None, None) => WavSpec {
+                format: self.format.unwrap_or_default(),
+                sample_rate: self.sample_rate,
+                channel_layout: ChannelLayout::from_count(self.channels),
+            },
+        };
+
+        let frame_rate = self.profile.frame_rate;
+        let writer = self.writer(spec)?;
+        match &frame.audio {
+            Some(audio) => writer.write(audio),
+            None => {
+                let silence = AudioBuffer::new(
+                    spec.format.sample_format(),
+                    SampleLayout::Interleaved,
+                    spec.sample_rate,
+                    spec.channel_layout,
+                    samples_per_frame(frame_rate, spec.sample_rate, frame.position()),
+                )?;
+                writer.write(&silence)
+            }
+        }
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
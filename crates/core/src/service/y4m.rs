@@ -0,0 +1,760 @@
+//! YUV4MPEG2 streams, as read and written by most encoders.
+//!
+//! The stream header maps to a [`Profile`]: `W`/`H` to the frame, `F` to
+//! the frame rate, `A` to the sample aspect, `I` to the render type and
+//! field order and `C` to the pixel format. Frames are raw planes, 16-bit
+//! samples in little endian.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use super::{
+    Consumer, Producer, ProducerState, Result, Service, ServiceError, enum_property,
+    generator::base_frame,
+};
+use crate::{
+    color::{ColorInfo, ColorRange, ColorSpace},
+    frame::Frame,
+    image::{
+        Image,
+        chroma::{ChromaOptions, ChromaSiting},
+        convert, draw,
+        format::PixelFormat,
+    },
+    profile::{FieldOrder, Profile, ProfileBuilder, Ratio, RenderType},
+    structures::object::{Object, ObjectValues},
+};
+use mcf_math::geometry;
+
+const MAGIC: &str = "YUV4MPEG2";
+/// Longest header line accepted, guards against reading binary data.
+const MAX_LINE: usize = 4096;
+/// Largest width or height accepted, guards against corrupt headers.
+const MAX_DIMENSION: usize = 1 << 16;
+
+/// Scan type of the `I` header field.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Interlace {
+    #[default]
+    Progressive,
+    TopFieldFirst,
+    BottomFieldFirst,
+    /// Signalled per frame, read as top field first.
+    Mixed,
+}
+
+/// Stream header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Y4mHeader {
+    pub width: usize,
+    pub height: usize,
+    pub frame_rate: Ratio,
+    /// `0:0` when unknown.
+    pub sample_aspect: Ratio,
+    pub interlace: Interlace,
+    pub format: PixelFormat,
+    /// `C` tag, e.g. `420mpeg2`, kept for its chroma siting.
+    pub chroma: String,
+    /// `XCOLORRANGE` extension.
+    pub range: Option<ColorRange>,
+}
+
+fn invalid(reason: impl Into<String>) -> ServiceError {
+    ServiceError::InvalidData(reason.into())
+}
+
+fn format_of_chroma(chroma: &str) -> Option<PixelFormat> {
+    Some(match chroma {
+        "420jpeg" | "420paldv" | "420mpeg2" | "420" => PixelFormat::Yuv420p,
+        "422" => PixelFormat::Yuv422p,
+        "444" => PixelFormat::Yuv444p,
+        "mono" => PixelFormat::Gray8,
+        "420p10" => PixelFormat::Yuv420p10,
+        "422p10" => PixelFormat::Yuv422p10,
+        "444p10" => PixelFormat::Yuv444p10,
+        "mono16" => PixelFormat::Gray16,
+        _ => return None,
+    })
+}
+
+/// Chroma siting of the `C` tag `chroma`, centered for plain `420`.
+pub fn siting_of_chroma(chroma: &str) -> ChromaSiting {
+    match chroma {
+        "420mpeg2" => ChromaSiting::Left,
+        "420paldv" => ChromaSiting::TopLeft,
+        _ if chroma.starts_with("420") => ChromaSiting::Center,
+        _ => ChromaSiting::Left,
+    }
+}
+
+/// `C` tag of `format`, `None` when Y4M cannot carry it.
+pub fn chroma_of_format(format: PixelFormat) -> Option<&'static str> {
+    Some(match format {
+        PixelFormat::Yuv420p => "420jpeg",
+        PixelFormat::Yuv422p => "422",
+        PixelFormat::Yuv444p => "444",
+        PixelFormat::Gray8 => "mono",
+        PixelFormat::Yuv420p10 => "420p10",
+        PixelFormat::Yuv422p10 => "422p10",
+        PixelFormat::Yuv444p10 => "444p10",
+        PixelFormat::Gray16 => "mono16",
+        _ => return None,
+    })
+}
+
+fn parse_ratio(value: &str) -> Option<Ratio> {
+    let (number, denominator) = value.split_once(':')?;
+    Some(Ratio {
+        number: number.parse().ok()?,
+        denominator: denominator.parse().ok()?,
+    })
+}
+
+impl Y4mHeader {
+    /// Parse a header line, with or without its newline.
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut fields = line.trim_end_matches('\n').split(' ');
+        if fields.next() != Some(MAGIC) {
+            return Err(invalid("missing YUV4MPEG2 signature"));
+        }
+
+        let mut header = Y4mHeader {
+            width: 0,
+            height: 0,
+            frame_rate: Ratio::default(),
+            sample_aspect: Ratio::new(0, 1),
+            interlace: Interlace::Progressive,
+            format: PixelFormat::Yuv420p,
+            chroma: "420jpeg".to_string(),
+            range: None,
+        };
+
+        for field in fields {
+            // Tags are single characters, unknown ones may be any UTF-8.
+            let Some(tag) = field.chars().next() else {
+                continue;
+            };
+            let value = &field[tag.len_utf8()..];
+            match tag {
+                'W' => header.width = value.parse().map_err(|_| invalid("invalid width"))?,
+                'H' => header.height = value.parse().map_err(|_| invalid("invalid height"))?,
+                'F' => {
+                    header.frame_rate = parse_ratio(value)
+                        .filter(|rate| rate.number > 0 && rate.denominator > 0)
+                        .ok_or_else(|| invalid("invalid frame rate"))?;
+                }
+                'A' => {
+                    header.sample_aspect =
+                        parse_ratio(value).ok_or_else(|| invalid("invalid sample aspect"))?;
+                }
+                'I' => {
+                    header.interlace = match value {
+                        "p" | "?" => Interlace::Progressive,
+                        "t" => Interlace::TopFieldFirst,
+                        "b" => Interlace::BottomFieldFirst,
+                        "m" => Interlace::Mixed,
+                        _ => return Err(invalid(format!("unknown interlacing `{value}`"))),
+                    };
+                }
+                'C' => {
+                    header.format = format_of_chroma(value)
+                        .ok_or_else(|| invalid(format!("unsupported chroma `{value}`")))?;
+                    header.chroma = value.to_string();
+                }
+                'X' => {
+                    if let Some(range) = value.strip_prefix("COLORRANGE=") {
+                        header.range = match range {
+                            "FULL" => Some(ColorRange::Full),
+                            "LIMITED" => Some(ColorRange::Limited),
+                            _ => None,
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if header.width == 0 || header.height == 0 || header.frame_rate.denominator == 0 {
+            return Err(invalid("missing size or frame rate"));
+        }
+        if header.width > MAX_DIMENSION
+            || header.height > MAX_DIMENSION
+            || header.frame_size().is_none()
+        {
+            return Err(invalid(format!(
+                "invalid size {}x{}",
+                header.width, header.height
+            )));
+        }
+        Ok(header)
+    }
+
+    /// Header line including its newline.
+    pub fn to_line(&self) -> String {
+        let interlace = match self.interlace {
+            Interlace::Progressive => 'p',
+            Interlace::TopFieldFirst => 't',
+            Interlace::BottomFieldFirst => 'b',
+            Interlace::Mixed => 'm',
+        };
+
+        let mut line = format!(
+            "{MAGIC} W{} H{} F{}:{} I{interlace} A{}:{} C{}",
+            self.width,
+            self.height,
+            self.frame_rate.number,
+            self.frame_rate.denominator,
+            self.sample_aspect.number,
+            self.sample_aspect.denominator,
+            self.chroma
+        );
+        if let Some(range) = self.range {
+            line.push_str(match range {
+                ColorRange::Limited => " XCOLORRANGE=LIMITED",
+                ColorRange::Full => " XCOLORRANGE=FULL",
+            });
+        }
+        line.push('\n');
+        line
+    }
+
+    /// Header for frames of `profile` in `format`.
+    pub fn from_profile(profile: &Profile, format: PixelFormat) -> Result<Self> {
+        let chroma = chroma_of_format(format)
+            .ok_or_else(|| invalid(format!("{format:?} cannot be stored in Y4M")))?;
+
+        let interlace = match (&profile.render_type, profile.field_order) {
+            (RenderType::Progressive, _) => Interlace::Progressive,
+            (RenderType::Interlace, FieldOrder::TopFieldFirst) => Interlace::TopFieldFirst,
+            (RenderType::Interlace, FieldOrder::BottomFieldFirst) => Interlace::BottomFieldFirst,
+        };
+
+        Ok(Self {
+            width: profile.frame.width as usize,
+            height: profile.frame.height as usize,
+            frame_rate: profile.frame_rate.reduced(),
+            sample_aspect: profile.sample_aspect.reduced(),
+            interlace,
+            format,
+            chroma: chroma.to_string(),
+            range: Some(profile.color.range),
+        })
+    }
+
+    /// Profile of the stream. The matrix is not signalled, BT.709 is
+    /// assumed above 576 lines and BT.601 otherwise.
+    pub fn to_profile(&self) -> Profile {
+        let sample_aspect = match self.sample_aspect {
+            Ratio { number: 0, .. } | Ratio { denominator: 0, .. } => Ratio::new(1, 1),
+            sample_aspect => sample_aspect.reduced(),
+        };
+        let display_aspect = Ratio::new(
+            self.width as i64 * sample_aspect.number,
+            self.height as i64 * sample_aspect.denominator,
+        );
+
+        let mut color = ColorInfo::from(if self.height > 576 {
+            ColorSpace::Bt709
+        } else {
+            ColorSpace::Bt601
+        });
+        if let Some(range) = self.range {
+            color.range = range;
+        }
+
+        let (render_type, field_order) = match self.interlace {
+            Interlace::Progressive => (RenderType::Progressive, FieldOrder::TopFieldFirst),
+            Interlace::TopFieldFirst | Interlace::Mixed => {
+                (RenderType::Interlace, FieldOrder::TopFieldFirst)
+            }
+            Interlace::BottomFieldFirst => (RenderType::Interlace, FieldOrder::BottomFieldFirst),
+        };
+
+        ProfileBuilder::new()
+            .set_frame(geometry::Frame {
+                width: self.width as f32,
+                height: self.height as f32,
+            })
+            .set_frame_rate(self.frame_rate.reduced())
+            .set_sample_aspect(sample_aspect)
+            .set_display_aspect(display_aspect)
+            .set_color(color)
+            .set_render_type(render_type)
+            .set_field_order(field_order)
+            .build()
+    }
+
+    /// Bytes of the planes of one frame, `None` when it overflows.
+    pub fn frame_size(&self) -> Option<usize> {
+        let sample = self.format.sample_type().size();
+        self.format.planes().iter().try_fold(0usize, |size, plane| {
+            plane
+                .width(self.width)
+                .checked_mul(plane.components)?
+                .checked_mul(plane.height(self.height))?
+                .checked_mul(sample)?
+                .checked_add(size)
+        })
+    }
+}
+
+/// Read one `\n` terminated line.
+fn read_line(reader: &mut impl Read) -> Result<Option<String>> {
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+
+    loop {
+        if reader.read(&mut byte)? == 0 {
+            return match line.is_empty() {
+                true => Ok(None),
+                false => Err(invalid("truncated header")),
+            };
+        }
+        if byte[0] == b'\n' {
+            break;
+        }
+        if line.len() >= MAX_LINE {
+            return Err(invalid("header line too long"));
+        }
+        line.push(byte[0]);
+    }
+
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| invalid("header is not text"))
+}
+
+fn read_frame_header(reader: &mut impl Read) -> Result<bool> {
+    match read_line(reader)? {
+        Some(line) if line == "FRAME" || line.starts_with("FRAME ") => Ok(true),
+        Some(_) => Err(invalid("missing FRAME marker")),
+        None => Ok(false),
+    }
+}
+
+fn read_planes(reader: &mut impl Read, header: &Y4mHeader) -> Result<Image> {
+    let mut image = Image::new(header.format, header.width, header.height)?;
+
+    for index in 0..image.planes().len() {
+        if header.format.bit_depth() == 8 {
+            let data = image.plane_data_mut::<u8>(index).expect("8-bit plane");
+            reader.read_exact(data)?;
+        } else {
+            let data = image.plane_data_mut::<u16>(index).expect("16-bit plane");
+            let mut bytes = vec![0u8; data.len() * 2];
+            reader.read_exact(&mut bytes)?;
+            for (sample, bytes) in data.iter_mut().zip(bytes.chunks_exact(2)) {
+                *sample = u16::from_le_bytes([bytes[0], bytes[1]]);
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+/// Sequential reader, works on pipes.
+pub struct Y4mReader<R: Read> {
+    reader: R,
+    header: Y4mHeader,
+}
+
+impl<R: Read> Y4mReader<R> {
+    /// Read the stream header.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let line = read_line(&mut reader)?.ok_or_else(|| invalid("empty stream"))?;
+        let header = Y4mHeader::parse(&line)?;
+        Ok(Self { reader, header })
+    }
+
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+
+    /// Next frame, `None` at the end of the stream.
+    pub fn read_frame(&mut self) -> Result<Option<Image>> {
+        if !read_frame_header(&mut self.reader)? {
+            return Ok(None);
+        }
+        read_planes(&mut self.reader, &self.header).map(Some)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Sequential writer, works on pipes.
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    header: Y4mHeader,
+    started: bool,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// The header is written with the first frame.
+    pub fn new(writer: W, header: Y4mHeader) -> Self {
+        Self {
+            writer,
+            header,
+            started: false,
+        }
+    }
+
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+
+    pub fn write_frame(&mut self, image: &Image) -> Result<()> {
+        let header = &self.header;
+        if image.format() != header.format
+            || image.width() != header.width
+            || image.height() != header.height
+        {
+            return Err(ServiceError::Mismatch(format!(
+                "{:?} {}x{} image in {:?} {}x{} stream",
+                image.format(),
+                image.width(),
+                image.height(),
+                header.format,
+                header.width,
+                header.height
+            )));
+        }
+
+        if !self.started {
+            self.writer.write_all(header.to_line().as_bytes())?;
+            self.started = true;
+        }
+        self.writer.write_all(b"FRAME\n")?;
+
+        for (index, plane) in image.planes().iter().enumerate() {
+            for y in 0..plane.height {
+                if header.format.bit_depth() == 8 {
+                    let row = image.row::<u8>(index, y).expect("8-bit plane");
+                    self.writer.write_all(row)?;
+                } else {
+                    let row = image.row::<u16>(index, y).expect("16-bit plane");
+                    let bytes = row.iter().flat_map(|s| s.to_le_bytes()).collect::<Vec<_>>();
+                    self.writer.write_all(&bytes)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Readers a [`Y4mProducer`] can seek in.
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Producer of a seekable Y4M file, with the profile of its header.
+pub struct Y4mProducer {
+    state: ProducerState,
+    reader: Box<dyn ReadSeek>,
+    header: Y4mHeader,
+    /// Offset of the planes of every frame.
+    index: Vec<u64>,
+}
+
+impl Y4mProducer {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read the header and index the frames. Truncated trailing frames
+    /// are ignored.
+    pub fn from_reader(reader: impl ReadSeek + 'static) -> Result<Self> {
+        let mut reader: Box<dyn ReadSeek> = Box::new(reader);
+        let line = read_line(&mut reader)?.ok_or_else(|| invalid("empty stream"))?;
+        let header = Y4mHeader::parse(&line)?;
+
+        let end = reader.seek(SeekFrom::End(0))?;
+        let mut offset = reader.seek(SeekFrom::Start(line.len() as u64 + 1))?;
+        let frame_size = header
+            .frame_size()
+            .ok_or_else(|| invalid("invalid frame size"))? as u64;
+        let mut index = Vec::new();
+
+        while offset < end {
+            if !read_frame_header(&mut reader)? {
+                break;
+            }
+            let start = reader.stream_position()?;
+            if start.checked_add(frame_size).is_none_or(|next| next > end) {
+                break;
+            }
+            index.push(start);
+            offset = reader.seek(SeekFrom::Start(start + frame_size))?;
+        }
+
+        let profile = header.to_profile();
+        let mut state = ProducerState::new(&profile, index.len() as i64);
+        state
+            .properties
+            .set("y4m.chroma", ObjectValues::String(header.chroma.clone()));
+
+        Ok(Self {
+            state,
+            reader,
+            header,
+            index,
+        })
+    }
+
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+}
+
+impl Service for Y4mProducer {
+    fn properties(&self) -> &Object {
+        &self.state.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.state.properties
+    }
+}
+
+impl Producer for Y4mProducer {
+    fn state(&self) -> &ProducerState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut ProducerState {
+        &mut self.state
+    }
+
+    fn produce(&mut self, position: i64) -> Result<Frame> {
+        let offset = usize::try_from(position)
+            .ok()
+            .and_then(|position| self.index.get(position))
+            .ok_or(ServiceError::OutOfRange(position))?;
+
+        self.reader.seek(SeekFrom::Start(*offset))?;
+        let image = read_planes(&mut self.reader, &self.header)?;
+
+        let mut frame = base_frame(&self.state.profile);
+        frame.set_image(image);
+        Ok(frame.build())
+    }
+}
+
+/// Consumer writing frames to a Y4M file or pipe.
+///
+/// The `format` property selects the pixel format, by default the format
+/// of the first image when Y4M can carry it and YUV 4:2:0 otherwise. The
+/// format is fixed once the header is written. Images of other formats
+/// are converted, chroma
+/// resampled per field for interlaced profiles, with the `chroma_filter`
+/// property and the `chroma_siting` of the incoming images. Frames
+/// without image are written black.
+pub struct Y4mConsumer {
+    profile: Profile,
+    properties: Object,
+    format: Option<PixelFormat>,
+    chroma: ChromaOptions,
+    output: Option<Box<dyn Write + Send>>,
+    writer: Option<Y4mWriter<Box<dyn Write + Send>>>,
+    black: Option<Image>,
+}
+
+impl Y4mConsumer {
+    pub fn create(profile: &Profile, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(profile, BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn new(profile: &Profile, output: impl Write + Send + 'static) -> Self {
+        Self {
+            profile: profile.clone(),
+            properties: Object::default(),
+            format: None,
+            chroma: ChromaOptions::for_profile(profile),
+            output: Some(Box::new(output)),
+            writer: None,
+            black: None,
+        }
+    }
+
+    fn writer(&mut self, format: PixelFormat) -> Result<&mut Y4mWriter<Box<dyn Write + Send>>> {
+        if self.writer.is_none() {
+            let header = Y4mHeader::from_profile(&self.profile, format)?;
+            let output = self
+                .output
+                .take()
+                .ok_or_else(|| invalid("consumer already stopped"))?;
+            self.writer = Some(Y4mWriter::new(output, header));
+        }
+        Ok(self.writer.as_mut().expect("writer created"))
+    }
+}
+
+impl Service for Y4mConsumer {
+    fn properties(&self) -> &Object {
+        &self.properties
+    }
+
+    fn properties_mut(&mut self) -> &mut Object {
+        &mut self.properties
+    }
+
+    fn set_property(&mut self, key: &str, value: ObjectValues) -> Result<()> {
+        if key == "format" {
+            let format = enum_property(key, &value)?;
+            chroma_of_format(format)
+                .ok_or_else(|| ServiceError::invalid_property(key, "not a Y4M format"))?;
+            self.format = Some(format);
+        }
+        match key {
+            "chroma_filter" => self.chroma.filter = enum_property(key, &value)?,
+            "chroma_siting" => self.chroma.source = enum_property(key, &value)?,
+            _ => {}
+        }
+
+        self.properties.set(key, value);
+        Ok(())
+    }
+}
+
+impl Consumer for Y4mConsumer {
+    fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    fn consume(&mut self, frame: &Frame) -> Result<()> {
+        let format = match &self.writer {
+            Some(writer) => writer.header().format,
+            None => self
+                .format
+                .or(frame
+                    .image
+                    .as_ref()
+                    .map(Image::format)
+                    .filter(|format| chroma_of_format(*format).is_some()))
+                .unwrap_or(PixelFormat::Yuv420p),
+        };
+
+        match &frame.image {
+            Some(image) if image.format() != format => {
+                let writer = self.writer(format)?;
+                let options = ChromaOptions {
+                    target: siting_of_chroma(&writer.header().chroma),
+                    ..self.chroma
+                };
+                let color = &self.profile.color;
+                let image = convert::convert_with(image, color, format, color, &options)?;
+                self.writer(format)?.write_frame(&image)
+            }
+            Some(image) => self.writer(format)?.write_frame(image),
+            None => {
+                if self.black.is_none() {
+                    let header = self.writer(format)?.header().clone();
+                    let mut black = Image::new(header.format, header.width, header.height)?;
+                    draw::fill(&mut black, [0.0, 0.0, 0.0, 1.0], &self.profile.color)?;
+                    self.black = Some(black);
+                }
+                let black = self.black.take().expect("black frame");
+                let written = self.writer(format)?.write_frame(&black);
+                self.black = Some(black);
+                written
+            }
+        }
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        match &mut self.writer {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn header_skips_unknown_utf8_tags() {
+        let header =
+            Y4mHeader::parse("YUV4MPEG2 W4 H2 F25:1 \u{e9}x \u{1f600} XCOLORRANGE=FULL\n").unwrap();
+        assert_eq!((header.width, header.height), (4, 2));
+        assert_eq!(header.frame_rate, Ratio::new(25, 1));
+        assert_eq!(header.range, Some(ColorRange::Full));
+    }
+
+    #[test]
+    fn header_rejects_missing_size() {
+        assert!(Y4mHeader::parse("YUV4MPEG2 F25:1 \u{e9}").is_err());
+    }
+
+    #[test]
+    fn header_rejects_huge_size() {
+        assert!(Y4mHeader::parse("YUV4MPEG2 W65537 H2 F25:1").is_err());
+        assert!(Y4mHeader::parse("YUV4MPEG2 W4 H18446744073709551615 F25:1").is_err());
+        assert!(Y4mHeader::parse("YUV4MPEG2 W65536 H65536 F25:1 C444p10").is_ok());
+    }
+
+    /// Output shared with the test after the consumer took it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn consumer_converts_to_first_format() {
+        let profile = ProfileBuilder::new()
+            .set_frame(geometry::Frame {
+                width: 4.0,
+                height: 2.0,
+            })
+            .set_frame_rate(Ratio::new(25, 1))
+            .set_sample_aspect(Ratio::new(1, 1))
+            .set_display_aspect(Ratio::new(2, 1))
+            .set_color(ColorInfo::from(ColorSpace::Bt601))
+            .build();
+        let output = Shared::default();
+        let mut consumer = Y4mConsumer::new(&profile, output.clone());
+
+        for format in [PixelFormat::Rgb24, PixelFormat::Yuv422p] {
+            let mut image = Image::new(format, 4, 2).unwrap();
+            draw::fill(&mut image, [1.0, 1.0, 1.0, 1.0], &profile.color).unwrap();
+            let mut frame = base_frame(&profile);
+            frame.set_image(image);
+            consumer.consume(&frame.build()).unwrap();
+        }
+        consumer.stop().unwrap();
+
+        let data = output.0.lock().unwrap().clone();
+        let mut reader = Y4mReader::new(data.as_slice()).unwrap();
+        assert_eq!(reader.header().format, PixelFormat::Yuv420p);
+        for _ in 0..2 {
+            let image = reader.read_frame().unwrap().unwrap();
+            assert_eq!(image.format(), PixelFormat::Yuv420p);
+            assert!(image.plane_data::<u8>(0).unwrap().iter().all(|&y| y == 235));
+        }
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+}
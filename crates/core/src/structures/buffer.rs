@@ -1,21 +1,361 @@
-use std::mem;
+use std::{ffi::c_void, fmt, mem, ptr::NonNull, slice, sync::Arc};
 
 #[derive(Debug, Clone)]
 #[repr(C)]
 pub enum Buffer {
     OwnedU8(Vec<u8>),
     OwnedI16(Vec<i16>),
+    OwnedU16(Vec<u16>),
     OwnedI32(Vec<i32>),
     OwnedF32(Vec<f32>),
+    /// Reference counted buffer, cloning shares the data.
+    Shared(Arc<Buffer>),
+    /// Memory owned outside of Rust, released when the last clone drops.
+    External(Arc<ExternalBuffer>),
 }
 
+/// Type of the elements stored in a [`Buffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub enum ElementType {
+    U8,
+    I16,
+    U16,
+    I32,
+    F32,
+}
+
+impl ElementType {
+    pub fn size(&self) -> usize {
+        match self {
+            ElementType::U8 => mem::size_of::<u8>(),
+            ElementType::I16 => mem::size_of::<i16>(),
+            ElementType::U16 => mem::size_of::<u16>(),
+            ElementType::I32 => mem::size_of::<i32>(),
+            ElementType::F32 => mem::size_of::<f32>(),
+        }
+    }
+}
+
+/// Called with the data pointer and user data when an external buffer is
+/// released.
+pub type ReleaseCallback = unsafe extern "C" fn(data: *mut c_void, user_data: *mut c_void);
+
+/// Memory not allocated by Rust, e.g. a frame owned by a C decoder.
+pub struct ExternalBuffer {
+    /// Pointer as handed over by the creator, null only when `len` is zero.
+    data: *mut u8,
+    len: usize,
+    element_type: ElementType,
+    release: Option<ReleaseCallback>,
+    user_data: *mut c_void,
+    /// Data may be written in place while the buffer is not shared.
+    writable: bool,
+}
+
+// SAFETY: creators of external buffers guarantee the memory stays valid and
+// unmodified while the buffer lives, see `Buffer::from_external`.
+unsafe impl Send for ExternalBuffer {}
+unsafe impl Sync for ExternalBuffer {}
+
+impl ExternalBuffer {
+    pub fn element_type(&self) -> ElementType {
+        self.element_type
+    }
+
+    /// Length in elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Elements as `T`, empty without touching `data` when `len` is zero.
+    ///
+    /// # Safety
+    ///
+    /// `T` must match `element_type`.
+    unsafe fn elements<T>(&self) -> &[T] {
+        if self.len == 0 {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.data.cast::<T>(), self.len) }
+    }
+
+    /// Mutable elements as `T`, see [`ExternalBuffer::elements`].
+    ///
+    /// # Safety
+    ///
+    /// `T` must match `element_type` and the buffer must be writable and
+    /// not shared.
+    unsafe fn elements_mut<T>(&mut self) -> &mut [T] {
+        if self.len == 0 {
+            return &mut [];
+        }
+        unsafe { slice::from_raw_parts_mut(self.data.cast::<T>(), self.len) }
+    }
+}
+
+impl Drop for ExternalBuffer {
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            unsafe { release(self.data.cast(), self.user_data) };
+        }
+    }
+}
+
+impl fmt::Debug for ExternalBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalBuffer")
+            .field("data", &self.data)
+            .field("len", &self.len)
+            .field("element_type", &self.element_type)
+            .finish()
+    }
+}
+
+/// Element type that can be stored in a [`Buffer`].
+pub trait BufferElement: Copy + Default + 'static {
+    const ELEMENT_TYPE: ElementType;
+
+    fn owned(buffer: &Buffer) -> Option<&Vec<Self>>;
+    fn owned_mut(buffer: &mut Buffer) -> Option<&mut Vec<Self>>;
+    fn into_buffer(values: Vec<Self>) -> Buffer;
+}
+
+macro_rules! buffer_element {
+    ($type:ty, $variant:ident, $element:ident) => {
+        impl BufferElement for $type {
+            const ELEMENT_TYPE: ElementType = ElementType::$element;
+
+            fn owned(buffer: &Buffer) -> Option<&Vec<Self>> {
+                match buffer {
+                    Buffer::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+
+            fn owned_mut(buffer: &mut Buffer) -> Option<&mut Vec<Self>> {
+                match buffer {
+                    Buffer::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+
+            fn into_buffer(values: Vec<Self>) -> Buffer {
+                Buffer::$variant(values)
+            }
+        }
+
+        impl From<Vec<$type>> for Buffer {
+            fn from(values: Vec<$type>) -> Self {
+                Buffer::$variant(values)
+            }
+        }
+    };
+}
+
+buffer_element!(u8, OwnedU8, U8);
+buffer_element!(i16, OwnedI16, I16);
+buffer_element!(u16, OwnedU16, U16);
+buffer_element!(i32, OwnedI32, I32);
+buffer_element!(f32, OwnedF32, F32);
+
 impl Buffer {
+    /// Buffer of `len` zeroed elements of type `T`.
+    pub fn zeroed<T: BufferElement>(len: usize) -> Self {
+        T::into_buffer(vec![T::default(); len])
+    }
+
+    /// Wrap memory owned outside of Rust without copying.
+    ///
+    /// `release` is called once with the original `data` and `user_data`
+    /// when the last clone of the buffer is dropped. Without `release` the
+    /// memory is borrowed.
+    ///
+    /// Returns `None` when `data` is null or misaligned for `element_type`
+    /// while `len` is not zero. A zero length buffer never reads `data`.
+    /// On `None`, `release` is not called and the memory stays with the
+    /// caller.
+    ///
+    /// # Safety
+    ///
+    /// `data` must point to `len` initialized elements of `element_type`
+    /// that stay valid and unmodified until `release` is called, or for the
+    /// whole lifetime of the buffer when it is `None`.
+    pub unsafe fn from_external(
+        data: *mut c_void,
+        len: usize,
+        element_type: ElementType,
+        release: Option<ReleaseCallback>,
+        user_data: *mut c_void,
+    ) -> Option<Self> {
+        let data = data.cast::<u8>();
+        if len > 0 && (data.is_null() || data.align_offset(element_type.size()) != 0) {
+            return None;
+        }
+
+        Some(Buffer::External(Arc::new(ExternalBuffer {
+            data,
+            len,
+            element_type,
+            release,
+            user_data,
+            writable: false,
+        })))
+    }
+
+    /// Wrap memory handed over to the buffer, written in place while the
+    /// buffer is not shared.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Buffer::from_external`], and nothing else may access the
+    /// memory until `release` is called.
+    pub(crate) unsafe fn from_external_writable(
+        data: NonNull<u8>,
+        len: usize,
+        element_type: ElementType,
+        release: ReleaseCallback,
+        user_data: *mut c_void,
+    ) -> Self {
+        Buffer::External(Arc::new(ExternalBuffer {
+            data: data.as_ptr(),
+            len,
+            element_type,
+            release: Some(release),
+            user_data,
+            writable: true,
+        }))
+    }
+
+    /// Borrow `values` without copying.
+    ///
+    /// # Safety
+    ///
+    /// `values` must outlive the buffer and every clone of it.
+    pub unsafe fn from_borrowed<T: BufferElement>(values: &[T]) -> Self {
+        Buffer::External(Arc::new(ExternalBuffer {
+            data: values.as_ptr().cast_mut().cast(),
+            len: values.len(),
+            element_type: T::ELEMENT_TYPE,
+            release: None,
+            user_data: std::ptr::null_mut(),
+            writable: false,
+        }))
+    }
+
+    pub fn element_type(&self) -> ElementType {
+        match self {
+            Buffer::OwnedU8(_) => ElementType::U8,
+            Buffer::OwnedI16(_) => ElementType::I16,
+            Buffer::OwnedU16(_) => ElementType::U16,
+            Buffer::OwnedI32(_) => ElementType::I32,
+            Buffer::OwnedF32(_) => ElementType::F32,
+            Buffer::Shared(inner) => inner.element_type(),
+            Buffer::External(external) => external.element_type,
+        }
+    }
+
+    /// Whether the data is held in a `Vec` of this buffer.
+    pub fn is_owned(&self) -> bool {
+        !matches!(self, Buffer::Shared(_) | Buffer::External(_))
+    }
+
+    /// Whether the data can be written without copying.
+    fn is_unique(&mut self) -> bool {
+        match self {
+            Buffer::Shared(_) => false,
+            Buffer::External(external) => external.writable && Arc::get_mut(external).is_some(),
+            _ => true,
+        }
+    }
+
+    /// Turn into a reference counted buffer, so clones share the data.
+    pub fn into_shared(self) -> Self {
+        match self {
+            Buffer::Shared(_) | Buffer::External(_) => self,
+            owned => Buffer::Shared(Arc::new(owned)),
+        }
+    }
+
+    /// Turn into an owned buffer, copying only when the data is shared
+    /// with other clones or external.
+    pub fn into_owned(self) -> Self {
+        match self {
+            Buffer::Shared(inner) => Arc::try_unwrap(inner)
+                .unwrap_or_else(|inner| (*inner).clone())
+                .into_owned(),
+            Buffer::External(_) => self.to_owned_copy(),
+            owned => owned,
+        }
+    }
+
+    /// Copy-on-write: make the data owned before mutation.
+    ///
+    /// Unshared writable external memory, e.g. from a
+    /// [`BufferPool`](super::pool::BufferPool), is kept as is.
+    pub fn make_owned(&mut self) {
+        if !self.is_unique() {
+            let buffer = mem::replace(self, Buffer::OwnedU8(Vec::new()));
+            *self = buffer.into_owned();
+        }
+    }
+
+    fn to_owned_copy(&self) -> Self {
+        fn copy<T: BufferElement>(buffer: &Buffer) -> Buffer {
+            T::into_buffer(buffer.as_slice::<T>().unwrap_or_default().to_vec())
+        }
+
+        match self.element_type() {
+            ElementType::U8 => copy::<u8>(self),
+            ElementType::I16 => copy::<i16>(self),
+            ElementType::U16 => copy::<u16>(self),
+            ElementType::I32 => copy::<i32>(self),
+            ElementType::F32 => copy::<f32>(self),
+        }
+    }
+
+    /// Elements as `T`, `None` when the buffer holds another type.
+    pub fn as_slice<T: BufferElement>(&self) -> Option<&[T]> {
+        match self {
+            Buffer::Shared(inner) => inner.as_slice(),
+            Buffer::External(external) => {
+                (external.element_type == T::ELEMENT_TYPE).then(|| unsafe { external.elements() })
+            }
+            owned => T::owned(owned).map(Vec::as_slice),
+        }
+    }
+
+    /// Mutable elements as `T`, copying shared or read-only data first.
+    pub fn as_mut_slice<T: BufferElement>(&mut self) -> Option<&mut [T]> {
+        if self.element_type() != T::ELEMENT_TYPE {
+            return None;
+        }
+        self.make_owned();
+        match self {
+            Buffer::External(external) => {
+                Arc::get_mut(external).map(|external| unsafe { external.elements_mut() })
+            }
+            owned => T::owned_mut(owned).map(Vec::as_mut_slice),
+        }
+    }
+
+    pub fn elem_size(&self) -> usize {
+        self.element_type().size()
+    }
+
     pub fn len_elems(&self) -> usize {
         match self {
             Buffer::OwnedU8(v) => v.len(),
             Buffer::OwnedI16(v) => v.len(),
+            Buffer::OwnedU16(v) => v.len(),
             Buffer::OwnedI32(v) => v.len(),
             Buffer::OwnedF32(v) => v.len(),
+            Buffer::Shared(inner) => inner.len_elems(),
+            Buffer::External(external) => external.len,
         }
     }
 
@@ -23,8 +363,11 @@ impl Buffer {
         match self {
             Buffer::OwnedU8(v) => v.len(),
             Buffer::OwnedI16(v) => v.len() * mem::size_of::<i16>(),
+            Buffer::OwnedU16(v) => v.len() * mem::size_of::<u16>(),
             Buffer::OwnedI32(v) => v.len() * mem::size_of::<i32>(),
             Buffer::OwnedF32(v) => v.len() * mem::size_of::<f32>(),
+            Buffer::Shared(inner) => inner.len_bytes(),
+            Buffer::External(external) => external.len * external.element_type.size(),
         }
     }
 
@@ -32,17 +375,182 @@ impl Buffer {
         match self {
             Buffer::OwnedU8(v) => v.as_ptr(),
             Buffer::OwnedI16(v) => v.as_ptr() as *const u8,
+            Buffer::OwnedU16(v) => v.as_ptr() as *const u8,
             Buffer::OwnedI32(v) => v.as_ptr() as *const u8,
             Buffer::OwnedF32(v) => v.as_ptr() as *const u8,
+            Buffer::Shared(inner) => inner.as_ptr(),
+            Buffer::External(external) => external.data,
         }
     }
 
+    /// Mutable pointer to the data, copying shared or read-only data first.
     pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.make_owned();
         match self {
             Buffer::OwnedU8(v) => v.as_mut_ptr(),
             Buffer::OwnedI16(v) => v.as_mut_ptr() as *mut u8,
+            Buffer::OwnedU16(v) => v.as_mut_ptr() as *mut u8,
             Buffer::OwnedI32(v) => v.as_mut_ptr() as *mut u8,
             Buffer::OwnedF32(v) => v.as_mut_ptr() as *mut u8,
+            Buffer::External(external) => external.data,
+            Buffer::Shared(_) => unreachable!("buffer made owned"),
+        }
+    }
+}
+
+#[cfg(feature = "ffi")]
+pub mod ffi {
+    use super::*;
+
+    /// Wrap C memory, `release` is called when the buffer is destroyed.
+    ///
+    /// # Safety
+    ///
+    /// See [`Buffer::from_external`].
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn buffer_from_external(
+        data: *mut c_void,
+        len: usize,
+        element_type: ElementType,
+        release: Option<ReleaseCallback>,
+        user_data: *mut c_void,
+    ) -> *mut Buffer {
+        match unsafe { Buffer::from_external(data, len, element_type, release, user_data) } {
+            Some(buffer) => Box::into_raw(Box::new(buffer)),
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    /// New handle sharing the data of `buffer`.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be null or a live pointer returned by this module, not
+    /// used concurrently from another thread.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn buffer_share(buffer: *mut Buffer) -> *mut Buffer {
+        if buffer.is_null() {
+            return std::ptr::null_mut();
+        }
+
+        unsafe {
+            let shared = mem::replace(&mut *buffer, Buffer::OwnedU8(Vec::new())).into_shared();
+            *buffer = shared.clone();
+            Box::into_raw(Box::new(shared))
+        }
+    }
+
+    /// Destroy a handle, the data is released with its last handle.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be null or a pointer returned by this module that has
+    /// not been destroyed yet.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn buffer_destroy(buffer: *mut Buffer) {
+        if !buffer.is_null() {
+            unsafe {
+                let _ = Box::from_raw(buffer);
+            };
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `buffer` must be null or a live pointer returned by this module. The
+    /// returned data is valid until the handle is destroyed.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn buffer_get_data(buffer: *const Buffer) -> *const u8 {
+        if buffer.is_null() {
+            return std::ptr::null();
+        }
+        unsafe { (*buffer).as_ptr() }
+    }
+
+    /// # Safety
+    ///
+    /// `buffer` must be null or a live pointer returned by this module.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn buffer_get_len_bytes(buffer: *const Buffer) -> usize {
+        if buffer.is_null() {
+            return 0;
+        }
+        unsafe { (*buffer).len_bytes() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn record(data: *mut c_void, _user_data: *mut c_void) {
+        RELEASED.store(data as usize, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn external_rejects_null_data() {
+        let buffer = unsafe {
+            Buffer::from_external(
+                std::ptr::null_mut(),
+                4,
+                ElementType::U8,
+                None,
+                std::ptr::null_mut(),
+            )
+        };
+        assert!(buffer.is_none());
+    }
+
+    #[test]
+    fn external_rejects_misaligned_data() {
+        let values = [0u16; 4];
+        let data = unsafe { values.as_ptr().cast::<u8>().add(1) }.cast_mut();
+        let buffer = unsafe {
+            Buffer::from_external(data.cast(), 2, ElementType::U16, None, std::ptr::null_mut())
+        };
+        assert!(buffer.is_none());
+    }
+
+    #[test]
+    fn external_empty_is_empty_slice() {
+        let mut buffer = unsafe {
+            Buffer::from_external(
+                std::ptr::null_mut(),
+                0,
+                ElementType::F32,
+                None,
+                std::ptr::null_mut(),
+            )
+        }
+        .unwrap();
+        assert_eq!(buffer.as_slice::<f32>(), Some(&[][..]));
+        assert_eq!(buffer.as_mut_slice::<f32>(), Some(&mut [][..]));
+        assert_eq!(buffer.len_bytes(), 0);
+    }
+
+    #[test]
+    fn external_release_gets_original_pointer() {
+        let values = [1u8, 2, 3];
+        let data = values.as_ptr().cast_mut();
+        let buffer = unsafe {
+            Buffer::from_external(
+                data.cast(),
+                3,
+                ElementType::U8,
+                Some(record),
+                std::ptr::null_mut(),
+            )
         }
+        .unwrap();
+        let clone = buffer.clone();
+        assert_eq!(clone.as_slice::<u8>(), Some(&values[..]));
+        drop(buffer);
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 0);
+        drop(clone);
+        assert_eq!(RELEASED.load(Ordering::SeqCst), data as usize);
     }
 }
@@ -1,2 +1,3 @@
 pub mod buffer;
 pub mod object;
+pub mod pool;
@@ -0,0 +1,366 @@
+//! Recycling of aligned buffer memory between frames.
+
+use std::{
+    alloc::{self, Layout},
+    collections::HashMap,
+    ffi::c_void,
+    fmt,
+    ptr::NonNull,
+    sync::{
+        Arc, Mutex, MutexGuard, Weak,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+use super::buffer::{Buffer, ElementType};
+
+/// Block of pool memory, only accessed by the buffer holding it.
+struct Block(NonNull<u8>);
+
+// SAFETY: idle blocks are plain memory owned by the pool.
+unsafe impl Send for Block {}
+
+/// Error returned when a [`BufferPool`] cannot hand out a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolError {
+    /// Handing out `requested` bytes would exceed the memory budget, only
+    /// `available` bytes are left.
+    OverBudget { requested: usize, available: usize },
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolError::OverBudget {
+                requested,
+                available,
+            } => write!(
+                f,
+                "buffer of {requested} bytes exceeds pool budget, {available} bytes available"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {}
+
+/// Counters of a [`BufferPool`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Buffers served from recycled memory.
+    pub hits: u64,
+    /// Buffers needing a new allocation.
+    pub misses: u64,
+    /// Bytes of buffers currently handed out.
+    pub used_bytes: usize,
+    /// Bytes kept for reuse.
+    pub idle_bytes: usize,
+    /// Number of buffers kept for reuse.
+    pub idle_buffers: usize,
+}
+
+struct PoolInner {
+    free: Mutex<HashMap<Layout, Vec<Block>>>,
+    max_idle_bytes: AtomicUsize,
+    max_used_bytes: AtomicUsize,
+    idle_bytes: AtomicUsize,
+    used_bytes: AtomicUsize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl PoolInner {
+    /// Count `size` bytes as handed out, failing when the budget would be
+    /// exceeded.
+    fn reserve(&self, size: usize) -> Result<(), PoolError> {
+        let max = self.max_used_bytes.load(Ordering::Relaxed);
+        self.used_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(size).filter(|&total| total <= max)
+            })
+            .map(|_| ())
+            .map_err(|used| PoolError::OverBudget {
+                requested: size,
+                available: max.saturating_sub(used),
+            })
+    }
+
+    fn free(&self) -> MutexGuard<'_, HashMap<Layout, Vec<Block>>> {
+        self.free
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn recycle(&self, block: Block, layout: Layout) {
+        self.used_bytes.fetch_sub(layout.size(), Ordering::Relaxed);
+
+        let mut free = self.free();
+        let idle = self.idle_bytes.load(Ordering::Relaxed);
+        if idle + layout.size() > self.max_idle_bytes.load(Ordering::Relaxed) {
+            unsafe { alloc::dealloc(block.0.as_ptr(), layout) };
+            return;
+        }
+
+        self.idle_bytes.fetch_add(layout.size(), Ordering::Relaxed);
+        free.entry(layout).or_default().push(block);
+    }
+
+    /// Drop idle blocks until at most `max` bytes are kept.
+    fn trim(&self, max: usize) {
+        let mut free = self.free();
+
+        for (layout, blocks) in free.iter_mut() {
+            while self.idle_bytes.load(Ordering::Relaxed) > max {
+                let Some(block) = blocks.pop() else { break };
+                self.idle_bytes.fetch_sub(layout.size(), Ordering::Relaxed);
+                unsafe { alloc::dealloc(block.0.as_ptr(), *layout) };
+            }
+        }
+        free.retain(|_, blocks| !blocks.is_empty());
+    }
+}
+
+impl Drop for PoolInner {
+    fn drop(&mut self) {
+        self.trim(0);
+    }
+}
+
+/// Buffer handed out by the pool, returned when the buffer drops.
+struct Lease {
+    pool: Weak<PoolInner>,
+    layout: Layout,
+}
+
+unsafe extern "C" fn release(data: *mut c_void, user_data: *mut c_void) {
+    let lease = unsafe { Box::from_raw(user_data.cast::<Lease>()) };
+    let Some(data) = NonNull::new(data.cast::<u8>()) else {
+        return;
+    };
+
+    match lease.pool.upgrade() {
+        Some(pool) => pool.recycle(Block(data), lease.layout),
+        None => unsafe { alloc::dealloc(data.as_ptr(), lease.layout) },
+    }
+}
+
+/// Pool of aligned buffers keyed by byte size and alignment.
+///
+/// Buffers go back to the pool when their last clone drops. Mutating a
+/// shared clone copies the data out of the pool, see [`Buffer::make_owned`].
+/// Cloning the pool shares it.
+///
+/// Two bounds apply: idle memory above `max_idle_bytes` is freed, and
+/// acquiring fails once the bytes handed out would exceed
+/// `max_used_bytes`.
+#[derive(Clone)]
+pub struct BufferPool {
+    inner: Arc<PoolInner>,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BufferPool {
+    /// Pool keeping any amount of idle memory, without a budget.
+    pub fn new() -> Self {
+        Self::with_max_idle_bytes(usize::MAX)
+    }
+
+    /// Pool keeping at most `max_idle_bytes` for reuse, memory returned
+    /// beyond it is freed.
+    pub fn with_max_idle_bytes(max_idle_bytes: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                free: Mutex::new(HashMap::new()),
+                max_idle_bytes: AtomicUsize::new(max_idle_bytes),
+                max_used_bytes: AtomicUsize::new(usize::MAX),
+                idle_bytes: AtomicUsize::new(0),
+                used_bytes: AtomicUsize::new(0),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn max_idle_bytes(&self) -> usize {
+        self.inner.max_idle_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Change the memory bound, freeing idle memory above it.
+    pub fn set_max_idle_bytes(&self, max_idle_bytes: usize) {
+        self.inner
+            .max_idle_bytes
+            .store(max_idle_bytes, Ordering::Relaxed);
+        self.inner.trim(max_idle_bytes);
+    }
+
+    pub fn max_used_bytes(&self) -> usize {
+        self.inner.max_used_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Change the budget of bytes handed out at once. Buffers already out
+    /// are kept, later acquires fail until enough of them are returned.
+    pub fn set_max_used_bytes(&self, max_used_bytes: usize) {
+        self.inner
+            .max_used_bytes
+            .store(max_used_bytes, Ordering::Relaxed);
+    }
+
+    /// Buffer of `len` elements starting at a multiple of `align` bytes.
+    ///
+    /// `align` is raised to the element alignment and the next power of two.
+    /// Recycled memory keeps its previous contents. Fails when the buffer
+    /// does not fit in the remaining budget.
+    pub fn acquire(
+        &self,
+        element_type: ElementType,
+        len: usize,
+        align: usize,
+    ) -> Result<Buffer, PoolError> {
+        let size = len * element_type.size();
+        if size == 0 {
+            return Ok(empty(element_type));
+        }
+
+        let align = align.max(element_type.size()).next_power_of_two();
+        let layout = Layout::from_size_align(size, align).expect("buffer size overflows");
+        self.inner.reserve(size)?;
+
+        let recycled = self.inner.free().get_mut(&layout).and_then(Vec::pop);
+        let block = match recycled {
+            Some(block) => {
+                self.inner.idle_bytes.fetch_sub(size, Ordering::Relaxed);
+                self.inner.hits.fetch_add(1, Ordering::Relaxed);
+                block
+            }
+            None => {
+                self.inner.misses.fetch_add(1, Ordering::Relaxed);
+                let data = unsafe { alloc::alloc_zeroed(layout) };
+                Block(NonNull::new(data).unwrap_or_else(|| alloc::handle_alloc_error(layout)))
+            }
+        };
+
+        let lease = Box::new(Lease {
+            pool: Arc::downgrade(&self.inner),
+            layout,
+        });
+
+        // SAFETY: the block holds `len` initialized elements, as every
+        // element type is valid for any bit pattern, and is only released
+        // through the lease.
+        Ok(unsafe {
+            Buffer::from_external_writable(
+                block.0,
+                len,
+                element_type,
+                release,
+                Box::into_raw(lease).cast(),
+            )
+        })
+    }
+
+    /// Like [`BufferPool::acquire`] with every byte set to zero.
+    pub fn acquire_zeroed(
+        &self,
+        element_type: ElementType,
+        len: usize,
+        align: usize,
+    ) -> Result<Buffer, PoolError> {
+        let mut buffer = self.acquire(element_type, len, align)?;
+        let size = buffer.len_bytes();
+        unsafe { buffer.as_mut_ptr().write_bytes(0, size) };
+        Ok(buffer)
+    }
+
+    /// Free all idle memory.
+    pub fn clear(&self) {
+        self.inner.trim(0);
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let free = self.inner.free();
+
+        PoolStats {
+            hits: self.inner.hits.load(Ordering::Relaxed),
+            misses: self.inner.misses.load(Ordering::Relaxed),
+            used_bytes: self.inner.used_bytes.load(Ordering::Relaxed),
+            idle_bytes: self.inner.idle_bytes.load(Ordering::Relaxed),
+            idle_buffers: free.values().map(Vec::len).sum(),
+        }
+    }
+
+    /// Reset hit and miss counters.
+    pub fn reset_stats(&self) {
+        self.inner.hits.store(0, Ordering::Relaxed);
+        self.inner.misses.store(0, Ordering::Relaxed);
+    }
+}
+
+fn empty(element_type: ElementType) -> Buffer {
+    match element_type {
+        ElementType::U8 => Buffer::zeroed::<u8>(0),
+        ElementType::I16 => Buffer::zeroed::<i16>(0),
+        ElementType::U16 => Buffer::zeroed::<u16>(0),
+        ElementType::I32 => Buffer::zeroed::<i32>(0),
+        ElementType::F32 => Buffer::zeroed::<f32>(0),
+    }
+}
+
+impl std::fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("max_idle_bytes", &self.max_idle_bytes())
+            .field("max_used_bytes", &self.max_used_bytes())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_limits_outstanding_bytes() {
+        let pool = BufferPool::new();
+        pool.set_max_used_bytes(256);
+
+        let first = pool.acquire(ElementType::U8, 128, 32).unwrap();
+        let second = pool.acquire(ElementType::U16, 64, 32).unwrap();
+        assert_eq!(pool.stats().used_bytes, 256);
+        assert_eq!(
+            pool.acquire(ElementType::U8, 1, 1).unwrap_err(),
+            PoolError::OverBudget {
+                requested: 1,
+                available: 0
+            }
+        );
+
+        drop(first);
+        assert_eq!(pool.stats().used_bytes, 128);
+        let third = pool.acquire(ElementType::U8, 128, 32).unwrap();
+        assert_eq!(pool.stats().hits, 1);
+
+        drop((second, third));
+        assert_eq!(pool.stats().used_bytes, 0);
+    }
+
+    #[test]
+    fn shared_clones_count_once() {
+        let pool = BufferPool::new();
+        pool.set_max_used_bytes(64);
+
+        let buffer = pool.acquire_zeroed(ElementType::F32, 16, 64).unwrap();
+        let clone = buffer.clone().into_shared();
+        assert!(pool.acquire(ElementType::U8, 1, 1).is_err());
+
+        drop(buffer);
+        assert!(pool.acquire(ElementType::U8, 1, 1).is_err());
+        drop(clone);
+        assert!(pool.acquire(ElementType::U8, 64, 1).is_ok());
+    }
+}
@@ -0,0 +1,459 @@
+//! Conversion between frame positions, seconds and time strings.
+//!
+//! Supported string formats:
+//! - frames: `1234`
+//! - SMPTE timecode: `HH:MM:SS:FF`, or `HH:MM:SS;FF` for drop-frame
+//! - clock: `HH:MM:SS.mmm`
+//! - milliseconds: `1234ms`
+//!
+//! Drop-frame timecode skips frame labels `00` and `01` (`00`-`03` at 59.94)
+//! at the start of every minute except each tenth, so labels stay in sync
+//! with the wall clock at NTSC rates.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::profile::{Profile, Ratio};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimecodeError {
+    /// Frame rate is zero, negative or has zero denominator.
+    InvalidRate(Ratio),
+    /// Drop-frame is only defined for 29.97 and 59.94 based rates.
+    DropFrameUnsupported(Ratio),
+    /// String does not match any supported format.
+    InvalidFormat(String),
+    /// Timecode field is out of its range.
+    OutOfRange(&'static str),
+    /// Label skipped by drop-frame counting, e.g. `00:01:00;00`.
+    DroppedLabel(Timecode),
+}
+
+impl fmt::Display for TimecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimecodeError::InvalidRate(rate) => write!(f, "invalid frame rate {rate}"),
+            TimecodeError::DropFrameUnsupported(rate) => {
+                write!(f, "drop-frame is not supported at {rate}")
+            }
+            TimecodeError::InvalidFormat(value) => write!(f, "invalid time string `{value}`"),
+            TimecodeError::OutOfRange(field) => write!(f, "`{field}` is out of range"),
+            TimecodeError::DroppedLabel(timecode) => {
+                write!(f, "`{timecode}` does not exist in drop-frame counting")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TimecodeError {}
+
+/// String format used by [`Timebase::format`] and [`Timebase::parse_as`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeFormat {
+    Frames,
+    #[default]
+    Timecode,
+    Clock,
+    Milliseconds,
+}
+
+/// SMPTE timecode label.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Timecode {
+    pub negative: bool,
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub frames: u32,
+    pub drop_frame: bool,
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.negative { "-" } else { "" };
+        let separator = if self.drop_frame { ';' } else { ':' };
+        write!(
+            f,
+            "{sign}{:02}:{:02}:{:02}{separator}{:02}",
+            self.hours, self.minutes, self.seconds, self.frames
+        )
+    }
+}
+
+impl FromStr for Timecode {
+    type Err = TimecodeError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || TimecodeError::InvalidFormat(value.to_string());
+
+        let (negative, rest) = match value.trim().strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value.trim()),
+        };
+
+        let split = rest.rfind([':', ';']).ok_or_else(invalid)?;
+        let drop_frame = rest[split..].starts_with(';');
+
+        let mut fields = rest[..split].split(':');
+        let mut next = || -> Result<u32, TimecodeError> {
+            fields
+                .next()
+                .and_then(|field| field.parse().ok())
+                .ok_or_else(invalid)
+        };
+
+        let timecode = Timecode {
+            negative,
+            hours: next()?,
+            minutes: next()?,
+            seconds: next()?,
+            frames: rest[split + 1..].parse().map_err(|_| invalid())?,
+            drop_frame,
+        };
+
+        if fields.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(timecode)
+    }
+}
+
+/// Frame rate with drop-frame flag used to convert positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Timebase {
+    rate: Ratio,
+    drop_frame: bool,
+}
+
+impl Timebase {
+    pub fn new(rate: Ratio, drop_frame: bool) -> Result<Self, TimecodeError> {
+        if rate.denominator == 0 || rate.reduced().number <= 0 {
+            return Err(TimecodeError::InvalidRate(rate));
+        }
+
+        let timebase = Self {
+            rate: rate.reduced(),
+            drop_frame,
+        };
+
+        if drop_frame && !timebase.supports_drop_frame() {
+            return Err(TimecodeError::DropFrameUnsupported(rate));
+        }
+
+        Ok(timebase)
+    }
+
+    /// Timebase of the profile frame rate. Drop-frame is enabled for the
+    /// rates supporting it.
+    pub fn from_profile(profile: &Profile) -> Result<Self, TimecodeError> {
+        let timebase = Self::new(profile.frame_rate, false)?;
+        Ok(Self {
+            drop_frame: timebase.supports_drop_frame(),
+            ..timebase
+        })
+    }
+
+    pub fn with_drop_frame(self, drop_frame: bool) -> Result<Self, TimecodeError> {
+        Self::new(self.rate, drop_frame)
+    }
+
+    pub fn rate(&self) -> Ratio {
+        self.rate
+    }
+
+    pub fn drop_frame(&self) -> bool {
+        self.drop_frame
+    }
+
+    /// Whole frames per timecode second, e.g. `30` for 29.97.
+    pub fn nominal_fps(&self) -> i64 {
+        self.rate.round().max(1)
+    }
+
+    /// Drop-frame needs a `N * 1000 / 1001` rate with `N` a multiple of 30.
+    pub fn supports_drop_frame(&self) -> bool {
+        self.rate.denominator == 1001
+            && self.rate.number % 1000 == 0
+            && (self.rate.number / 1000) % 30 == 0
+    }
+
+    /// Frame labels skipped each minute in drop-frame counting.
+    fn dropped_per_minute(&self) -> i64 {
+        self.nominal_fps() / 15
+    }
+
+    /// Exact start time of `frames`.
+    pub fn to_seconds(&self, frames: i64) -> Ratio {
+        Ratio::from_integer(frames) / self.rate
+    }
+
+    /// Frame shown at exact time `seconds`.
+    pub fn from_seconds(&self, seconds: Ratio) -> i64 {
+        (seconds * self.rate).floor()
+    }
+
+    pub fn to_seconds_f64(&self, frames: i64) -> f64 {
+        self.to_seconds(frames).to_f64()
+    }
+
+    /// Frame nearest to `seconds`.
+    pub fn from_seconds_f64(&self, seconds: f64) -> i64 {
+        (seconds * self.rate.to_f64()).round() as i64
+    }
+
+    /// Start time of `frames` rounded to milliseconds.
+    pub fn to_millis(&self, frames: i64) -> i64 {
+        (self.to_seconds(frames) * 1000).round()
+    }
+
+    /// Frame nearest to `millis`.
+    pub fn from_millis(&self, millis: i64) -> i64 {
+        (Ratio::new(millis, 1000) * self.rate).round()
+    }
+
+    /// Timecode label of `frames` using the timebase drop-frame flag.
+    pub fn to_timecode(&self, frames: i64) -> Timecode {
+        let fps = self.nominal_fps();
+        let mut count = frames.abs();
+
+        if self.drop_frame {
+            let drop = self.dropped_per_minute();
+            let per_ten_minutes = fps * 600 - drop * 9;
+            let per_minute = fps * 60 - drop;
+
+            let tens = count / per_ten_minutes;
+            let rest = count % per_ten_minutes;
+
+            count += drop * 9 * tens;
+            if rest > drop {
+                count += drop * ((rest - drop) / per_minute);
+            }
+        }
+
+        Timecode {
+            negative: frames < 0,
+            hours: (count / (fps * 3600)) as u32,
+            minutes: (count / (fps * 60) % 60) as u32,
+            seconds: (count / fps % 60) as u32,
+            frames: (count % fps) as u32,
+            drop_frame: self.drop_frame,
+        }
+    }
+
+    /// Frame position of `timecode`, honouring its drop-frame flag.
+    pub fn from_timecode(&self, timecode: &Timecode) -> Result<i64, TimecodeError> {
+        let fps = self.nominal_fps();
+
+        if timecode.minutes >= 60 {
+            return Err(TimecodeError::OutOfRange("minutes"));
+        }
+        if timecode.seconds >= 60 {
+            return Err(TimecodeError::OutOfRange("seconds"));
+        }
+        if timecode.frames as i64 >= fps {
+            return Err(TimecodeError::OutOfRange("frames"));
+        }
+
+        let total_minutes = timecode.hours as i64 * 60 + timecode.minutes as i64;
+        let mut count =
+            (total_minutes * 60 + timecode.seconds as i64) * fps + timecode.frames as i64;
+
+        if timecode.drop_frame {
+            if !self.supports_drop_frame() {
+                return Err(TimecodeError::DropFrameUnsupported(self.rate));
+            }
+
+            let drop = self.dropped_per_minute();
+            if timecode.seconds == 0
+                && !timecode.minutes.is_multiple_of(10)
+                && (timecode.frames as i64) < drop
+            {
+                return Err(TimecodeError::DroppedLabel(*timecode));
+            }
+
+            count -= drop * (total_minutes - total_minutes / 10);
+        }
+
+        Ok(if timecode.negative { -count } else { count })
+    }
+
+    /// Format `frames` as string.
+    pub fn format(&self, frames: i64, format: TimeFormat) -> String {
+        match format {
+            TimeFormat::Frames => frames.to_string(),
+            TimeFormat::Timecode => self.to_timecode(frames).to_string(),
+            TimeFormat::Clock => {
+                let millis = self.to_millis(frames);
+                let sign = if millis < 0 { "-" } else { "" };
+                let millis = millis.abs();
+                format!(
+                    "{sign}{:02}:{:02}:{:02}.{:03}",
+                    millis / 3_600_000,
+                    millis / 60_000 % 60,
+                    millis / 1000 % 60,
+                    millis % 1000
+                )
+            }
+            TimeFormat::Milliseconds => format!("{}ms", self.to_millis(frames)),
+        }
+    }
+
+    /// Parse string in the given format into a frame position.
+    pub fn parse_as(&self, value: &str, format: TimeFormat) -> Result<i64, TimecodeError> {
+        let invalid = || TimecodeError::InvalidFormat(value.to_string());
+        let trimmed = value.trim();
+
+        match format {
+            TimeFormat::Frames => trimmed.parse().map_err(|_| invalid()),
+            TimeFormat::Timecode => self.from_timecode(&trimmed.parse()?),
+            TimeFormat::Clock => {
+                let (negative, rest) = match trimmed.strip_prefix('-') {
+                    Some(rest) => (true, rest),
+                    None => (false, trimmed),
+                };
+
+                let mut millis = 0i64;
+                let mut fields = rest.rsplit(':');
+                let seconds = fields.next().ok_or_else(invalid)?;
+                let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+
+                if fraction.len() > 3 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+                millis += whole.parse::<i64>().map_err(|_| invalid())? * 1000;
+                millis += format!("{fraction:0<3}").parse::<i64>().unwrap_or(0);
+
+                for scale in [60_000, 3_600_000] {
+                    if let Some(field) = fields.next() {
+                        millis += field.parse::<i64>().map_err(|_| invalid())? * scale;
+                    }
+                }
+                if fields.next().is_some() {
+                    return Err(invalid());
+                }
+
+                Ok(self.from_millis(if negative { -millis } else { millis }))
+            }
+            TimeFormat::Milliseconds => trimmed
+                .trim_end_matches("ms")
+                .parse()
+                .map(|millis| self.from_millis(millis))
+                .map_err(|_| invalid()),
+        }
+    }
+
+    /// Parse string detecting its format.
+    pub fn parse(&self, value: &str) -> Result<i64, TimecodeError> {
+        let trimmed = value.trim();
+
+        let format = if trimmed.ends_with("ms") {
+            TimeFormat::Milliseconds
+        } else if trimmed.contains(';') || trimmed.matches(':').count() == 3 {
+            TimeFormat::Timecode
+        } else if trimmed.contains([':', '.']) {
+            TimeFormat::Clock
+        } else {
+            TimeFormat::Frames
+        };
+
+        self.parse_as(trimmed, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames until the `24:00:00:00` label of `timebase`.
+    fn day(timebase: &Timebase) -> i64 {
+        let timecode = Timecode {
+            hours: 24,
+            drop_frame: timebase.drop_frame(),
+            ..Timecode::default()
+        };
+        timebase.from_timecode(&timecode).unwrap()
+    }
+
+    fn round_trip(rate: Ratio, drop_frame: bool) {
+        let timebase = Timebase::new(rate, drop_frame).unwrap();
+        let mut previous = None;
+        for frame in 0..day(&timebase) {
+            let timecode = timebase.to_timecode(frame);
+            assert_eq!(timebase.from_timecode(&timecode), Ok(frame), "{timecode}");
+            assert_ne!(Some(timecode), previous);
+            if frame % 97 == 0 {
+                assert_eq!(timebase.parse(&timecode.to_string()), Ok(frame));
+                assert_eq!(
+                    timebase
+                        .to_timecode(-frame)
+                        .to_string()
+                        .trim_start_matches('-'),
+                    timecode.to_string()
+                );
+            }
+            previous = Some(timecode);
+        }
+    }
+
+    #[test]
+    fn round_trips_non_drop_frame() {
+        round_trip(Ratio::from_integer(25), false);
+        round_trip(Ratio::new(24000, 1001), false);
+        round_trip(Ratio::new(30000, 1001), false);
+    }
+
+    #[test]
+    fn round_trips_drop_frame_2997() {
+        round_trip(Ratio::new(30000, 1001), true);
+    }
+
+    #[test]
+    fn round_trips_drop_frame_5994() {
+        round_trip(Ratio::new(60000, 1001), true);
+    }
+
+    #[test]
+    fn drop_frame_day_is_wall_clock() {
+        let timebase = Timebase::new(Ratio::new(30000, 1001), true).unwrap();
+        assert_eq!(day(&timebase), 2_589_408);
+        let timebase = Timebase::new(Ratio::new(60000, 1001), true).unwrap();
+        assert_eq!(day(&timebase), 5_178_816);
+    }
+
+    #[test]
+    fn drop_frame_boundaries() {
+        let ntsc = Timebase::new(Ratio::new(30000, 1001), true).unwrap();
+        let label = |frame| ntsc.to_timecode(frame).to_string();
+
+        assert_eq!(label(1799), "00:00:59;29");
+        assert_eq!(label(1800), "00:01:00;02");
+        assert_eq!(label(17981), "00:09:59;29");
+        assert_eq!(label(17982), "00:10:00;00");
+        assert_eq!(label(17983), "00:10:00;01");
+        assert_eq!(label(19781), "00:10:59;29");
+        assert_eq!(label(19782), "00:11:00;02");
+
+        for dropped in ["00:01:00;00", "00:01:00;01", "00:19:00;01"] {
+            assert!(matches!(
+                ntsc.parse(dropped),
+                Err(TimecodeError::DroppedLabel(_))
+            ));
+        }
+        assert_eq!(ntsc.parse("00:10:00;00"), Ok(17982));
+
+        let fast = Timebase::new(Ratio::new(60000, 1001), true).unwrap();
+        assert_eq!(fast.to_timecode(3599).to_string(), "00:00:59;59");
+        assert_eq!(fast.to_timecode(3600).to_string(), "00:01:00;04");
+        assert_eq!(fast.to_timecode(35964).to_string(), "00:10:00;00");
+        assert!(fast.parse("00:01:00;03").is_err());
+    }
+
+    #[test]
+    fn rejects_drop_frame_at_integer_rates() {
+        assert!(Timebase::new(Ratio::from_integer(25), true).is_err());
+        let pal = Timebase::new(Ratio::from_integer(25), false).unwrap();
+        assert!(pal.parse("00:00:01;00").is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::rational::Ratio;
+
 pub type Coordinate = f32;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -27,14 +29,39 @@ pub struct Frame {
 
 impl Frame {
     /// Calculate the width scale factor.
-    pub(crate) fn calculate_scale_width(&self, width: Coordinate) -> Coordinate {
+    pub fn calculate_scale_width(&self, width: Coordinate) -> Coordinate {
         width / self.width
     }
 
     /// Get the height scale factor.
-    pub(crate) fn calculate_scale_height(&self, height: Coordinate) -> Coordinate {
+    pub fn calculate_scale_height(&self, height: Coordinate) -> Coordinate {
         height / self.height
     }
+
+    /// Width with square pixels of pixels `sample_aspect` wide, an
+    /// unknown `0:0` or non-positive aspect counting as square.
+    pub fn calculate_display_width(&self, sample_aspect: Ratio) -> Coordinate {
+        match sample_aspect.to_f64() {
+            aspect if aspect > 0.0 => self.width * aspect as Coordinate,
+            _ => self.width,
+        }
+    }
+
+    /// Calculate the width scale factor as displayed, from pixels
+    /// `sample_aspect` wide to `width` pixels `target_aspect` wide. Sample
+    /// aspect does not change the height scale factor.
+    pub fn calculate_display_scale_width(
+        &self,
+        width: Coordinate,
+        sample_aspect: Ratio,
+        target_aspect: Ratio,
+    ) -> Coordinate {
+        let target = Frame {
+            width,
+            height: self.height,
+        };
+        target.calculate_display_width(target_aspect) / self.calculate_display_width(sample_aspect)
+    }
 }
 
 #[cfg(feature = "ffi")]
@@ -272,6 +299,32 @@ mod ffi {
                 }
                 unsafe { (&*frame).calculate_scale_height(height) }
             }
+
+            #[unsafe(no_mangle)]
+            pub extern "C" fn frame_calculate_display_width(
+                frame: *const Frame,
+                sample_aspect: Ratio,
+            ) -> Coordinate {
+                if frame.is_null() {
+                    return 0.0;
+                }
+                unsafe { (&*frame).calculate_display_width(sample_aspect) }
+            }
+
+            #[unsafe(no_mangle)]
+            pub extern "C" fn frame_calculate_display_scale_width(
+                frame: *const Frame,
+                width: Coordinate,
+                sample_aspect: Ratio,
+                target_aspect: Ratio,
+            ) -> Coordinate {
+                if frame.is_null() {
+                    return 0.0;
+                }
+                unsafe {
+                    (&*frame).calculate_display_scale_width(width, sample_aspect, target_aspect)
+                }
+            }
         }
     }
 }
@@ -0,0 +1,414 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Exact rational number, e.g. `30000/1001` frames per second.
+///
+/// Comparison and hashing work on the value, so `2/4 == 1/2`. Ratios with
+/// a zero denominator, e.g. the unset `0/0`, are undefined: they are equal
+/// to each other and order after every defined ratio. Use [`Ratio::new`] to
+/// get a reduced ratio with positive denominator.
+#[derive(Default, Debug, Clone, Copy, Serialize)]
+#[repr(C)]
+pub struct Ratio {
+    pub number: i64,
+    pub denominator: i64,
+}
+
+/// Greatest common divisor, always non-negative.
+pub fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i64
+}
+
+impl Ratio {
+    /// Create reduced ratio.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `denominator` is zero.
+    pub fn new(number: i64, denominator: i64) -> Self {
+        assert!(denominator != 0, "ratio with zero denominator");
+        Self::from_i128(number as i128, denominator as i128)
+    }
+
+    /// Create ratio from integer value.
+    pub const fn from_integer(value: i64) -> Self {
+        Self {
+            number: value,
+            denominator: 1,
+        }
+    }
+
+    fn from_i128(number: i128, denominator: i128) -> Self {
+        let sign = if denominator < 0 { -1 } else { 1 };
+        let divisor = gcd_i128(number, denominator).max(1);
+        let number = sign * number / divisor;
+        let denominator = sign * denominator / divisor;
+
+        Self {
+            number: number.try_into().expect("ratio overflow"),
+            denominator: denominator.try_into().expect("ratio overflow"),
+        }
+    }
+
+    /// Same value with smallest terms and positive denominator.
+    pub fn reduced(&self) -> Self {
+        Self::from_i128(self.number as i128, self.denominator as i128)
+    }
+
+    /// Inverse ratio.
+    pub fn recip(&self) -> Self {
+        Self::new(self.denominator, self.number)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.number == 0 && self.denominator != 0
+    }
+
+    /// Largest integer less than or equal to the value.
+    pub fn floor(&self) -> i64 {
+        let r = self.reduced();
+        r.number.div_euclid(r.denominator)
+    }
+
+    /// Nearest integer, halves rounded away from zero.
+    pub fn round(&self) -> i64 {
+        let r = self.reduced();
+        let twice = 2 * r.number as i128 + r.denominator as i128 * r.number.signum() as i128;
+        (twice / (2 * r.denominator as i128)) as i64
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.number as f64 / self.denominator as f64
+    }
+
+    pub fn to_f32(&self) -> f32 {
+        self.to_f64() as f32
+    }
+
+    /// Best rational approximation of `value` whose denominator does not
+    /// exceed `max_denominator`.
+    ///
+    /// Returns `None` for `NaN`, infinities and out of range values.
+    pub fn approximate(value: f64, max_denominator: i64) -> Option<Self> {
+        if !value.is_finite() || value.abs() >= i64::MAX as f64 || max_denominator < 1 {
+            return None;
+        }
+
+        let x = value.abs();
+        let max = max_denominator as i128;
+        let (mut p0, mut q0, mut p1, mut q1) = (0i128, 1i128, 1i128, 0i128);
+        let mut fraction = x;
+
+        loop {
+            let a = fraction.floor() as i128;
+            let q2 = q0 + a * q1;
+            if q2 > max {
+                break;
+            }
+            (p0, q0, p1, q1) = (p1, q1, p0 + a * p1, q2);
+
+            let rest = fraction - fraction.floor();
+            if rest == 0.0 || (p1 as f64 / q1 as f64) == x {
+                break;
+            }
+            fraction = 1.0 / rest;
+            if fraction >= i64::MAX as f64 {
+                break;
+            }
+        }
+
+        // Semi-convergent is closer when the last partial quotient was cut.
+        let k = (max - q0) / q1;
+        let (sp, sq) = (p0 + k * p1, q0 + k * q1);
+        let (p, q) = if (sp as f64 / sq as f64 - x).abs() < (p1 as f64 / q1 as f64 - x).abs() {
+            (sp, sq)
+        } else {
+            (p1, q1)
+        };
+
+        let p = if value < 0.0 { -p } else { p };
+        Some(Self::from_i128(p, q))
+    }
+
+    /// Convert floating point value, keeping up to six decimal digits.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        Self::approximate(value, 1_000_000)
+    }
+
+    /// Closest rational for rates written as decimals.
+    ///
+    /// Values near the NTSC family `N * 1000 / 1001` (23.976, 29.97, 59.94
+    /// ...) snap to the exact ratio, other values use [`Ratio::approximate`]
+    /// with a denominator up to `1001`.
+    pub fn closest(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        if (value - value.round()).abs() > 1e-6 {
+            let ntsc = value * 1001.0 / 1000.0;
+            if (ntsc - ntsc.round()).abs() < 1e-3 {
+                return Some(Self::new(ntsc.round() as i64 * 1000, 1001));
+            }
+        }
+
+        Self::approximate(value, 1001)
+    }
+}
+
+fn gcd_i128(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i128
+}
+
+impl From<i64> for Ratio {
+    fn from(value: i64) -> Self {
+        Self::from_integer(value)
+    }
+}
+
+impl From<Ratio> for f64 {
+    fn from(value: Ratio) -> Self {
+        value.to_f64()
+    }
+}
+
+impl PartialEq for Ratio {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ratio {}
+
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ratio {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.denominator == 0, other.denominator == 0) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                let (a, b) = (self.reduced(), other.reduced());
+                (a.number as i128 * b.denominator as i128)
+                    .cmp(&(b.number as i128 * a.denominator as i128))
+            }
+        }
+    }
+}
+
+impl Hash for Ratio {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Every undefined ratio hashes as `0/0`.
+        let reduced = match self.denominator {
+            0 => Ratio::default(),
+            _ => self.reduced(),
+        };
+        reduced.number.hash(state);
+        reduced.denominator.hash(state);
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.number, self.denominator)
+    }
+}
+
+impl Mul for Ratio {
+    type Output = Ratio;
+
+    fn mul(self, rhs: Ratio) -> Ratio {
+        Ratio::from_i128(
+            self.number as i128 * rhs.number as i128,
+            self.denominator as i128 * rhs.denominator as i128,
+        )
+    }
+}
+
+impl Div for Ratio {
+    type Output = Ratio;
+
+    fn div(self, rhs: Ratio) -> Ratio {
+        assert!(rhs.number != 0, "division of ratio by zero");
+        Ratio::from_i128(
+            self.number as i128 * rhs.denominator as i128,
+            self.denominator as i128 * rhs.number as i128,
+        )
+    }
+}
+
+impl Add for Ratio {
+    type Output = Ratio;
+
+    fn add(self, rhs: Ratio) -> Ratio {
+        Ratio::from_i128(
+            self.number as i128 * rhs.denominator as i128
+                + rhs.number as i128 * self.denominator as i128,
+            self.denominator as i128 * rhs.denominator as i128,
+        )
+    }
+}
+
+impl Sub for Ratio {
+    type Output = Ratio;
+
+    fn sub(self, rhs: Ratio) -> Ratio {
+        self + -rhs
+    }
+}
+
+impl Neg for Ratio {
+    type Output = Ratio;
+
+    fn neg(self) -> Ratio {
+        Ratio {
+            number: -self.number,
+            denominator: self.denominator,
+        }
+    }
+}
+
+impl Mul<i64> for Ratio {
+    type Output = Ratio;
+
+    fn mul(self, rhs: i64) -> Ratio {
+        self * Ratio::from_integer(rhs)
+    }
+}
+
+impl Div<i64> for Ratio {
+    type Output = Ratio;
+
+    fn div(self, rhs: i64) -> Ratio {
+        self / Ratio::from_integer(rhs)
+    }
+}
+
+/// Accepts integers as well as the old float based fields, so documents
+/// written with `{"number": 29.97, "denominator": 1.0}` still load.
+impl<'de> Deserialize<'de> for Ratio {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct RawRatio {
+            number: f64,
+            denominator: f64,
+        }
+
+        let raw = RawRatio::deserialize(deserializer)?;
+        let integral = |value: f64| value.fract() == 0.0 && value.abs() < i64::MAX as f64;
+
+        if integral(raw.number) && integral(raw.denominator) {
+            return Ok(Ratio {
+                number: raw.number as i64,
+                denominator: raw.denominator as i64,
+            });
+        }
+
+        Ratio::closest(raw.number / raw.denominator)
+            .ok_or_else(|| serde::de::Error::custom("ratio is not a finite number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn compares_by_value() {
+        assert_eq!(Ratio::new(2, 4), Ratio::new(1, 2));
+        assert_eq!(
+            Ratio {
+                number: 2,
+                denominator: -4
+            },
+            Ratio::new(-1, 2)
+        );
+        assert!(Ratio::new(24000, 1001) < Ratio::from_integer(24));
+        assert!(Ratio::new(-1, 2) < Ratio::new(1, 3));
+    }
+
+    #[test]
+    fn zero_denominator_is_undefined() {
+        let unset = Ratio::default();
+        let infinite = Ratio {
+            number: 5,
+            denominator: 0,
+        };
+        assert_ne!(unset, Ratio::new(30000, 1001));
+        assert_ne!(unset, Ratio::from_integer(0));
+        assert_eq!(unset, infinite);
+        assert!(unset > Ratio::from_integer(i64::MAX));
+        assert!(Ratio::from_integer(i64::MIN) < infinite);
+    }
+
+    #[test]
+    fn hash_matches_eq() {
+        let set: HashSet<Ratio> = [
+            Ratio {
+                number: 2,
+                denominator: 4,
+            },
+            Ratio {
+                number: -3,
+                denominator: -6,
+            },
+            Ratio::default(),
+            Ratio {
+                number: 7,
+                denominator: 0,
+            },
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&Ratio::new(1, 2)));
+        assert!(set.contains(&Ratio {
+            number: -1,
+            denominator: 0
+        }));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let ntsc = Ratio::new(30000, 1001);
+        assert_eq!(ntsc * Ratio::new(1001, 1000), Ratio::from_integer(30));
+        assert_eq!(ntsc / ntsc, Ratio::from_integer(1));
+        assert_eq!(Ratio::new(1, 3) + Ratio::new(1, 6), Ratio::new(1, 2));
+        assert_eq!(Ratio::new(1, 3) - Ratio::new(1, 2), Ratio::new(-1, 6));
+        assert_eq!(ntsc.recip(), Ratio::new(1001, 30000));
+        assert_eq!(Ratio::new(-7, 2).floor(), -4);
+        assert_eq!(Ratio::new(-5, 2).round(), -3);
+        assert_eq!(Ratio::new(5, 2).round(), 3);
+    }
+
+    #[test]
+    fn closest_snaps_ntsc_rates() {
+        assert_eq!(Ratio::closest(29.97), Some(Ratio::new(30000, 1001)));
+        assert_eq!(Ratio::closest(23.976), Some(Ratio::new(24000, 1001)));
+        assert_eq!(Ratio::closest(59.94), Some(Ratio::new(60000, 1001)));
+        assert_eq!(Ratio::closest(25.0), Some(Ratio::from_integer(25)));
+        assert_eq!(Ratio::from_f64(0.125), Some(Ratio::new(1, 8)));
+        assert_eq!(Ratio::closest(f64::NAN), None);
+    }
+}